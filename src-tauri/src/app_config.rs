@@ -0,0 +1,218 @@
+use crate::audit;
+use crate::connection::ConnectionSettings;
+use crate::prometheus::PrometheusEndpoint;
+use crate::protection::ProtectionLevel;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+const CONFIG_FILE: &str = "settings.json";
+
+static CONFIG_DIR: OnceLock<PathBuf> = OnceLock::new();
+// serializes read-modify-write cycles of the settings file
+static CONFIG_LOCK: Mutex<()> = Mutex::new(());
+
+// settings the app keeps per context of a kubeconfig, next to what the kubeconfig defines
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ContextSettings {
+    pub prometheus: Option<PrometheusEndpoint>,
+    // the namespace last selected in the UI
+    pub last_namespace: Option<String>,
+    // namespaces to offer when the identity cannot list them
+    pub namespaces: Vec<String>,
+    pub connection: ConnectionSettings,
+    pub protection: ProtectionLevel,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AuditSettings {
+    // the audit log is rotated so it never takes more than this
+    pub retention_bytes: u64,
+}
+
+impl Default for AuditSettings {
+    fn default() -> Self {
+        AuditSettings {
+            retention_bytes: audit::DEFAULT_RETENTION_BYTES,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AppConfig {
+    // by kubeconfig path, then context name; two files may both name a context "prod"
+    pub kubeconfigs: BTreeMap<String, BTreeMap<String, ContextSettings>>,
+    // settings saved before they were keyed by kubeconfig, used until the context is saved again
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub contexts: BTreeMap<String, ContextSettings>,
+    pub audit: AuditSettings,
+}
+
+impl AppConfig {
+    pub fn context(&self, kubeconfig_path: &str, context: &str) -> ContextSettings {
+        self.kubeconfigs
+            .get(kubeconfig_path)
+            .and_then(|contexts| contexts.get(context))
+            .or_else(|| self.contexts.get(context))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn context_mut(&mut self, kubeconfig_path: &str, context: &str) -> &mut ContextSettings {
+        let legacy = self.contexts.get(context).cloned();
+        self.kubeconfigs
+            .entry(kubeconfig_path.to_string())
+            .or_default()
+            .entry(context.to_string())
+            .or_insert_with(|| legacy.unwrap_or_default())
+    }
+}
+
+// remember where settings live, called once from the app setup
+pub fn init(config_dir: PathBuf) {
+    let _ = CONFIG_DIR.set(config_dir);
+}
+
+// a config directory shared by the tests of every module, the directory can only be set once
+#[cfg(test)]
+pub(crate) fn init_for_tests() -> &'static Path {
+    let _ = CONFIG_DIR.set(std::env::temp_dir().join(format!("kubeintel-{}", std::process::id())));
+    CONFIG_DIR.get().unwrap()
+}
+
+// directory for files the app writes, e.g. settings and logs
+pub fn config_dir() -> Result<&'static Path, String> {
+    CONFIG_DIR
+        .get()
+        .map(|dir| dir.as_path())
+        .ok_or_else(|| "App config directory is not initialized".to_string())
+}
+
+// write a file only the current user can read, e.g. a copy of credentials; the mode is
+// set when the file is created, so the content is never readable by others
+pub fn write_private_file(path: &Path, content: &[u8]) -> Result<(), String> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(path)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    // an existing file keeps its mode when opened, tighten it before writing
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))
+            .map_err(|e| e.to_string())?;
+    }
+    std::io::Write::write_all(&mut file, content)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn read_config(path: &Path) -> Result<AppConfig, String> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(AppConfig::default()),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+pub fn load() -> Result<AppConfig, String> {
+    let _guard = CONFIG_LOCK.lock().map_err(|e| e.to_string())?;
+    read_config(&config_dir()?.join(CONFIG_FILE))
+}
+
+// settings of a context, defaults when none were saved
+pub fn context_settings(kubeconfig_path: &str, context: &str) -> ContextSettings {
+    load()
+        .map(|config| config.context(kubeconfig_path, context))
+        .unwrap_or_default()
+}
+
+// change the settings and write them back
+pub fn update<F>(change: F) -> Result<AppConfig, String>
+where
+    F: FnOnce(&mut AppConfig),
+{
+    let _guard = CONFIG_LOCK.lock().map_err(|e| e.to_string())?;
+    let dir = config_dir()?;
+    let path = dir.join(CONFIG_FILE);
+    let mut config = read_config(&path)?;
+    change(&mut config);
+
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+    // write to a temporary file first so a crash never leaves half a file behind
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, content).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp_path, &path).map_err(|e| e.to_string())?;
+    Ok(config)
+}
+
+// change the settings of a context and write them back
+pub fn update_context<F>(
+    kubeconfig_path: &str,
+    context: &str,
+    change: F,
+) -> Result<ContextSettings, String>
+where
+    F: FnOnce(&mut ContextSettings),
+{
+    let config = update(|config| change(config.context_mut(kubeconfig_path, context)))?;
+    Ok(config.context(kubeconfig_path, context))
+}
+
+#[tauri::command]
+pub async fn get_context_settings(
+    kubeconfig_path: String,
+    context: String,
+) -> Result<ContextSettings, String> {
+    Ok(load()?.context(&kubeconfig_path, &context))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contexts_of_different_kubeconfigs_are_kept_apart() {
+        let mut config = AppConfig::default();
+        config
+            .context_mut("/home/me/.kube/staging", "prod")
+            .protection = ProtectionLevel::ReadOnly;
+
+        assert_eq!(
+            config.context("/home/me/.kube/staging", "prod").protection,
+            ProtectionLevel::ReadOnly
+        );
+        assert_eq!(
+            config.context("/home/me/.kube/config", "prod").protection,
+            ProtectionLevel::Unrestricted
+        );
+    }
+
+    #[test]
+    fn settings_saved_by_context_name_are_still_used() {
+        let mut config: AppConfig = serde_json::from_str(
+            r#"{"contexts": {"prod": {"protection": "confirmRequired", "namespaces": ["web"]}}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.context("/home/me/.kube/config", "prod").protection,
+            ProtectionLevel::ConfirmRequired
+        );
+
+        // a change starts from the old settings instead of the defaults
+        config
+            .context_mut("/home/me/.kube/config", "prod")
+            .last_namespace = Some("web".into());
+        let saved = &config.kubeconfigs["/home/me/.kube/config"]["prod"];
+        assert_eq!(saved.protection, ProtectionLevel::ConfirmRequired);
+        assert_eq!(saved.namespaces, vec!["web".to_string()]);
+    }
+}
@@ -0,0 +1,356 @@
+use crate::app_config;
+use crate::protection::{self, Action};
+use k8s_openapi::chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::future::Future;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const AUDIT_FILE: &str = "audit.jsonl";
+// the previous file after a rotation, read together with the current one
+const ROTATED_AUDIT_FILE: &str = "audit.1.jsonl";
+pub const DEFAULT_RETENTION_BYTES: u64 = 10 * 1024 * 1024;
+const DEFAULT_QUERY_LIMIT: usize = 500;
+
+// serializes appends and rotations
+static AUDIT_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum AuditOutcome {
+    Succeeded,
+    Failed,
+    // refused by the protection level of the context
+    Rejected,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    // the OS account running the app
+    pub user: String,
+    pub context: String,
+    pub cluster: Option<String>,
+    pub action: Action,
+    pub namespace: Option<String>,
+    pub kind: Option<String>,
+    pub name: Option<String>,
+    pub params: Value,
+    pub outcome: AuditOutcome,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AuditQuery {
+    pub context: Option<String>,
+    pub action: Option<Action>,
+    pub namespace: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    // matched against kind, name and error
+    pub text: Option<String>,
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        let text = self.text.as_deref().map(str::to_lowercase);
+        self.context.as_ref().is_none_or(|c| *c == entry.context)
+            && self.action.is_none_or(|a| a == entry.action)
+            && self
+                .namespace
+                .as_ref()
+                .is_none_or(|n| entry.namespace.as_ref() == Some(n))
+            && self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp <= until)
+            && text.is_none_or(|text| {
+                [&entry.kind, &entry.name, &entry.error]
+                    .iter()
+                    .any(|field| {
+                        field
+                            .as_ref()
+                            .is_some_and(|f| f.to_lowercase().contains(&text))
+                    })
+            })
+    }
+}
+
+// what a mutating command is about to do, recorded once it finished
+pub struct AuditEvent {
+    kubeconfig_path: String,
+    entry: AuditEntry,
+}
+
+impl AuditEvent {
+    pub fn new(
+        kubeconfig_path: &str,
+        context: &str,
+        action: Action,
+        namespace: Option<&str>,
+        kind: Option<&str>,
+        name: Option<&str>,
+        params: Value,
+    ) -> Self {
+        AuditEvent {
+            kubeconfig_path: kubeconfig_path.to_string(),
+            entry: AuditEntry {
+                timestamp: Utc::now(),
+                user: os_user(),
+                context: context.to_string(),
+                cluster: cluster_server(kubeconfig_path, context),
+                action,
+                namespace: namespace.filter(|n| !n.is_empty()).map(str::to_string),
+                kind: kind.map(str::to_string),
+                name: name.map(str::to_string),
+                params,
+                outcome: AuditOutcome::Succeeded,
+                error: None,
+            },
+        }
+    }
+
+    // check the protection level, run the change and record how it went
+    pub async fn run<T, F>(self, confirmation: Option<&str>, change: F) -> Result<T, String>
+    where
+        F: Future<Output = Result<T, String>>,
+    {
+        if let Err(e) = protection::authorize(
+            &self.kubeconfig_path,
+            &self.entry.context,
+            self.entry.action,
+            confirmation,
+        ) {
+            self.finish(AuditOutcome::Rejected, Some(&e));
+            return Err(e);
+        }
+        let result = change.await;
+        self.record(&result);
+        result
+    }
+
+    pub fn record<T>(self, result: &Result<T, String>) {
+        match result {
+            Ok(_) => self.finish(AuditOutcome::Succeeded, None),
+            Err(e) => self.finish(AuditOutcome::Failed, Some(e)),
+        }
+    }
+
+    // a failure to write the log never fails the command itself
+    fn finish(mut self, outcome: AuditOutcome, error: Option<&String>) {
+        self.entry.outcome = outcome;
+        self.entry.error = error.cloned();
+        let _ = append(&self.entry);
+    }
+}
+
+fn os_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+fn cluster_server(kubeconfig_path: &str, context: &str) -> Option<String> {
+    let kubeconfig = crate::k8s_config::load_kubeconfig(kubeconfig_path).ok()?;
+    let cluster = kubeconfig
+        .contexts
+        .iter()
+        .find(|c| c.name == context)?
+        .context
+        .as_ref()?
+        .cluster
+        .clone();
+    kubeconfig
+        .clusters
+        .into_iter()
+        .find(|c| c.name == cluster)?
+        .cluster?
+        .server
+}
+
+fn audit_paths() -> Result<(PathBuf, PathBuf), String> {
+    let dir = app_config::config_dir()?;
+    Ok((dir.join(AUDIT_FILE), dir.join(ROTATED_AUDIT_FILE)))
+}
+
+// half of the retention goes to each file, so rotating never drops more than the older half
+fn append(entry: &AuditEntry) -> Result<(), String> {
+    let _guard = AUDIT_LOCK.lock().map_err(|e| e.to_string())?;
+    let (path, rotated) = audit_paths()?;
+    let retention = app_config::load()
+        .map(|config| config.audit.retention_bytes)
+        .unwrap_or(DEFAULT_RETENTION_BYTES);
+    let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    if size > retention / 2 {
+        std::fs::rename(&path, &rotated).map_err(|e| e.to_string())?;
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let mut line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+    line.push('\n');
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| e.to_string())?;
+    file.write_all(line.as_bytes()).map_err(|e| e.to_string())
+}
+
+fn read_entries(path: &Path, entries: &mut Vec<AuditEntry>) -> Result<(), String> {
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    // a line cut short by a crash is skipped rather than failing the whole query
+    for line in std::io::BufReader::new(file).lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        if let Ok(entry) = serde_json::from_str(&line) {
+            entries.push(entry);
+        }
+    }
+    Ok(())
+}
+
+// matching entries, newest first
+fn query(filter: &AuditQuery) -> Result<Vec<AuditEntry>, String> {
+    let _guard = AUDIT_LOCK.lock().map_err(|e| e.to_string())?;
+    let (path, rotated) = audit_paths()?;
+    let mut entries = vec![];
+    read_entries(&rotated, &mut entries)?;
+    read_entries(&path, &mut entries)?;
+    Ok(entries
+        .into_iter()
+        .rev()
+        .filter(|entry| filter.matches(entry))
+        .take(filter.limit.unwrap_or(DEFAULT_QUERY_LIMIT))
+        .collect())
+}
+
+#[tauri::command]
+pub async fn query_audit_log(filter: Option<AuditQuery>) -> Result<Vec<AuditEntry>, String> {
+    query(&filter.unwrap_or_default())
+}
+
+// write the matching entries, oldest first, as JSON Lines to a file chosen by the user
+#[tauri::command]
+pub async fn export_audit_log(path: String, filter: Option<AuditQuery>) -> Result<usize, String> {
+    let filter = filter.unwrap_or_default();
+    let filter = AuditQuery {
+        limit: filter.limit.or(Some(usize::MAX)),
+        ..filter
+    };
+    let entries = query(&filter)?;
+    let mut content = String::new();
+    for entry in entries.iter().rev() {
+        content.push_str(&serde_json::to_string(entry).map_err(|e| e.to_string())?);
+        content.push('\n');
+    }
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(entries.len())
+}
+
+#[tauri::command]
+pub async fn set_audit_retention(retention_bytes: u64) -> Result<u64, String> {
+    // keep at least enough room for a few hundred entries
+    let retention_bytes = retention_bytes.max(64 * 1024);
+    let config = app_config::update(|config| config.audit.retention_bytes = retention_bytes)?;
+    Ok(config.audit.retention_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::chrono::TimeZone;
+
+    fn entry() -> AuditEntry {
+        AuditEntry {
+            timestamp: Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap(),
+            user: "me".to_string(),
+            context: "prod".to_string(),
+            cluster: Some("https://prod.example.com".to_string()),
+            action: Action::Delete,
+            namespace: Some("web".to_string()),
+            kind: Some("Deployment".to_string()),
+            name: Some("frontend".to_string()),
+            params: Value::Null,
+            outcome: AuditOutcome::Failed,
+            error: Some("Forbidden".to_string()),
+        }
+    }
+
+    #[test]
+    fn empty_query_matches_everything() {
+        assert!(AuditQuery::default().matches(&entry()));
+    }
+
+    #[test]
+    fn every_filter_has_to_match() {
+        let query = AuditQuery {
+            context: Some("prod".to_string()),
+            action: Some(Action::Delete),
+            namespace: Some("web".to_string()),
+            ..Default::default()
+        };
+        assert!(query.matches(&entry()));
+
+        let other_context = AuditQuery {
+            context: Some("staging".to_string()),
+            ..Default::default()
+        };
+        assert!(!other_context.matches(&entry()));
+        let other_action = AuditQuery {
+            action: Some(Action::Scale),
+            ..Default::default()
+        };
+        assert!(!other_action.matches(&entry()));
+        let cluster_scoped = AuditEntry {
+            namespace: None,
+            ..entry()
+        };
+        let namespaced = AuditQuery {
+            namespace: Some("web".to_string()),
+            ..Default::default()
+        };
+        assert!(!namespaced.matches(&cluster_scoped));
+    }
+
+    #[test]
+    fn time_range_includes_its_bounds() {
+        let at = entry().timestamp;
+        let query = AuditQuery {
+            since: Some(at),
+            until: Some(at),
+            ..Default::default()
+        };
+        assert!(query.matches(&entry()));
+        let later = AuditQuery {
+            since: Some(at + k8s_openapi::chrono::Duration::seconds(1)),
+            ..Default::default()
+        };
+        assert!(!later.matches(&entry()));
+        let earlier = AuditQuery {
+            until: Some(at - k8s_openapi::chrono::Duration::seconds(1)),
+            ..Default::default()
+        };
+        assert!(!earlier.matches(&entry()));
+    }
+
+    #[test]
+    fn text_searches_kind_name_and_error_ignoring_case() {
+        let text = |text: &str| AuditQuery {
+            text: Some(text.to_string()),
+            ..Default::default()
+        };
+        assert!(text("deployment").matches(&entry()));
+        assert!(text("FRONT").matches(&entry()));
+        assert!(text("forbidden").matches(&entry()));
+        // the context and the user are filtered separately
+        assert!(!text("prod").matches(&entry()));
+    }
+}
@@ -0,0 +1,484 @@
+use crate::k8s_client;
+use crate::k8s_config::load_kubeconfig;
+use base64::Engine;
+use k8s_openapi::chrono::{DateTime, Utc};
+use kube::config::{AuthInfo, ExecConfig};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+const REDACTED: &str = "<redacted>";
+// auth-provider config keys that hold credentials
+const SECRET_PROVIDER_KEYS: [&str; 5] = [
+    "id-token",
+    "refresh-token",
+    "access-token",
+    "client-secret",
+    "idp-certificate-authority-data",
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum AuthMethod {
+    Token,
+    TokenFile,
+    ClientCertificate,
+    Basic,
+    Exec,
+    Oidc,
+    AuthProvider,
+    None,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthSummary {
+    pub methods: Vec<AuthMethod>,
+    pub username: Option<String>,
+    pub impersonate: Option<String>,
+    // the exec plugin or auth provider in use
+    pub plugin: Option<String>,
+    pub plugin_args: Vec<String>,
+    // expiry of a static or OIDC token when it is a JWT, RFC 3339
+    pub token_expiry: Option<String>,
+    // the command that logs in again when the plugin needs an interactive login
+    pub login_command: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginStatus {
+    pub authenticated: bool,
+    pub error: Option<String>,
+    pub login_command: Option<String>,
+}
+
+// flags like --client-secret or --token whose value is a credential
+fn is_secret_flag(flag: &str) -> bool {
+    let name = flag.trim_start_matches('-').to_lowercase();
+    ["secret", "token", "password", "key"]
+        .iter()
+        .any(|word| name.contains(word))
+}
+
+// plugin args with the values of secret flags replaced, in both --flag=value and --flag value form
+fn redacted_args(args: &[String]) -> Vec<String> {
+    let mut redacted = Vec::with_capacity(args.len());
+    let mut hide_next = false;
+    for arg in args {
+        let is_flag = arg.starts_with('-');
+        if hide_next && !is_flag {
+            redacted.push(REDACTED.to_string());
+            hide_next = false;
+            continue;
+        }
+        hide_next = false;
+        match arg.split_once('=') {
+            Some((flag, _)) if is_flag && is_secret_flag(flag) => {
+                redacted.push(format!("{}={}", flag, REDACTED))
+            }
+            Some(_) => redacted.push(arg.clone()),
+            None => {
+                hide_next = is_flag && is_secret_flag(arg);
+                redacted.push(arg.clone());
+            }
+        }
+    }
+    redacted
+}
+
+// the AuthInfo as JSON with tokens, passwords, keys, plugin env values and secret args replaced
+pub(crate) fn redacted_auth_info(auth_info: &AuthInfo) -> Result<Value, String> {
+    let mut value = serde_json::to_value(auth_info).map_err(|e| e.to_string())?;
+    let Some(object) = value.as_object_mut() else {
+        return Ok(value);
+    };
+    for key in ["token", "password", "client-key-data"] {
+        if let Some(field) = object.get_mut(key).filter(|f| !f.is_null()) {
+            *field = Value::String(REDACTED.to_string());
+        }
+    }
+    if let Some(config) = object
+        .get_mut("auth-provider")
+        .and_then(|p| p.get_mut("config"))
+        .and_then(|c| c.as_object_mut())
+    {
+        for key in SECRET_PROVIDER_KEYS {
+            if let Some(field) = config.get_mut(key) {
+                *field = Value::String(REDACTED.to_string());
+            }
+        }
+    }
+    if let (Some(exec), Some(config)) = (object.get_mut("exec"), auth_info.exec.as_ref()) {
+        if let Some(field) = exec.get_mut("args").filter(|a| !a.is_null()) {
+            let args = redacted_args(config.args.as_deref().unwrap_or_default());
+            *field = serde_json::to_value(args).map_err(|e| e.to_string())?;
+        }
+    }
+    if let Some(env) = object
+        .get_mut("exec")
+        .and_then(|e| e.get_mut("env"))
+        .and_then(|e| e.as_array_mut())
+    {
+        for var in env.iter_mut().filter_map(|v| v.as_object_mut()) {
+            if let Some(field) = var.get_mut("value") {
+                *field = Value::String(REDACTED.to_string());
+            }
+        }
+    }
+    Ok(value)
+}
+
+// expiry of a JWT, without verifying it
+fn jwt_expiry(token: &str) -> Option<String> {
+    let payload = token.split('.').nth(1)?;
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .ok()?;
+    let claims: Value = serde_json::from_slice(&bytes).ok()?;
+    let exp = claims.get("exp")?.as_i64()?;
+    DateTime::<Utc>::from_timestamp(exp, 0).map(|t| t.to_rfc3339())
+}
+
+fn flag_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter().enumerate().find_map(|(i, arg)| {
+        arg.strip_prefix(&format!("{}=", flag))
+            .map(|v| v.to_string())
+            .or_else(|| (arg == flag).then(|| args.get(i + 1).cloned()).flatten())
+    })
+}
+
+// how to log in again for the exec plugins that need an interactive login;
+// secret args are redacted, the hint ends up in error messages
+pub(crate) fn exec_login_command(exec: &ExecConfig) -> Option<String> {
+    let command = exec.command.as_deref()?;
+    let args = exec.args.clone().unwrap_or_default();
+    let binary = std::path::Path::new(command)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let env_value = |name: &str| {
+        exec.env
+            .iter()
+            .flatten()
+            .find(|var| var.get("name").map(|n| n.as_str()) == Some(name))
+            .and_then(|var| var.get("value").cloned())
+    };
+
+    match binary.as_str() {
+        "gke-gcloud-auth-plugin" => Some("gcloud auth login".to_string()),
+        "aws" | "aws-iam-authenticator" => {
+            let profile = flag_value(&args, "--profile").or_else(|| env_value("AWS_PROFILE"));
+            Some(match profile {
+                Some(profile) => format!("aws sso login --profile {}", profile),
+                None => "aws sso login".to_string(),
+            })
+        }
+        // Azure kubelogin, devicecode and interactive modes log in through the browser
+        "kubelogin" => {
+            if flag_value(&args, "--login").as_deref() == Some("azurecli") {
+                Some("az login".to_string())
+            } else {
+                Some(format!("{} {}", command, redacted_args(&args).join(" ")))
+            }
+        }
+        // int128/kubelogin installed as a kubectl plugin
+        "kubectl" | "kubectl-oidc_login" => {
+            Some(format!("{} {}", command, redacted_args(&args).join(" ")))
+        }
+        _ => None,
+    }
+}
+
+pub(crate) fn auth_summary(auth_info: &AuthInfo) -> AuthSummary {
+    let mut methods = vec![];
+    let mut token_expiry = None;
+    if auth_info.token.is_some() {
+        methods.push(AuthMethod::Token);
+        // the token is only readable through its serialized form
+        token_expiry = serde_json::to_value(auth_info)
+            .ok()
+            .and_then(|v| v.get("token")?.as_str().and_then(jwt_expiry));
+    }
+    if auth_info.token_file.is_some() {
+        methods.push(AuthMethod::TokenFile);
+    }
+    if auth_info.client_certificate.is_some() || auth_info.client_certificate_data.is_some() {
+        methods.push(AuthMethod::ClientCertificate);
+    }
+    if auth_info.username.is_some() && auth_info.password.is_some() {
+        methods.push(AuthMethod::Basic);
+    }
+
+    let mut plugin = None;
+    let mut plugin_args = vec![];
+    let mut login_command = None;
+    if let Some(exec) = &auth_info.exec {
+        methods.push(AuthMethod::Exec);
+        plugin = exec.command.clone();
+        plugin_args = redacted_args(exec.args.as_deref().unwrap_or_default());
+        login_command = exec_login_command(exec);
+    }
+    if let Some(provider) = &auth_info.auth_provider {
+        if provider.name == "oidc" {
+            methods.push(AuthMethod::Oidc);
+            token_expiry = provider
+                .config
+                .get("id-token")
+                .and_then(|token| jwt_expiry(token));
+        } else {
+            methods.push(AuthMethod::AuthProvider);
+            // gcp and azure providers were removed from kubectl in favour of exec plugins
+            login_command = match provider.name.as_str() {
+                "gcp" => Some("gcloud auth login".to_string()),
+                "azure" => Some("az login".to_string()),
+                _ => None,
+            };
+        }
+        plugin = Some(provider.name.clone());
+    }
+    if methods.is_empty() {
+        methods.push(AuthMethod::None);
+    }
+
+    AuthSummary {
+        methods,
+        username: auth_info.username.clone(),
+        impersonate: auth_info.impersonate.clone(),
+        plugin,
+        plugin_args,
+        token_expiry,
+        login_command,
+    }
+}
+
+fn context_auth_info(kubeconfig_path: &str, context: &str) -> Result<AuthInfo, String> {
+    let kubeconfig = load_kubeconfig(kubeconfig_path)?;
+    let user = kubeconfig
+        .contexts
+        .iter()
+        .find(|c| c.name == context)
+        .and_then(|c| c.context.as_ref())
+        .ok_or_else(|| format!("Context {} not found", context))?
+        .user
+        .clone()
+        .unwrap_or_default();
+    Ok(kubeconfig
+        .auth_infos
+        .into_iter()
+        .find(|a| a.name == user)
+        .and_then(|a| a.auth_info)
+        .unwrap_or_default())
+}
+
+// a clearer message for failures of exec plugins and auth providers
+pub(crate) fn auth_error_message(error: &kube::Error, login_command: Option<&str>) -> String {
+    match (error, login_command) {
+        (kube::Error::Auth(e), Some(login)) => format!(
+            "Authentication failed: {}. Your login may have expired, run `{}` in a terminal and retry",
+            e, login
+        ),
+        (kube::Error::Auth(e), None) => format!("Authentication failed: {}", e),
+        (e, _) => e.to_string(),
+    }
+}
+
+// the authentication setup of a context without any secrets
+#[tauri::command]
+pub async fn cluster_auth_summary(
+    kubeconfig_path: String,
+    context: String,
+) -> Result<AuthSummary, String> {
+    Ok(auth_summary(&context_auth_info(
+        &kubeconfig_path,
+        &context,
+    )?))
+}
+
+// authenticate against the context again, e.g. after logging in with the suggested command
+#[tauri::command]
+pub async fn retry_context_login(
+    kubeconfig_path: String,
+    context: String,
+) -> Result<LoginStatus, String> {
+    let login_command = auth_summary(&context_auth_info(&kubeconfig_path, &context)?).login_command;
+    // a new client runs the exec plugin again instead of reusing a cached credential
+    let client = match k8s_client::create_k8s_client(kubeconfig_path, context).await {
+        Ok(client) => client,
+        Err(e) => {
+            return Ok(LoginStatus {
+                authenticated: false,
+                error: Some(e),
+                login_command,
+            })
+        }
+    };
+    Ok(match client.apiserver_version().await {
+        Ok(_) => LoginStatus {
+            authenticated: true,
+            error: None,
+            login_command: None,
+        },
+        Err(e) => LoginStatus {
+            authenticated: false,
+            error: Some(auth_error_message(&e, login_command.as_deref())),
+            login_command: matches!(e, kube::Error::Auth(_))
+                .then_some(login_command)
+                .flatten(),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth_info(yaml: &str) -> AuthInfo {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn redacts_every_credential() {
+        let info = auth_info(
+            r#"
+token: secret-token
+username: admin
+password: hunter2
+client-certificate-data: Y2VydA==
+client-key-data: a2V5
+auth-provider:
+  name: oidc
+  config:
+    client-id: kubernetes
+    client-secret: oidc-secret
+    id-token: header.payload.signature
+    refresh-token: refresh-secret
+exec:
+  apiVersion: client.authentication.k8s.io/v1
+  command: aws
+  args: ["eks", "get-token"]
+  env:
+  - name: AWS_SECRET_ACCESS_KEY
+    value: aws-secret
+"#,
+        );
+        let redacted = redacted_auth_info(&info).unwrap();
+        let text = redacted.to_string();
+        for secret in [
+            "secret-token",
+            "hunter2",
+            "a2V5",
+            "oidc-secret",
+            "header.payload.signature",
+            "refresh-secret",
+            "aws-secret",
+        ] {
+            assert!(!text.contains(secret), "{} leaked in {}", secret, text);
+        }
+        // what identifies the setup stays visible
+        assert_eq!(redacted["username"], "admin");
+        assert_eq!(redacted["client-certificate-data"], "Y2VydA==");
+        assert_eq!(
+            redacted["auth-provider"]["config"]["client-id"],
+            "kubernetes"
+        );
+        assert_eq!(redacted["exec"]["env"][0]["name"], "AWS_SECRET_ACCESS_KEY");
+        assert_eq!(redacted["exec"]["command"], "aws");
+    }
+
+    #[test]
+    fn redacts_secret_plugin_args() {
+        let info = auth_info(
+            r#"
+exec:
+  apiVersion: client.authentication.k8s.io/v1
+  command: kubectl
+  args:
+  - oidc-login
+  - get-token
+  - --oidc-issuer-url=https://issuer.example.com
+  - --oidc-client-id=kubernetes
+  - --oidc-client-secret=oidc-secret
+  - --token
+  - static-token
+  - --password=hunter2
+  - --skip-open-browser
+"#,
+        );
+        let summary = auth_summary(&info);
+        let login = summary.login_command.unwrap();
+        let redacted = redacted_auth_info(&info).unwrap().to_string();
+        for text in [summary.plugin_args.join(" "), login.clone(), redacted] {
+            for secret in ["oidc-secret", "static-token", "hunter2"] {
+                assert!(!text.contains(secret), "{} leaked in {}", secret, text);
+            }
+        }
+        assert_eq!(
+            login,
+            "kubectl oidc-login get-token --oidc-issuer-url=https://issuer.example.com \
+             --oidc-client-id=kubernetes --oidc-client-secret=<redacted> --token <redacted> \
+             --password=<redacted> --skip-open-browser"
+        );
+    }
+
+    #[test]
+    fn leaves_missing_fields_missing() {
+        let redacted = redacted_auth_info(&auth_info("username: admin")).unwrap();
+        assert!(redacted.get("token").is_none_or(|t| t.is_null()));
+        assert!(redacted.get("password").is_none_or(|p| p.is_null()));
+    }
+
+    #[test]
+    fn reads_jwt_expiry() {
+        let payload =
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(r#"{"exp":1700000000}"#);
+        assert_eq!(
+            jwt_expiry(&format!("eyJhbGciOiJub25lIn0.{}.sig", payload)).as_deref(),
+            Some("2023-11-14T22:13:20+00:00")
+        );
+        assert_eq!(jwt_expiry("not-a-jwt"), None);
+        assert_eq!(jwt_expiry("a.b.c"), None);
+    }
+
+    fn exec(command: &str, args: &[&str], env: &[(&str, &str)]) -> ExecConfig {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        let env: Vec<String> = env
+            .iter()
+            .map(|(name, value)| format!("{{name: {}, value: {}}}", name, value))
+            .collect();
+        serde_yaml::from_str(&format!(
+            "{{apiVersion: client.authentication.k8s.io/v1, command: {}, args: {:?}, env: [{}]}}",
+            command,
+            args,
+            env.join(", ")
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn suggests_login_commands() {
+        assert_eq!(
+            exec_login_command(&exec("gke-gcloud-auth-plugin", &[], &[])).as_deref(),
+            Some("gcloud auth login")
+        );
+        assert_eq!(
+            exec_login_command(&exec("aws", &["eks", "get-token", "--profile", "dev"], &[]))
+                .as_deref(),
+            Some("aws sso login --profile dev")
+        );
+        assert_eq!(
+            exec_login_command(&exec(
+                "/usr/local/bin/aws",
+                &["eks"],
+                &[("AWS_PROFILE", "ops")]
+            ))
+            .as_deref(),
+            Some("aws sso login --profile ops")
+        );
+        assert_eq!(
+            exec_login_command(&exec("kubelogin", &["get-token", "--login=azurecli"], &[]))
+                .as_deref(),
+            Some("az login")
+        );
+        assert_eq!(exec_login_command(&exec("vault-helper", &[], &[])), None);
+    }
+}
@@ -0,0 +1,358 @@
+use crate::audit::AuditEvent;
+use crate::k8s_client;
+use crate::protection::Action;
+use crate::resources::{
+    dynamic_api_for, resolve_api_resource, restart_patch, DeleteOptions, ResourceTarget,
+    TargetResult,
+};
+use crate::trash;
+use futures::{stream, StreamExt};
+use kube::api::{DeleteParams, ListParams, Patch, PatchParams};
+use kube::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter};
+
+const DEFAULT_CONCURRENCY: usize = 5;
+const MAX_CONCURRENCY: usize = 20;
+const BULK_PROGRESS_EVENT: &str = "bulk-operation-progress";
+
+#[derive(Debug, Clone)]
+enum BulkOperation {
+    Delete(DeleteParams),
+    Restart(Value),
+    Scale(i32),
+}
+
+// selects every object of a kind matching the given selectors
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceSelector {
+    // "all" selects from every namespace
+    pub namespace: String,
+    pub kind: String,
+    pub api_version: Option<String>,
+    pub label_selector: Option<String>,
+    pub field_selector: Option<String>,
+    // only keep objects whose status.reason matches, e.g. "Evicted"
+    pub status_reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkProgress {
+    pub operation_id: String,
+    pub completed: usize,
+    pub total: usize,
+    pub dry_run: bool,
+    pub result: TargetResult,
+}
+
+async fn select_targets(
+    client: &Client,
+    selector: &ResourceSelector,
+) -> Result<Vec<ResourceTarget>, String> {
+    let (api_resource, namespaced) =
+        resolve_api_resource(client, &selector.kind, selector.api_version.as_deref()).await?;
+    let list_all_namespaces = selector.namespace == "all";
+    let api = k8s_client::dynamic_api(
+        client.clone(),
+        &api_resource,
+        namespaced && !list_all_namespaces,
+        &selector.namespace,
+    );
+
+    let mut lp = ListParams::default();
+    if let Some(labels) = selector.label_selector.as_deref().filter(|l| !l.is_empty()) {
+        lp = lp.labels(labels);
+    }
+    if let Some(fields) = selector.field_selector.as_deref().filter(|f| !f.is_empty()) {
+        lp = lp.fields(fields);
+    }
+    let list = api.list(&lp).await.map_err(|e| e.to_string())?;
+
+    let targets = list
+        .items
+        .into_iter()
+        .filter(|obj| match selector.status_reason.as_deref() {
+            None | Some("") => true,
+            Some(reason) => {
+                obj.data
+                    .get("status")
+                    .and_then(|status| status.get("reason"))
+                    .and_then(Value::as_str)
+                    == Some(reason)
+            }
+        })
+        .map(|obj| ResourceTarget {
+            namespace: obj.metadata.namespace.unwrap_or_default(),
+            kind: selector.kind.clone(),
+            api_version: selector.api_version.clone(),
+            name: obj.metadata.name.unwrap_or_default(),
+        })
+        .collect();
+    Ok(targets)
+}
+
+async fn execute(
+    client: Client,
+    context: &str,
+    target: &ResourceTarget,
+    operation: &BulkOperation,
+    dry_run: bool,
+) -> Result<Option<String>, String> {
+    let api = dynamic_api_for(client, target).await?;
+    let patch_params = PatchParams {
+        dry_run,
+        ..Default::default()
+    };
+
+    let mut warning = None;
+    match operation {
+        BulkOperation::Delete(delete_params) => {
+            let delete_params = DeleteParams {
+                dry_run: dry_run || delete_params.dry_run,
+                ..delete_params.clone()
+            };
+            let mut snapshot = None;
+            if !delete_params.dry_run {
+                snapshot = Some(trash::snapshot(&api, context, &target.kind, &target.name).await?);
+            }
+            let deleted = api
+                .delete(&target.name, &delete_params)
+                .await
+                .map_err(|e| e.to_string());
+            warning = snapshot.and_then(|s| s.finish(&deleted));
+            deleted?;
+        }
+        BulkOperation::Restart(patch) => {
+            if !["Deployment", "StatefulSet", "DaemonSet"].contains(&target.kind.as_str()) {
+                return Err(format!("Resource type {} cannot be restarted", target.kind));
+            }
+            api.patch(&target.name, &patch_params, &Patch::Merge(patch))
+                .await
+                .map_err(|e| format!("Failed to restart {}: {}", target.kind, e))?;
+        }
+        BulkOperation::Scale(replicas) => {
+            if !["Deployment", "StatefulSet"].contains(&target.kind.as_str()) {
+                return Err(format!("Resource type {} cannot be scaled", target.kind));
+            }
+            let patch = serde_json::json!({ "spec": { "replicas": replicas } });
+            api.patch_scale(&target.name, &patch_params, &Patch::Merge(&patch))
+                .await
+                .map_err(|e| format!("Failed to scale {}: {}", target.kind, e))?;
+        }
+    }
+    Ok(warning)
+}
+
+// run an operation on every target with bounded concurrency,
+// emitting a progress event as each one completes; the operation id cancels it like a request id
+#[allow(clippy::too_many_arguments)]
+async fn run_bulk_operation(
+    app: AppHandle,
+    kubeconfig_path: String,
+    context: String,
+    operation_id: String,
+    operation: BulkOperation,
+    targets: Option<Vec<ResourceTarget>>,
+    selector: Option<ResourceSelector>,
+    dry_run: Option<bool>,
+    concurrency: Option<usize>,
+) -> Result<Vec<TargetResult>, String> {
+    let (client, _request) = k8s_client::create_tracked_client(
+        kubeconfig_path,
+        context.clone(),
+        Some(operation_id.clone()),
+    )
+    .await?;
+    let dry_run = dry_run.unwrap_or(false);
+    let concurrency = concurrency
+        .unwrap_or(DEFAULT_CONCURRENCY)
+        .clamp(1, MAX_CONCURRENCY);
+
+    let targets = match (targets, selector) {
+        (Some(targets), None) => targets,
+        (None, Some(selector)) => select_targets(&client, &selector).await?,
+        _ => return Err("Either targets or a selector must be given".to_string()),
+    };
+    let total = targets.len();
+
+    let mut results = Vec::with_capacity(total);
+    let mut outcomes = stream::iter(targets)
+        .map(|target| {
+            let client = client.clone();
+            let operation = &operation;
+            let context = &context;
+            async move {
+                let outcome = execute(client, context, &target, operation, dry_run).await;
+                let warning = outcome.as_ref().ok().cloned().flatten();
+                TargetResult {
+                    warning,
+                    ..TargetResult::new(target, outcome.map(|_| ()))
+                }
+            }
+        })
+        .buffer_unordered(concurrency);
+
+    while let Some(result) = outcomes.next().await {
+        let _ = app.emit(
+            BULK_PROGRESS_EVENT,
+            BulkProgress {
+                operation_id: operation_id.clone(),
+                completed: results.len() + 1,
+                total,
+                dry_run,
+                result: result.clone(),
+            },
+        );
+        results.push(result);
+    }
+    Ok(results)
+}
+
+// one audit entry for the whole operation, with the targets or selector as parameters
+fn bulk_audit_event(
+    kubeconfig_path: &str,
+    context: &str,
+    action: Action,
+    targets: &Option<Vec<ResourceTarget>>,
+    selector: &Option<ResourceSelector>,
+    mut params: Value,
+) -> AuditEvent {
+    params["targets"] = json!(targets);
+    params["selector"] = json!(selector);
+    AuditEvent::new(
+        kubeconfig_path,
+        context,
+        action,
+        selector.as_ref().map(|s| s.namespace.as_str()),
+        selector.as_ref().map(|s| s.kind.as_str()),
+        None,
+        params,
+    )
+}
+
+// delete several resources, given explicitly or by selector
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn bulk_delete_resources(
+    app: AppHandle,
+    kubeconfig_path: String,
+    context: String,
+    operation_id: String,
+    targets: Option<Vec<ResourceTarget>>,
+    selector: Option<ResourceSelector>,
+    options: Option<DeleteOptions>,
+    dry_run: Option<bool>,
+    concurrency: Option<usize>,
+    confirmation: Option<String>,
+) -> Result<Vec<TargetResult>, String> {
+    let audit = bulk_audit_event(
+        &kubeconfig_path,
+        &context,
+        Action::Delete,
+        &targets,
+        &selector,
+        json!({ "options": options }),
+    );
+    let dp = options.unwrap_or_default().to_delete_params()?;
+    let operation = run_bulk_operation(
+        app,
+        kubeconfig_path,
+        context,
+        operation_id,
+        BulkOperation::Delete(dp),
+        targets,
+        selector,
+        dry_run,
+        concurrency,
+    );
+    // a dry run changes nothing, so it is allowed on protected contexts and not recorded
+    if dry_run.unwrap_or(false) {
+        return operation.await;
+    }
+    audit.run(confirmation.as_deref(), operation).await
+}
+
+// restart several deployments, statefulsets or daemonsets
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn bulk_restart_resources(
+    app: AppHandle,
+    kubeconfig_path: String,
+    context: String,
+    operation_id: String,
+    targets: Option<Vec<ResourceTarget>>,
+    selector: Option<ResourceSelector>,
+    dry_run: Option<bool>,
+    concurrency: Option<usize>,
+    confirmation: Option<String>,
+) -> Result<Vec<TargetResult>, String> {
+    let audit = bulk_audit_event(
+        &kubeconfig_path,
+        &context,
+        Action::Restart,
+        &targets,
+        &selector,
+        json!({}),
+    );
+    let operation = run_bulk_operation(
+        app,
+        kubeconfig_path,
+        context,
+        operation_id,
+        BulkOperation::Restart(restart_patch()),
+        targets,
+        selector,
+        dry_run,
+        concurrency,
+    );
+    if dry_run.unwrap_or(false) {
+        return operation.await;
+    }
+    audit.run(confirmation.as_deref(), operation).await
+}
+
+// scale several deployments or statefulsets to the same number of replicas
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn bulk_scale_resources(
+    app: AppHandle,
+    kubeconfig_path: String,
+    context: String,
+    operation_id: String,
+    replicas: i32,
+    targets: Option<Vec<ResourceTarget>>,
+    selector: Option<ResourceSelector>,
+    dry_run: Option<bool>,
+    concurrency: Option<usize>,
+    confirmation: Option<String>,
+) -> Result<Vec<TargetResult>, String> {
+    if replicas < 0 {
+        return Err("Replicas cannot be negative".to_string());
+    }
+    let audit = bulk_audit_event(
+        &kubeconfig_path,
+        &context,
+        Action::Scale,
+        &targets,
+        &selector,
+        json!({ "replicas": replicas }),
+    );
+    let operation = run_bulk_operation(
+        app,
+        kubeconfig_path,
+        context,
+        operation_id,
+        BulkOperation::Scale(replicas),
+        targets,
+        selector,
+        dry_run,
+        concurrency,
+    );
+    if dry_run.unwrap_or(false) {
+        return operation.await;
+    }
+    audit.run(confirmation.as_deref(), operation).await
+}
@@ -0,0 +1,207 @@
+use crate::app_config;
+use crate::requests;
+use base64::Engine;
+use kube::config::{KubeConfigOptions, Kubeconfig};
+use kube::Config;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// proxy schemes the client supports, kubectl accepts the same ones
+const PROXY_SCHEMES: [&str; 2] = ["http", "socks5"];
+// kube's default of 30s keeps the UI waiting long on an unreachable cluster
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// connection overrides the app applies on top of a context's kubeconfig entries
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ConnectionSettings {
+    // http:// or socks5:// proxy for requests to the API server
+    pub proxy_url: Option<String>,
+    // PEM bundle trusted in addition to the certificate authority of the kubeconfig
+    pub ca_file: Option<String>,
+    pub tls_server_name: Option<String>,
+    pub insecure_skip_tls_verify: bool,
+    pub connect_timeout_secs: Option<u64>,
+    // limit for a single API request, kubectl keeps its own since it runs interactive sessions
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedConnectionSettings {
+    pub settings: ConnectionSettings,
+    pub warnings: Vec<String>,
+}
+
+impl ConnectionSettings {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    // rewrite the cluster of the context in place, so the client and kubectl see the same config
+    pub(crate) fn apply_to_kubeconfig(
+        &self,
+        kubeconfig: &mut Kubeconfig,
+        context: &str,
+    ) -> Result<(), String> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let cluster_name = kubeconfig
+            .contexts
+            .iter()
+            .find(|c| c.name == context)
+            .and_then(|c| c.context.as_ref())
+            .map(|c| c.cluster.clone())
+            .ok_or_else(|| format!("Context {} not found", context))?;
+        let cluster = kubeconfig
+            .clusters
+            .iter_mut()
+            .find(|c| c.name == cluster_name)
+            .and_then(|c| c.cluster.as_mut())
+            .ok_or_else(|| format!("Cluster {} not found", cluster_name))?;
+
+        if let Some(proxy_url) = &self.proxy_url {
+            cluster.proxy_url = Some(proxy_url.clone());
+        }
+        if let Some(tls_server_name) = &self.tls_server_name {
+            cluster.tls_server_name = Some(tls_server_name.clone());
+        }
+        if self.insecure_skip_tls_verify {
+            // kubectl refuses a certificate authority together with the insecure flag
+            cluster.insecure_skip_tls_verify = Some(true);
+            cluster.certificate_authority = None;
+            cluster.certificate_authority_data = None;
+        } else if let Some(ca_file) = &self.ca_file {
+            let mut bundle = match (
+                &cluster.certificate_authority_data,
+                &cluster.certificate_authority,
+            ) {
+                (Some(data), _) => base64::engine::general_purpose::STANDARD
+                    .decode(data.trim())
+                    .map_err(|e| format!("Embedded certificate is not valid base64: {}", e))?,
+                (None, Some(file)) => {
+                    std::fs::read(file).map_err(|e| format!("Failed to read {}: {}", file, e))?
+                }
+                (None, None) => vec![],
+            };
+            if !bundle.is_empty() && !bundle.ends_with(b"\n") {
+                bundle.push(b'\n');
+            }
+            bundle.extend(read_ca_bundle(ca_file)?);
+            cluster.certificate_authority = None;
+            cluster.certificate_authority_data =
+                Some(base64::engine::general_purpose::STANDARD.encode(bundle));
+        }
+        Ok(())
+    }
+
+    // the read timeout is left alone, exec sessions and watches are idle for long stretches
+    pub(crate) fn apply_to_config(&self, config: &mut Config) {
+        config.connect_timeout = Some(
+            self.connect_timeout_secs
+                .map_or(DEFAULT_CONNECT_TIMEOUT, Duration::from_secs),
+        );
+    }
+
+    // enforced per request by the client's request layer
+    pub(crate) fn request_timeout(&self) -> Duration {
+        self.timeout_secs
+            .map_or(requests::DEFAULT_REQUEST_TIMEOUT, Duration::from_secs)
+    }
+
+    // reject settings the client cannot use, and list the risky ones
+    fn validate(&self) -> Result<Vec<String>, String> {
+        let mut warnings = vec![];
+        if let Some(proxy_url) = &self.proxy_url {
+            let url = url::Url::parse(proxy_url)
+                .map_err(|e| format!("Invalid proxy URL {}: {}", proxy_url, e))?;
+            if !PROXY_SCHEMES.contains(&url.scheme()) {
+                return Err(format!(
+                    "Unsupported proxy scheme {}, use one of {}",
+                    url.scheme(),
+                    PROXY_SCHEMES.join(", ")
+                ));
+            }
+        }
+        if let Some(ca_file) = &self.ca_file {
+            read_ca_bundle(ca_file)?;
+            if self.insecure_skip_tls_verify {
+                warnings.push(format!(
+                    "{} is ignored while certificate verification is disabled",
+                    ca_file
+                ));
+            }
+        }
+        if self.timeout_secs == Some(0) || self.connect_timeout_secs == Some(0) {
+            return Err("Timeouts must be at least one second".to_string());
+        }
+        if self.insecure_skip_tls_verify {
+            warnings.push(
+                "TLS certificate verification is DISABLED for this context: anyone on the network \
+                 path can impersonate the API server and read your credentials"
+                    .to_string(),
+            );
+        }
+        Ok(warnings)
+    }
+}
+
+// a PEM file with at least one certificate
+fn read_ca_bundle(path: &str) -> Result<Vec<u8>, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let certificates = x509_parser::pem::Pem::iter_from_buffer(&bytes)
+        .filter(|pem| matches!(pem, Ok(pem) if pem.label == "CERTIFICATE"))
+        .count();
+    if certificates == 0 {
+        return Err(format!("{} contains no PEM certificate", path));
+    }
+    Ok(bytes)
+}
+
+pub fn context_connection(kubeconfig_path: &str, context: &str) -> ConnectionSettings {
+    app_config::context_settings(kubeconfig_path, context).connection
+}
+
+// the client config of a context with its connection overrides applied
+pub(crate) async fn context_config(
+    kubeconfig_path: &str,
+    context: &str,
+    settings: &ConnectionSettings,
+) -> Result<Config, String> {
+    let mut kubeconfig = crate::k8s_config::load_kubeconfig(kubeconfig_path)?;
+    settings.apply_to_kubeconfig(&mut kubeconfig, context)?;
+
+    let options = KubeConfigOptions {
+        context: Some(context.to_string()),
+        ..Default::default()
+    };
+    let mut config = Config::from_custom_kubeconfig(kubeconfig, &options)
+        .await
+        .map_err(|e| e.to_string())?;
+    settings.apply_to_config(&mut config);
+    Ok(config)
+}
+
+// save the overrides of a context, an empty settings object removes them
+#[tauri::command]
+pub async fn set_connection_settings(
+    kubeconfig_path: String,
+    context: String,
+    settings: ConnectionSettings,
+) -> Result<SavedConnectionSettings, String> {
+    let non_empty = |value: Option<String>| value.filter(|v| !v.trim().is_empty());
+    let settings = ConnectionSettings {
+        proxy_url: non_empty(settings.proxy_url),
+        ca_file: non_empty(settings.ca_file),
+        tls_server_name: non_empty(settings.tls_server_name),
+        ..settings
+    };
+    let warnings = settings.validate()?;
+    let saved =
+        app_config::update_context(&kubeconfig_path, &context, |s| s.connection = settings)?;
+    Ok(SavedConnectionSettings {
+        settings: saved.connection,
+        warnings,
+    })
+}
@@ -0,0 +1,501 @@
+use crate::audit::AuditEvent;
+use crate::k8s_client;
+use crate::protection::Action;
+use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Status;
+use k8s_openapi::chrono::Utc;
+use kube::api::{AttachParams, AttachedProcess};
+use kube::Api;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::{Component, Path, PathBuf};
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_dialog::DialogExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::oneshot;
+
+const TRANSFER_PROGRESS_EVENT: &str = "file-transfer-progress";
+const CHUNK_SIZE: usize = 64 * 1024;
+// tar reads whole records, padding the archive to one avoids waiting for more input
+const TAR_RECORD_SIZE: u64 = 10240;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferProgress {
+    pub transfer_id: String,
+    pub bytes: u64,
+    pub total_bytes: Option<u64>,
+}
+
+fn emit_progress(app: &AppHandle, transfer_id: &str, bytes: u64, total_bytes: Option<u64>) {
+    let _ = app.emit(
+        TRANSFER_PROGRESS_EVENT,
+        TransferProgress {
+            transfer_id: transfer_id.to_string(),
+            bytes,
+            total_bytes,
+        },
+    );
+}
+
+// split a container path into the directory to run tar in and the name to archive
+fn split_remote_path(remote_path: &str) -> Result<(String, String), String> {
+    let trimmed = remote_path.trim_end_matches('/');
+    if trimmed.is_empty() {
+        return Err("Remote path must point to a file or directory".to_string());
+    }
+    let (dir, base) = match trimmed.rsplit_once('/') {
+        Some(("", base)) => ("/", base),
+        Some((dir, base)) => (dir, base),
+        None => (".", trimmed),
+    };
+    if base == "." || base == ".." {
+        return Err(format!("Invalid remote path: {}", remote_path));
+    }
+    Ok((dir.to_string(), base.to_string()))
+}
+
+// ask the user for a local path with the dialog plugin
+async fn choose_local_path(
+    app: &AppHandle,
+    save: bool,
+    file_name: &str,
+) -> Result<PathBuf, String> {
+    let (tx, rx) = oneshot::channel();
+    let dialog = app.dialog().file();
+    if save {
+        dialog.set_file_name(file_name).save_file(move |path| {
+            let _ = tx.send(path);
+        });
+    } else {
+        dialog.pick_file(move |path| {
+            let _ = tx.send(path);
+        });
+    }
+
+    rx.await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "No local path was chosen".to_string())?
+        .into_path()
+        .map_err(|e| e.to_string())
+}
+
+fn local_path_or_dialog(local_path: Option<String>) -> Option<PathBuf> {
+    local_path.filter(|p| !p.is_empty()).map(PathBuf::from)
+}
+
+fn temp_archive_path() -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "kubeintel-copy-{}.tar",
+        Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    ))
+}
+
+// turn a failed tar invocation into a readable error, calling out images without tar
+fn tar_error(container_name: &str, stderr: &str, status: Option<Status>) -> Option<String> {
+    let status = status?;
+    if status.status.as_deref() == Some("Success") {
+        return None;
+    }
+
+    let message = format!("{} {}", stderr, status.message.unwrap_or_default());
+    if message.contains("executable file not found")
+        || message.contains("tar: not found")
+        || message.contains("\"tar\": ")
+    {
+        return Some(format!(
+            "tar is not available in container {}, copying files requires tar in the container image",
+            container_name
+        ));
+    }
+    Some(format!(
+        "tar failed in container {}: {}",
+        container_name,
+        message.trim()
+    ))
+}
+
+async fn wait_for_status(process: &mut AttachedProcess) -> Option<Status> {
+    match process.take_status() {
+        Some(status) => status.await,
+        None => None,
+    }
+}
+
+// unpack an archive of `base` into `destination`, refusing entries that would escape it
+fn extract_archive(archive_path: &Path, base: &str, destination: &Path) -> Result<(), String> {
+    let file = std::fs::File::open(archive_path).map_err(|e| e.to_string())?;
+    let mut archive = tar::Archive::new(file);
+
+    for entry in archive.entries().map_err(|e| e.to_string())? {
+        let mut entry = entry.map_err(|e| e.to_string())?;
+        let entry_type = entry.header().entry_type();
+        // links could point outside of the destination, kubectl cp skips them too
+        if entry_type.is_symlink() || entry_type.is_hard_link() {
+            continue;
+        }
+
+        let path = entry.path().map_err(|e| e.to_string())?.into_owned();
+        let relative = path
+            .strip_prefix(base)
+            .map_err(|_| format!("Unexpected entry in archive: {}", path.display()))?;
+        if relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(format!("Refusing to extract {}", path.display()));
+        }
+
+        let target = destination.join(relative);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        entry.unpack(&target).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// archive a local file or directory under the name `base`, returning the archive size
+fn build_archive(local_path: &Path, base: &str, archive_path: &Path) -> Result<u64, String> {
+    let file = std::fs::File::create(archive_path).map_err(|e| e.to_string())?;
+    let mut builder = tar::Builder::new(file);
+    if local_path.is_dir() {
+        builder.append_dir_all(base, local_path)
+    } else {
+        builder.append_path_with_name(local_path, base)
+    }
+    .map_err(|e| format!("Failed to archive {}: {}", local_path.display(), e))?;
+
+    let file = builder.into_inner().map_err(|e| e.to_string())?;
+    let size = file.metadata().map_err(|e| e.to_string())?.len();
+    let padded = size.div_ceil(TAR_RECORD_SIZE) * TAR_RECORD_SIZE;
+    file.set_len(padded).map_err(|e| e.to_string())?;
+    Ok(padded)
+}
+
+// copy a file or directory out of a container, returning the local path it was written to
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn copy_from_pod(
+    app: AppHandle,
+    kubeconfig_path: String,
+    context: String,
+    namespace: String,
+    pod_name: String,
+    container_name: String,
+    remote_path: String,
+    local_path: Option<String>,
+    transfer_id: String,
+    confirmation: Option<String>,
+) -> Result<String, String> {
+    let audit = AuditEvent::new(
+        &kubeconfig_path,
+        &context,
+        Action::CopyFromPod,
+        Some(&namespace),
+        Some("Pod"),
+        Some(&pod_name),
+        json!({ "container": container_name, "remotePath": remote_path, "localPath": local_path }),
+    );
+    audit
+        .run(confirmation.as_deref(), async move {
+            let (dir, base) = split_remote_path(&remote_path)?;
+            let local_path = match local_path_or_dialog(local_path) {
+                Some(path) => path,
+                None => choose_local_path(&app, true, &base).await?,
+            };
+
+            let client = k8s_client::create_k8s_client(kubeconfig_path, context).await?;
+            let pods: Api<Pod> = Api::namespaced(client, &namespace);
+            let ap = AttachParams::default()
+                .container(container_name.clone())
+                .stdout(true)
+                .stderr(true);
+            let mut process = pods
+                .exec(&pod_name, vec!["tar", "cf", "-", "-C", &dir, &base], &ap)
+                .await
+                .map_err(|e| format!("Failed to exec into pod {}: {}", pod_name, e))?;
+            let mut stdout = process.stdout().ok_or("tar output is not available")?;
+            let mut stderr = process.stderr().ok_or("tar errors are not available")?;
+
+            let archive_path = temp_archive_path();
+            let mut archive = tokio::fs::File::create(&archive_path)
+                .await
+                .map_err(|e| e.to_string())?;
+
+            let receive = async {
+                let mut buf = vec![0u8; CHUNK_SIZE];
+                let mut bytes = 0u64;
+                loop {
+                    let n = stdout.read(&mut buf).await?;
+                    if n == 0 {
+                        break;
+                    }
+                    archive.write_all(&buf[..n]).await?;
+                    bytes += n as u64;
+                    emit_progress(&app, &transfer_id, bytes, None);
+                }
+                archive.flush().await
+            };
+            let collect_errors = async {
+                let mut output = String::new();
+                let _ = stderr.read_to_string(&mut output).await;
+                output
+            };
+            let (received, errors) = tokio::join!(receive, collect_errors);
+            let status = wait_for_status(&mut process).await;
+            drop(archive);
+
+            if let Some(error) = tar_error(&container_name, &errors, status) {
+                let _ = std::fs::remove_file(&archive_path);
+                return Err(error);
+            }
+            if let Err(e) = received {
+                let _ = std::fs::remove_file(&archive_path);
+                return Err(format!("Failed to receive {}: {}", remote_path, e));
+            }
+
+            let destination = local_path.clone();
+            let extract_from = archive_path.clone();
+            let extracted = tauri::async_runtime::spawn_blocking(move || {
+                extract_archive(&extract_from, &base, &destination)
+            })
+            .await
+            .map_err(|e| e.to_string());
+            let _ = std::fs::remove_file(&archive_path);
+            extracted??;
+
+            Ok(local_path.to_string_lossy().to_string())
+        })
+        .await
+}
+
+// copy a local file or directory into a container at remote_path
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn copy_to_pod(
+    app: AppHandle,
+    kubeconfig_path: String,
+    context: String,
+    namespace: String,
+    pod_name: String,
+    container_name: String,
+    local_path: Option<String>,
+    remote_path: String,
+    transfer_id: String,
+    confirmation: Option<String>,
+) -> Result<(), String> {
+    let audit = AuditEvent::new(
+        &kubeconfig_path,
+        &context,
+        Action::CopyToPod,
+        Some(&namespace),
+        Some("Pod"),
+        Some(&pod_name),
+        json!({ "container": container_name, "remotePath": remote_path, "localPath": local_path }),
+    );
+    audit
+        .run(confirmation.as_deref(), async move {
+            let (dir, base) = split_remote_path(&remote_path)?;
+            let local_path = match local_path_or_dialog(local_path) {
+                Some(path) => path,
+                None => choose_local_path(&app, false, &base).await?,
+            };
+
+            let archive_path = temp_archive_path();
+            let archive_to = archive_path.clone();
+            let total = tauri::async_runtime::spawn_blocking(move || {
+                build_archive(&local_path, &base, &archive_to)
+            })
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|size| size);
+            let total = match total {
+                Ok(total) => total,
+                Err(e) => {
+                    let _ = std::fs::remove_file(&archive_path);
+                    return Err(e);
+                }
+            };
+
+            let result = send_archive(
+                &app,
+                kubeconfig_path,
+                context,
+                &namespace,
+                &pod_name,
+                &container_name,
+                &dir,
+                &archive_path,
+                total,
+                &transfer_id,
+            )
+            .await;
+            let _ = std::fs::remove_file(&archive_path);
+            result
+        })
+        .await
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn send_archive(
+    app: &AppHandle,
+    kubeconfig_path: String,
+    context: String,
+    namespace: &str,
+    pod_name: &str,
+    container_name: &str,
+    dir: &str,
+    archive_path: &Path,
+    total: u64,
+    transfer_id: &str,
+) -> Result<(), String> {
+    let client = k8s_client::create_k8s_client(kubeconfig_path, context).await?;
+    let pods: Api<Pod> = Api::namespaced(client, namespace);
+    let ap = AttachParams::default()
+        .container(container_name)
+        .stdin(true)
+        .stdout(true)
+        .stderr(true);
+    let mut process = pods
+        .exec(pod_name, vec!["tar", "xmf", "-", "-C", dir], &ap)
+        .await
+        .map_err(|e| format!("Failed to exec into pod {}: {}", pod_name, e))?;
+    let mut stdin = process.stdin().ok_or("tar input is not available")?;
+    let mut stdout = process.stdout().ok_or("tar output is not available")?;
+    let mut stderr = process.stderr().ok_or("tar errors are not available")?;
+
+    let mut archive = tokio::fs::File::open(archive_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    let send = async {
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        let mut bytes = 0u64;
+        loop {
+            let n = archive.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            stdin.write_all(&buf[..n]).await?;
+            bytes += n as u64;
+            emit_progress(app, transfer_id, bytes, Some(total));
+        }
+        stdin.flush().await
+    };
+    let drain_output = async {
+        let mut output = vec![];
+        let _ = stdout.read_to_end(&mut output).await;
+    };
+    let collect_errors = async {
+        let mut output = String::new();
+        let _ = stderr.read_to_string(&mut output).await;
+        output
+    };
+    let (sent, _, errors) = tokio::join!(
+        async {
+            let sent = send.await;
+            // closing stdin would tear down the whole connection, so it stays
+            // open until tar has read the end of the archive and exited
+            if sent.is_err() {
+                process.abort();
+            }
+            sent
+        },
+        drain_output,
+        collect_errors
+    );
+    let status = wait_for_status(&mut process).await;
+    drop(stdin);
+
+    if let Some(error) = tar_error(container_name, &errors, status) {
+        return Err(error);
+    }
+    sent.map_err(|e| format!("Failed to send archive: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // append an entry with a raw name, tar::Builder refuses to write `..` itself
+    fn append_raw(builder: &mut tar::Builder<std::fs::File>, name: &str, data: &[u8]) {
+        let mut header = tar::Header::new_gnu();
+        let raw = &mut header.as_gnu_mut().unwrap().name;
+        raw[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_cksum();
+        builder.append(&header, data).unwrap();
+    }
+
+    fn archive(dir: &Path, entries: &[(&str, &[u8])]) -> PathBuf {
+        let path = dir.join("archive.tar");
+        let mut builder = tar::Builder::new(std::fs::File::create(&path).unwrap());
+        for (name, data) in entries {
+            append_raw(&mut builder, name, data);
+        }
+        builder.finish().unwrap();
+        path
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("out")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn splits_remote_paths() {
+        assert_eq!(
+            split_remote_path("/var/log/app.log").unwrap(),
+            ("/var/log".to_string(), "app.log".to_string())
+        );
+        assert_eq!(
+            split_remote_path("/etc").unwrap(),
+            ("/".to_string(), "etc".to_string())
+        );
+        assert_eq!(
+            split_remote_path("dir/").unwrap(),
+            (".".to_string(), "dir".to_string())
+        );
+        assert_eq!(
+            split_remote_path("file").unwrap(),
+            (".".to_string(), "file".to_string())
+        );
+        assert!(split_remote_path("/").is_err());
+        assert!(split_remote_path("").is_err());
+        assert!(split_remote_path("logs/..").is_err());
+    }
+
+    #[test]
+    fn extracts_entries_below_the_base() {
+        let dir = temp_dir("copy-extract");
+        let path = archive(
+            &dir,
+            &[("logs/app.log", b"started"), ("logs/old/1.log", b"")],
+        );
+
+        extract_archive(&path, "logs", &dir.join("out")).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("out/app.log")).unwrap(),
+            "started"
+        );
+        assert!(dir.join("out/old/1.log").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn refuses_entries_escaping_the_destination() {
+        let dir = temp_dir("copy-escape");
+        let path = archive(&dir, &[("logs/../../escaped", b"x")]);
+        assert!(extract_archive(&path, "logs", &dir.join("out")).is_err());
+        assert!(!dir.join("escaped").exists());
+
+        let path = archive(&dir, &[("/tmp/absolute", b"x")]);
+        assert!(extract_archive(&path, "logs", &dir.join("out")).is_err());
+        assert_eq!(std::fs::read_dir(dir.join("out")).unwrap().count(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
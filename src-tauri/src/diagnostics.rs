@@ -0,0 +1,548 @@
+use crate::auth;
+use crate::connection;
+use crate::k8s_client;
+use crate::k8s_config::load_kubeconfig;
+use crate::requests;
+use base64::Engine;
+use k8s_openapi::chrono::{DateTime, Utc};
+use kube::config::{AuthInfo, Cluster};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(10);
+// certificates expiring sooner than this are reported as a warning
+const EXPIRY_WARNING_DAYS: i64 = 14;
+const NETWORK_STEPS: [&str; 4] = ["dns", "tcp", "tls", "authentication"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum StepStatus {
+    Ok,
+    Warning,
+    Failed,
+    Skipped,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticStep {
+    pub name: String,
+    pub status: StepStatus,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextDiagnosis {
+    pub context: String,
+    pub server: Option<String>,
+    pub steps: Vec<DiagnosticStep>,
+    // index of the first failed step, the one to fix first
+    pub first_failure: Option<usize>,
+}
+
+#[derive(Default)]
+struct Report {
+    steps: Vec<DiagnosticStep>,
+}
+
+impl Report {
+    fn add(&mut self, name: &str, status: StepStatus, message: impl Into<String>) {
+        self.steps.push(DiagnosticStep {
+            name: name.to_string(),
+            status,
+            message: message.into(),
+        });
+    }
+
+    // record a step from its result, returning whether it passed
+    fn check(&mut self, name: &str, result: Result<String, String>) -> bool {
+        let passed = result.is_ok();
+        match result {
+            Ok(message) => self.add(name, StepStatus::Ok, message),
+            Err(message) => self.add(name, StepStatus::Failed, message),
+        }
+        passed
+    }
+
+    fn skip(&mut self, names: &[&str]) {
+        for name in names {
+            self.add(
+                name,
+                StepStatus::Skipped,
+                "Skipped because of an earlier failure",
+            );
+        }
+    }
+}
+
+// find a command like the shell would, absolute and relative paths are used as is
+pub(crate) fn find_executable(command: &str) -> Option<PathBuf> {
+    let path = Path::new(command);
+    if path.components().count() > 1 {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+    let extensions: &[&str] = if cfg!(windows) {
+        &["", ".exe", ".cmd", ".bat"]
+    } else {
+        &[""]
+    };
+    std::env::var_os("PATH").and_then(|paths| {
+        std::env::split_paths(&paths).find_map(|dir| {
+            extensions
+                .iter()
+                .map(|ext| dir.join(format!("{}{}", command, ext)))
+                .find(|candidate| candidate.is_file())
+        })
+    })
+}
+
+// the PEM (or DER) bytes of a certificate given as a file or as base64 data
+fn certificate_bytes(
+    file: Option<&String>,
+    data: Option<&String>,
+) -> Option<Result<Vec<u8>, String>> {
+    if let Some(data) = data {
+        return Some(
+            base64::engine::general_purpose::STANDARD
+                .decode(data.trim())
+                .map_err(|e| format!("Embedded certificate is not valid base64: {}", e)),
+        );
+    }
+    file.map(|file| std::fs::read(file).map_err(|e| format!("Failed to read {}: {}", file, e)))
+}
+
+// subject and expiry of every certificate in a bundle, failing for expired ones
+fn check_certificates(bytes: &[u8]) -> Result<(StepStatus, String), String> {
+    let mut certificates = vec![];
+    for pem in x509_parser::pem::Pem::iter_from_buffer(bytes) {
+        let pem = pem.map_err(|e| format!("Invalid PEM data: {}", e))?;
+        let certificate = pem
+            .parse_x509()
+            .map_err(|e| format!("Invalid certificate: {}", e))?;
+        certificates.push((
+            certificate.subject().to_string(),
+            certificate.validity().not_after.timestamp(),
+        ));
+    }
+    if certificates.is_empty() {
+        let (_, certificate) = x509_parser::parse_x509_certificate(bytes)
+            .map_err(|e| format!("No certificate found: {}", e))?;
+        certificates.push((
+            certificate.subject().to_string(),
+            certificate.validity().not_after.timestamp(),
+        ));
+    }
+
+    let now = Utc::now().timestamp();
+    let mut status = StepStatus::Ok;
+    let mut messages = vec![];
+    for (subject, not_after) in certificates {
+        let expiry = DateTime::<Utc>::from_timestamp(not_after, 0)
+            .map(|t| t.to_rfc3339())
+            .unwrap_or_default();
+        if not_after < now {
+            return Err(format!("Certificate {} expired on {}", subject, expiry));
+        }
+        if not_after - now < EXPIRY_WARNING_DAYS * 24 * 3600 {
+            status = StepStatus::Warning;
+            messages.push(format!("{} expires soon, on {}", subject, expiry));
+        } else {
+            messages.push(format!("{} valid until {}", subject, expiry));
+        }
+    }
+    Ok((status, messages.join("; ")))
+}
+
+fn certificate_step(report: &mut Report, name: &str, file: Option<&String>, data: Option<&String>) {
+    match certificate_bytes(file, data) {
+        None => {
+            report.add(name, StepStatus::Skipped, "Not configured");
+        }
+        Some(Err(e)) => {
+            report.add(name, StepStatus::Failed, e);
+        }
+        Some(Ok(bytes)) => match check_certificates(&bytes) {
+            Ok((status, message)) => {
+                report.add(name, status, message);
+            }
+            Err(e) => {
+                report.add(name, StepStatus::Failed, e);
+            }
+        },
+    }
+}
+
+fn credentials_steps(report: &mut Report, cluster: &Cluster, auth_info: Option<&AuthInfo>) {
+    if cluster.insecure_skip_tls_verify == Some(true) {
+        report.add(
+            "certificateAuthority",
+            StepStatus::Warning,
+            "insecure-skip-tls-verify is set, the server certificate is not verified",
+        );
+    } else {
+        certificate_step(
+            report,
+            "certificateAuthority",
+            cluster.certificate_authority.as_ref(),
+            cluster.certificate_authority_data.as_ref(),
+        );
+    }
+
+    let Some(auth_info) = auth_info else {
+        return;
+    };
+    certificate_step(
+        report,
+        "clientCertificate",
+        auth_info.client_certificate.as_ref(),
+        auth_info.client_certificate_data.as_ref(),
+    );
+    let has_certificate =
+        auth_info.client_certificate.is_some() || auth_info.client_certificate_data.is_some();
+    let has_key = auth_info.client_key.is_some() || auth_info.client_key_data.is_some();
+    if has_certificate && !has_key {
+        report.add(
+            "clientKey",
+            StepStatus::Failed,
+            "A client certificate is configured without a client key",
+        );
+    } else if let Some(key_file) = &auth_info.client_key {
+        report.check(
+            "clientKey",
+            std::fs::metadata(key_file)
+                .map(|_| format!("{} exists", key_file))
+                .map_err(|e| format!("Failed to read {}: {}", key_file, e)),
+        );
+    }
+
+    if let Some(command) = auth_info.exec.as_ref().and_then(|e| e.command.as_ref()) {
+        report.check(
+            "execPlugin",
+            find_executable(command)
+                .map(|path| format!("{} found at {}", command, path.display()))
+                .ok_or_else(|| {
+                    format!(
+                        "{} was not found on PATH, install it or add its directory to PATH",
+                        command
+                    )
+                }),
+        );
+    }
+}
+
+async fn resolve(host: &str, port: u16) -> Result<Vec<SocketAddr>, String> {
+    let addrs: Vec<SocketAddr> =
+        tokio::time::timeout(NETWORK_TIMEOUT, tokio::net::lookup_host((host, port)))
+            .await
+            .map_err(|_| format!("Resolving {} timed out", host))?
+            .map_err(|e| format!("Failed to resolve {}: {}", host, e))?
+            .collect();
+    if addrs.is_empty() {
+        return Err(format!("{} did not resolve to any address", host));
+    }
+    Ok(addrs)
+}
+
+async fn connect(addrs: &[SocketAddr]) -> Result<SocketAddr, String> {
+    let mut errors = vec![];
+    for addr in addrs {
+        match tokio::time::timeout(NETWORK_TIMEOUT, tokio::net::TcpStream::connect(addr)).await {
+            Ok(Ok(_)) => return Ok(*addr),
+            Ok(Err(e)) => errors.push(format!("{}: {}", addr, e)),
+            Err(_) => errors.push(format!("{}: timed out", addr)),
+        }
+    }
+    Err(format!("Failed to connect: {}", errors.join(", ")))
+}
+
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message = format!("{}: {}", message, cause);
+        source = cause.source();
+    }
+    message
+}
+
+// any HTTP answer means the handshake worked, authentication is checked separately
+async fn tls_handshake(
+    server: &url::Url,
+    cluster: &Cluster,
+    addr: SocketAddr,
+) -> Result<String, String> {
+    let mut builder = reqwest::Client::builder()
+        .use_rustls_tls()
+        .timeout(NETWORK_TIMEOUT);
+    if cluster.insecure_skip_tls_verify == Some(true) {
+        builder = builder.danger_accept_invalid_certs(true);
+    } else if let Some(Ok(ca)) = certificate_bytes(
+        cluster.certificate_authority.as_ref(),
+        cluster.certificate_authority_data.as_ref(),
+    ) {
+        for certificate in reqwest::Certificate::from_pem_bundle(&ca).map_err(|e| e.to_string())? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    // verify against tls-server-name by connecting to that name at the server's address
+    let mut url = server.clone();
+    if let Some(server_name) = cluster.tls_server_name.as_deref() {
+        url.set_host(Some(server_name)).map_err(|e| e.to_string())?;
+        builder = builder.resolve(server_name, addr);
+    }
+    url.set_path("/version");
+
+    let client = builder.build().map_err(|e| e.to_string())?;
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("TLS handshake failed: {}", error_chain(&e)))?;
+    Ok(format!(
+        "Handshake succeeded, server answered {}",
+        response.status()
+    ))
+}
+
+// dns, tcp and tls steps of a direct connection, returning whether they passed
+async fn network_steps(report: &mut Report, url: &url::Url, cluster: &Cluster) -> bool {
+    let host = url
+        .host_str()
+        .unwrap_or_default()
+        .trim_matches(['[', ']'])
+        .to_string();
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    let addrs = match resolve(&host, port).await {
+        Ok(addrs) => {
+            let list: Vec<String> = addrs.iter().map(|a| a.ip().to_string()).collect();
+            report.add(
+                "dns",
+                StepStatus::Ok,
+                format!("{} resolves to {}", host, list.join(", ")),
+            );
+            addrs
+        }
+        Err(e) => {
+            report.add("dns", StepStatus::Failed, e);
+            report.skip(&NETWORK_STEPS[1..]);
+            return false;
+        }
+    };
+
+    let addr = match connect(&addrs).await {
+        Ok(addr) => {
+            report.add("tcp", StepStatus::Ok, format!("Connected to {}", addr));
+            addr
+        }
+        Err(e) => {
+            report.add("tcp", StepStatus::Failed, e);
+            report.skip(&NETWORK_STEPS[2..]);
+            return false;
+        }
+    };
+
+    if url.scheme() == "https" {
+        if !report.check("tls", tls_handshake(url, cluster, addr).await) {
+            report.skip(&NETWORK_STEPS[3..]);
+            return false;
+        }
+    } else {
+        report.add("tls", StepStatus::Warning, "The server is not using TLS");
+    }
+    true
+}
+
+// check a context step by step, from the kubeconfig entries to an authenticated call,
+// so the first failing step points at what to fix
+#[tauri::command]
+pub async fn diagnose_context(
+    kubeconfig_path: String,
+    context: String,
+    request_id: Option<String>,
+) -> Result<ContextDiagnosis, String> {
+    let mut report = Report::default();
+    let mut server = None;
+    // the network checks do not go through a client, so the whole diagnosis is dropped on cancel
+    let request = requests::track(&kubeconfig_path, &context, request_id);
+    let token = request.token();
+    tokio::select! {
+        _ = run_diagnosis(&mut report, &kubeconfig_path, &context, &mut server) => {}
+        _ = token.cancelled() => return Err("Request cancelled".to_string()),
+    }
+
+    let first_failure = report
+        .steps
+        .iter()
+        .position(|step| step.status == StepStatus::Failed);
+    Ok(ContextDiagnosis {
+        context,
+        server,
+        steps: report.steps,
+        first_failure,
+    })
+}
+
+async fn run_diagnosis(
+    report: &mut Report,
+    kubeconfig_path: &str,
+    context: &str,
+    server_url: &mut Option<String>,
+) {
+    // diagnose what the client uses, the kubeconfig with the app's connection overrides
+    let settings = connection::context_connection(kubeconfig_path, context);
+    let loaded = load_kubeconfig(kubeconfig_path).and_then(|mut kubeconfig| {
+        settings
+            .apply_to_kubeconfig(&mut kubeconfig, context)
+            .map(|_| kubeconfig)
+    });
+    let kubeconfig = match loaded {
+        Ok(kubeconfig) if settings.is_empty() => {
+            report.add("kubeconfig", StepStatus::Ok, "Kubeconfig loaded");
+            kubeconfig
+        }
+        Ok(kubeconfig) if settings.insecure_skip_tls_verify => {
+            report.add(
+                "kubeconfig",
+                StepStatus::Warning,
+                "Kubeconfig loaded, connection overrides disable TLS certificate verification",
+            );
+            kubeconfig
+        }
+        Ok(kubeconfig) => {
+            report.add(
+                "kubeconfig",
+                StepStatus::Ok,
+                "Kubeconfig loaded with the app's connection overrides",
+            );
+            kubeconfig
+        }
+        Err(e) => {
+            report.add("kubeconfig", StepStatus::Failed, e);
+            return;
+        }
+    };
+
+    let Some(named_context) = kubeconfig
+        .contexts
+        .iter()
+        .find(|c| c.name == context)
+        .and_then(|c| c.context.as_ref())
+    else {
+        report.add(
+            "context",
+            StepStatus::Failed,
+            format!("Context {} is not defined", context),
+        );
+        return;
+    };
+    report.add(
+        "context",
+        StepStatus::Ok,
+        format!("Context {} found", context),
+    );
+
+    let cluster = kubeconfig
+        .clusters
+        .iter()
+        .find(|c| c.name == named_context.cluster)
+        .and_then(|c| c.cluster.as_ref());
+    let Some(cluster) = cluster else {
+        report.add(
+            "cluster",
+            StepStatus::Failed,
+            format!(
+                "Cluster {} referenced by the context is not defined",
+                named_context.cluster
+            ),
+        );
+        return;
+    };
+    let Some(server) = cluster.server.clone() else {
+        report.add(
+            "cluster",
+            StepStatus::Failed,
+            "The cluster has no server URL",
+        );
+        return;
+    };
+    *server_url = Some(server.clone());
+    report.add("cluster", StepStatus::Ok, format!("Server {}", server));
+
+    let auth_info = match named_context.user.as_deref() {
+        None | Some("") => {
+            report.add(
+                "user",
+                StepStatus::Warning,
+                "The context has no user, requests are anonymous",
+            );
+            None
+        }
+        Some(user) => match kubeconfig.auth_infos.iter().find(|a| a.name == user) {
+            Some(named) => {
+                report.add("user", StepStatus::Ok, format!("User {} found", user));
+                named.auth_info.as_ref()
+            }
+            None => {
+                report.add(
+                    "user",
+                    StepStatus::Failed,
+                    format!("User {} referenced by the context is not defined", user),
+                );
+                None
+            }
+        },
+    };
+
+    credentials_steps(report, cluster, auth_info);
+    let login_command = auth_info.and_then(|a| auth::auth_summary(a).login_command);
+
+    let url = match url::Url::parse(&server) {
+        Ok(url) => url,
+        Err(e) => {
+            report.add(
+                "dns",
+                StepStatus::Failed,
+                format!("Invalid server URL: {}", e),
+            );
+            report.skip(&NETWORK_STEPS[1..]);
+            return;
+        }
+    };
+    match cluster.proxy_url.as_deref() {
+        // the proxy resolves and connects, so only an API call says whether it works
+        Some(proxy) => {
+            for step in &NETWORK_STEPS[..3] {
+                report.add(
+                    step,
+                    StepStatus::Skipped,
+                    format!("The server is reached through the proxy {}", proxy),
+                );
+            }
+        }
+        None => {
+            if !network_steps(report, &url, cluster).await {
+                return;
+            }
+        }
+    }
+
+    let version = async {
+        let client =
+            k8s_client::create_k8s_client(kubeconfig_path.to_string(), context.to_string()).await?;
+        tokio::time::timeout(NETWORK_TIMEOUT, client.apiserver_version())
+            .await
+            .map_err(|_| "Request timed out".to_string())?
+            .map_err(|e| auth::auth_error_message(&e, login_command.as_deref()))
+    };
+    report.check(
+        "authentication",
+        version
+            .await
+            .map(|info| format!("Authenticated, server version {}", info.git_version)),
+    );
+}
@@ -0,0 +1,148 @@
+use crate::audit::AuditEvent;
+use crate::k8s_client;
+use crate::protection::Action;
+use crate::resources::{dynamic_api_for, ResourceTarget};
+use k8s_openapi::api::core::v1::Namespace;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, Time};
+use kube::api::{Patch, PatchParams, PostParams};
+use kube::{Api, Client};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FinalizerStatus {
+    pub finalizers: Vec<String>,
+    // only set for namespaces, these are cleared through the finalize subresource
+    pub spec_finalizers: Vec<String>,
+    pub deletion_timestamp: Option<Time>,
+    pub resource_version: Option<String>,
+}
+
+impl FinalizerStatus {
+    fn new(metadata: &ObjectMeta, spec_finalizers: Vec<String>) -> Self {
+        FinalizerStatus {
+            finalizers: metadata.finalizers.clone().unwrap_or_default(),
+            spec_finalizers,
+            deletion_timestamp: metadata.deletion_timestamp.clone(),
+            resource_version: metadata.resource_version.clone(),
+        }
+    }
+}
+
+// namespaces are not a ResourceType, so they are matched by kind directly
+fn is_namespace(target: &ResourceTarget) -> bool {
+    target.kind == "Namespace"
+}
+
+fn namespace_spec_finalizers(namespace: &Namespace) -> Vec<String> {
+    namespace
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.finalizers.clone())
+        .unwrap_or_default()
+}
+
+async fn fetch_status(client: Client, target: &ResourceTarget) -> Result<FinalizerStatus, String> {
+    if is_namespace(target) {
+        let api: Api<Namespace> = Api::all(client);
+        let namespace = api.get(&target.name).await.map_err(|e| e.to_string())?;
+        Ok(FinalizerStatus::new(
+            &namespace.metadata,
+            namespace_spec_finalizers(&namespace),
+        ))
+    } else {
+        let api = dynamic_api_for(client, target).await?;
+        let obj = api.get(&target.name).await.map_err(|e| e.to_string())?;
+        Ok(FinalizerStatus::new(&obj.metadata, vec![]))
+    }
+}
+
+// list the finalizers that keep an object from being deleted
+#[tauri::command]
+pub async fn get_finalizers(
+    kubeconfig_path: String,
+    context: String,
+    target: ResourceTarget,
+) -> Result<FinalizerStatus, String> {
+    let client = k8s_client::create_k8s_client(kubeconfig_path, context).await?;
+    fetch_status(client, &target).await
+}
+
+// strip the given finalizers, or all of them, from an object stuck in Terminating
+#[tauri::command]
+pub async fn remove_finalizers(
+    kubeconfig_path: String,
+    context: String,
+    target: ResourceTarget,
+    finalizers: Option<Vec<String>>,
+    resource_version: Option<String>,
+    confirmation: Option<String>,
+) -> Result<FinalizerStatus, String> {
+    let audit = AuditEvent::new(
+        &kubeconfig_path,
+        &context,
+        Action::RemoveFinalizers,
+        Some(&target.namespace),
+        Some(&target.kind),
+        Some(&target.name),
+        json!({ "finalizers": finalizers }),
+    );
+    audit
+        .run(confirmation.as_deref(), async move {
+            let client = k8s_client::create_k8s_client(kubeconfig_path, context).await?;
+            let status = fetch_status(client.clone(), &target).await?;
+            let should_remove = |finalizer: &String| match finalizers.as_ref() {
+                None => true,
+                Some(selected) => selected.contains(finalizer),
+            };
+
+            let remaining: Vec<String> = status
+                .finalizers
+                .iter()
+                .filter(|f| !should_remove(f))
+                .cloned()
+                .collect();
+
+            if remaining.len() != status.finalizers.len() {
+                // the resourceVersion makes the patch fail if the object changed in the meantime
+                let resource_version = resource_version.or(status.resource_version.clone());
+                let patch = serde_json::json!({
+                    "metadata": {
+                        "finalizers": remaining,
+                        "resourceVersion": resource_version,
+                    }
+                });
+                let pp = PatchParams::default();
+                if is_namespace(&target) {
+                    let api: Api<Namespace> = Api::all(client.clone());
+                    api.patch(&target.name, &pp, &Patch::Merge(&patch))
+                        .await
+                        .map_err(|e| format!("Failed to remove finalizers: {}", e))?;
+                } else {
+                    let api = dynamic_api_for(client.clone(), &target).await?;
+                    api.patch(&target.name, &pp, &Patch::Merge(&patch))
+                        .await
+                        .map_err(|e| format!("Failed to remove finalizers: {}", e))?;
+                }
+            }
+
+            if is_namespace(&target) && status.spec_finalizers.iter().any(&should_remove) {
+                let api: Api<Namespace> = Api::all(client.clone());
+                let mut namespace = api.get(&target.name).await.map_err(|e| e.to_string())?;
+                if let Some(spec) = namespace.spec.as_mut() {
+                    spec.finalizers = spec
+                        .finalizers
+                        .take()
+                        .map(|f| f.into_iter().filter(|f| !should_remove(f)).collect());
+                }
+                let data = serde_json::to_vec(&namespace).map_err(|e| e.to_string())?;
+                api.replace_subresource("finalize", &target.name, &PostParams::default(), data)
+                    .await
+                    .map_err(|e| format!("Failed to finalize namespace: {}", e))?;
+            }
+
+            fetch_status(client, &target).await
+        })
+        .await
+}
@@ -1,26 +1,50 @@
 use k8s_openapi::api::core::v1::Event;
 use k8s_openapi::Resource;
-use kube::api::ListParams;
+use kube::api::{ApiResource, DeleteParams, DynamicObject, ListParams};
+use kube::client::{Body, ClientBuilder};
 use kube::core::ClusterResourceScope;
 use kube::Api;
-use kube::{config::KubeConfigOptions, config::Kubeconfig, Client, Config};
-use std::path::Path;
+use kube::Client;
+use tokio_util::sync::CancellationToken;
+use tower::buffer::BufferLayer;
 
-pub async fn create_k8s_client(kubeconfig_path: String, context: String) -> Result<Client, String> {
-    // Load the kubeconfig file
-    let kubeconfig: Kubeconfig =
-        Kubeconfig::read_from(Path::new(&kubeconfig_path)).map_err(|e| e.to_string())?;
+async fn build_client(
+    kubeconfig_path: &str,
+    context: &str,
+    token: CancellationToken,
+) -> Result<Client, String> {
+    // Load the kubeconfig files with the app's connection overrides
+    let settings = crate::connection::context_connection(kubeconfig_path, context);
+    let config = crate::connection::context_config(kubeconfig_path, context, &settings).await?;
 
-    // Create a client using the config
-    let options = KubeConfigOptions {
-        context: Some(context),
-        ..Default::default()
-    };
-    let config = Config::from_custom_kubeconfig(kubeconfig, &options)
-        .await
-        .map_err(|e| e.to_string())?;
+    // exec plugins run while the client is built, their failures get the context's login hint
+    let login_command = crate::auth::auth_summary(&config.auth_info).login_command;
+    // the buffer makes the stack cloneable, so the request layer can send retries
+    let client = ClientBuilder::try_from(config)
+        .map_err(|e| crate::auth::auth_error_message(&e, login_command.as_deref()))?
+        .with_layer(&BufferLayer::<http::Request<Body>>::new(1024))
+        .with_layer(&crate::requests::RequestLayer {
+            timeout: settings.request_timeout(),
+            token,
+        })
+        .build();
+    Ok(client)
+}
+
+pub async fn create_k8s_client(kubeconfig_path: String, context: String) -> Result<Client, String> {
+    let token = crate::requests::context_token(&kubeconfig_path, &context);
+    build_client(&kubeconfig_path, &context, token).await
+}
 
-    Client::try_from(config).map_err(|e| e.to_string())
+// a client whose requests can also be cancelled by request id while the guard is alive
+pub async fn create_tracked_client(
+    kubeconfig_path: String,
+    context: String,
+    request_id: Option<String>,
+) -> Result<(Client, crate::requests::RequestGuard), String> {
+    let guard = crate::requests::track(&kubeconfig_path, &context, request_id);
+    let client = build_client(&kubeconfig_path, &context, guard.token()).await?;
+    Ok((client, guard))
 }
 
 pub async fn list_resources<T>(
@@ -94,7 +118,12 @@ where
     Ok(resource)
 }
 
-pub async fn delete_resource<T>(client: Client, namespace: &str, name: &str) -> Result<(), String>
+pub async fn delete_resource<T>(
+    client: Client,
+    namespace: &str,
+    name: &str,
+    delete_params: &DeleteParams,
+) -> Result<(), String>
 where
     T: Resource<Scope = kube::core::NamespaceResourceScope>
         + Clone
@@ -104,13 +133,17 @@ where
 {
     let api: Api<T> = Api::namespaced(client, namespace);
     let _ = api
-        .delete(name, &Default::default())
+        .delete(name, delete_params)
         .await
         .map_err(|e| e.to_string())?;
     Ok(())
 }
 
-pub async fn delete_cluster_resource<K>(client: Client, name: &str) -> Result<(), String>
+pub async fn delete_cluster_resource<K>(
+    client: Client,
+    name: &str,
+    delete_params: &DeleteParams,
+) -> Result<(), String>
 where
     K: Resource<Scope = ClusterResourceScope>
         + Clone
@@ -120,12 +153,44 @@ where
 {
     let api: Api<K> = Api::all(client);
     let _ = api
-        .delete(name, &Default::default())
+        .delete(name, delete_params)
         .await
         .map_err(|e| e.to_string())?;
     Ok(())
 }
 
+pub fn dynamic_api(
+    client: Client,
+    api_resource: &ApiResource,
+    namespaced: bool,
+    namespace: &str,
+) -> Api<DynamicObject> {
+    if namespaced {
+        Api::namespaced_with(client, namespace, api_resource)
+    } else {
+        Api::all_with(client, api_resource)
+    }
+}
+
+// send a raw request and return the status with the body, whatever the status is;
+// kube's own error handling would turn a non-JSON error body into a quoted string
+pub async fn request_raw(
+    client: &Client,
+    request: http::Request<Vec<u8>>,
+) -> Result<(http::StatusCode, String), String> {
+    let response = client
+        .send(request.map(Body::from))
+        .await
+        .map_err(|e| e.to_string())?;
+    let status = response.status();
+    let body = response
+        .into_body()
+        .collect_bytes()
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok((status, String::from_utf8_lossy(&body).into_owned()))
+}
+
 #[allow(dead_code)]
 pub async fn list_events<T>(
     client: Client,
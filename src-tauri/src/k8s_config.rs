@@ -1,62 +1,281 @@
+use crate::app_config;
+use crate::auth;
+use crate::connection;
 use crate::kubectl::run_kubectl_command;
-use kube::{
-    config::{AuthInfo, KubeConfigOptions, Kubeconfig},
-    Client, Config,
-};
-use std::path::Path;
+use kube::{config::Kubeconfig, Client};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::time::Duration;
 
+const FLATTENED_DIR: &str = "kubeconfigs";
+// copies are rewritten on every use, older ones belong to contexts that are gone or renamed
+const FLATTENED_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextOrigin {
+    pub context: String,
+    // the file the context is taken from when several files define it
+    pub file: String,
+    pub cluster_file: Option<String>,
+    pub user_file: Option<String>,
+}
+
+// a kubeconfig_path may list several files separated like KUBECONFIG (":", or ";" on Windows),
+// an empty one means the KUBECONFIG environment variable or ~/.kube/config
+pub(crate) fn kubeconfig_paths(kubeconfig_path: &str) -> Vec<PathBuf> {
+    let value = if kubeconfig_path.trim().is_empty() {
+        default_kubeconfig_path()
+    } else {
+        kubeconfig_path.to_string()
+    };
+    std::env::split_paths(&value)
+        .filter(|path| !path.as_os_str().is_empty())
+        .collect()
+}
+
+// every file of the list that exists, in order; like kubectl, missing files are skipped
+fn read_kubeconfig_files(kubeconfig_path: &str) -> Result<Vec<(PathBuf, Kubeconfig)>, String> {
+    let paths = kubeconfig_paths(kubeconfig_path);
+    let files = paths
+        .iter()
+        .filter(|path| paths.len() == 1 || path.exists())
+        .map(|path| {
+            Kubeconfig::read_from(path)
+                .map(|kubeconfig| (path.clone(), kubeconfig))
+                .map_err(|e| format!("{}: {}", path.display(), e))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    if files.is_empty() {
+        return Err(format!("No kubeconfig file found in {}", kubeconfig_path));
+    }
+    Ok(files)
+}
+
+// merge the files with kubectl's precedence: the first file to define an entry wins
+pub(crate) fn load_kubeconfig(kubeconfig_path: &str) -> Result<Kubeconfig, String> {
+    read_kubeconfig_files(kubeconfig_path)?
+        .into_iter()
+        .try_fold(Kubeconfig::default(), |merged, (_, kubeconfig)| {
+            merged.merge(kubeconfig)
+        })
+        .map_err(|e| e.to_string())
+}
+
+pub(crate) fn context_origins(kubeconfig_path: &str) -> Result<Vec<ContextOrigin>, String> {
+    let files = read_kubeconfig_files(kubeconfig_path)?;
+    let first_file = |find: &dyn Fn(&Kubeconfig) -> bool| {
+        files
+            .iter()
+            .find(|(_, kubeconfig)| find(kubeconfig))
+            .map(|(path, _)| path.display().to_string())
+    };
+
+    let merged = load_kubeconfig(kubeconfig_path)?;
+    Ok(merged
+        .contexts
+        .iter()
+        .map(|named| {
+            let context = named.context.as_ref();
+            let cluster = context.map(|c| c.cluster.clone()).unwrap_or_default();
+            let user = context.and_then(|c| c.user.clone()).unwrap_or_default();
+            ContextOrigin {
+                context: named.name.clone(),
+                file: first_file(&|k| k.contexts.iter().any(|c| c.name == named.name))
+                    .unwrap_or_default(),
+                cluster_file: first_file(&|k| k.clusters.iter().any(|c| c.name == cluster)),
+                user_file: first_file(&|k| k.auth_infos.iter().any(|a| a.name == user)),
+            }
+        })
+        .collect())
+}
+
+// the readable part of the name can be the same for different contexts, e.g. "a/b" and "a_b",
+// the hash of the kubeconfig path and context keeps their copies apart and stays the same
+// across builds
+fn flattened_file_name(kubeconfig_path: &str, context: &str) -> String {
+    let readable: String = context
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let mut hasher = Sha256::new();
+    hasher.update(kubeconfig_path.as_bytes());
+    hasher.update([0]);
+    hasher.update(context.as_bytes());
+    let hash: String = hasher.finalize()[..8]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("{}-{}.yaml", readable, hash)
+}
+
+// delete the flattened copy of a context that was renamed or deleted
+pub(crate) fn remove_flattened_kubeconfig(kubeconfig_path: &str, context: &str) {
+    if let Ok(dir) = app_config::config_dir() {
+        let path = dir
+            .join(FLATTENED_DIR)
+            .join(flattened_file_name(kubeconfig_path, context));
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+// delete flattened copies that were not used for a while, called once from the app setup
+pub fn prune_flattened_kubeconfigs() {
+    let Ok(dir) = app_config::config_dir().map(|dir| dir.join(FLATTENED_DIR)) else {
+        return;
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let stale = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > FLATTENED_MAX_AGE);
+        if stale {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+// the single file to hand to kubectl --kubeconfig for a context: its origin file when that
+// also defines the context's cluster and user, otherwise a flattened copy of the context.
+// Contexts with connection overrides always get a copy with the overrides applied
+pub(crate) fn kubectl_kubeconfig(kubeconfig_path: &str, context: &str) -> Result<String, String> {
+    let settings = connection::context_connection(kubeconfig_path, context);
+    if settings.is_empty() {
+        let paths = kubeconfig_paths(kubeconfig_path);
+        if paths.len() == 1 {
+            return Ok(paths[0].display().to_string());
+        }
+
+        let origin = context_origins(kubeconfig_path)?
+            .into_iter()
+            .find(|origin| origin.context == context)
+            .ok_or_else(|| format!("Context {} not found in {}", context, kubeconfig_path))?;
+        let self_contained = origin.cluster_file.as_ref() == Some(&origin.file)
+            && origin.user_file.as_ref() == Some(&origin.file);
+        if self_contained {
+            return Ok(origin.file);
+        }
+    }
+
+    let mut merged = load_kubeconfig(kubeconfig_path)?;
+    settings.apply_to_kubeconfig(&mut merged, context)?;
+    let named_context = merged.contexts.iter().find(|c| c.name == context).cloned();
+    let cluster = named_context
+        .as_ref()
+        .and_then(|c| c.context.as_ref())
+        .map(|c| c.cluster.clone())
+        .unwrap_or_default();
+    let user = named_context
+        .as_ref()
+        .and_then(|c| c.context.as_ref())
+        .and_then(|c| c.user.clone())
+        .unwrap_or_default();
+    let flattened = Kubeconfig {
+        contexts: named_context.into_iter().collect(),
+        clusters: merged
+            .clusters
+            .into_iter()
+            .filter(|c| c.name == cluster)
+            .collect(),
+        auth_infos: merged
+            .auth_infos
+            .into_iter()
+            .filter(|a| a.name == user)
+            .collect(),
+        current_context: Some(context.to_string()),
+        ..Default::default()
+    };
+
+    let dir = app_config::config_dir()?.join(FLATTENED_DIR);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let path = dir.join(flattened_file_name(kubeconfig_path, context));
+    let content = serde_yaml::to_string(&flattened).map_err(|e| e.to_string())?;
+    // the copy holds credentials, keep it private like kubectl does
+    app_config::write_private_file(&path, content.as_bytes())?;
+    Ok(path.display().to_string())
+}
+
+// the KUBECONFIG environment variable, or ~/.kube/config when it is not set
 #[tauri::command]
-pub fn read_kubeconfig(kubeconfig_path: &str) -> Result<Kubeconfig, String> {
-    let kubeconfig =
-        Kubeconfig::read_from(Path::new(kubeconfig_path)).map_err(|e| e.to_string())?;
-    Ok(kubeconfig)
+pub fn default_kubeconfig_path() -> String {
+    match std::env::var("KUBECONFIG") {
+        Ok(value) if !value.trim().is_empty() => value,
+        _ => home::home_dir()
+            .map(|home| home.join(".kube").join("config"))
+            .unwrap_or_default()
+            .display()
+            .to_string(),
+    }
+}
+
+// the merged kubeconfig with the credentials of every user redacted
+#[tauri::command]
+pub fn read_kubeconfig(kubeconfig_path: &str) -> Result<serde_json::Value, String> {
+    let kubeconfig = load_kubeconfig(kubeconfig_path)?;
+    let users = kubeconfig
+        .auth_infos
+        .iter()
+        .map(|named| {
+            let user = named
+                .auth_info
+                .as_ref()
+                .map(auth::redacted_auth_info)
+                .transpose()?;
+            Ok(serde_json::json!({ "name": named.name, "user": user }))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let mut value = serde_json::to_value(&kubeconfig).map_err(|e| e.to_string())?;
+    value["users"] = serde_json::Value::Array(users);
+    Ok(value)
+}
+
+// the file each context of a merged kubeconfig comes from
+#[tauri::command]
+pub fn kubeconfig_context_origins(kubeconfig_path: &str) -> Result<Vec<ContextOrigin>, String> {
+    context_origins(kubeconfig_path)
 }
 
 #[tauri::command]
 pub async fn cluster_config_auth(
     kubeconfig_path: String,
     context: String,
-) -> Result<AuthInfo, String> {
-    // Load the kubeconfig file
-    let kubeconfig: Kubeconfig =
-        Kubeconfig::read_from(Path::new(&kubeconfig_path)).map_err(|e| e.to_string())?;
-
-    // Create a client using the config
-    let options = KubeConfigOptions {
-        context: Some(context),
-        ..Default::default()
-    };
-    let config = Config::from_custom_kubeconfig(kubeconfig, &options)
-        .await
-        .map_err(|e| e.to_string())?;
+) -> Result<serde_json::Value, String> {
+    // Load the kubeconfig files with the app's connection overrides
+    let settings = connection::context_connection(&kubeconfig_path, &context);
+    let config = connection::context_config(&kubeconfig_path, &context, &settings).await?;
 
-    // Extract the authentication information
-    Ok(config.auth_info)
+    // Extract the authentication information without tokens and keys
+    auth::redacted_auth_info(&config.auth_info)
 }
 
 #[tauri::command]
 pub async fn cluster_info(kubeconfig_path: String, context: String) -> Result<String, String> {
-    // Load the kubeconfig file
-    let kubeconfig: Kubeconfig =
-        Kubeconfig::read_from(Path::new(&kubeconfig_path)).map_err(|e| e.to_string())?;
-
-    // Create a client using the config
-    let options = KubeConfigOptions {
-        context: Some(context),
-        ..Default::default()
-    };
-    let config = Config::from_custom_kubeconfig(kubeconfig, &options)
-        .await
-        .map_err(|e| e.to_string())?;
+    // Load the kubeconfig files with the app's connection overrides
+    let settings = connection::context_connection(&kubeconfig_path, &context);
+    let config = connection::context_config(&kubeconfig_path, &context, &settings).await?;
 
     let cluster_url = config.cluster_url.clone();
-    let client = Client::try_from(config).map_err(|e| e.to_string())?;
+    let login_command = auth::auth_summary(&config.auth_info).login_command;
+    let client = Client::try_from(config)
+        .map_err(|e| auth::auth_error_message(&e, login_command.as_deref()))?;
 
     // Get cluster information
     let version = client
         .apiserver_version()
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| auth::auth_error_message(&e, login_command.as_deref()))?;
     Ok(format!(
         " Kubernetes control plane with version {:?} is Runing at: {:?}",
         version.git_version, cluster_url
@@ -71,8 +290,113 @@ pub async fn open_cluster_info_on_terminal(
     // generate a kubectl command for cluster-version
     let cmd_string = format!(
         "--kubeconfig={} --context={} cluster-info",
-        kubeconfig_path, context
+        kubectl_kubeconfig(&kubeconfig_path, &context)?,
+        context
     );
     run_kubectl_command(&cmd_string)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flattened_file_names_do_not_collide() {
+        let names = [
+            flattened_file_name("/home/a/.kube/config", "a/b"),
+            flattened_file_name("/home/a/.kube/config", "a_b"),
+            flattened_file_name("/home/a/.kube/other", "a_b"),
+        ];
+        assert!(names.iter().all(|name| name.starts_with("a_b-")));
+        assert_ne!(names[0], names[1]);
+        assert_ne!(names[1], names[2]);
+        assert_eq!(names[0], flattened_file_name("/home/a/.kube/config", "a/b"));
+    }
+
+    #[test]
+    fn flattened_file_names_are_stable() {
+        // the copies persist across app versions, a changed name would orphan them
+        assert_eq!(
+            flattened_file_name("/home/a/.kube/config", "a/b"),
+            "a_b-71bec8f7f04166d2.yaml"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn splits_path_lists_like_kubectl() {
+        assert_eq!(
+            kubeconfig_paths("/a/config::/b/config:"),
+            vec![PathBuf::from("/a/config"), PathBuf::from("/b/config")]
+        );
+        assert_eq!(
+            kubeconfig_paths("/a/config"),
+            vec![PathBuf::from("/a/config")]
+        );
+    }
+
+    fn kubeconfig_file(dir: &std::path::Path, name: &str, server: &str, extra: &str) -> String {
+        let path = dir.join(name);
+        let content = format!(
+            "apiVersion: v1
+kind: Config
+current-context: shared
+clusters:
+- name: shared
+  cluster:
+    server: {server}
+contexts:
+- name: shared
+  context:
+    cluster: shared
+    user: shared
+{extra}users:
+- name: shared
+  user:
+    token: {name}
+"
+        );
+        std::fs::write(&path, content).unwrap();
+        path.display().to_string()
+    }
+
+    #[test]
+    fn first_file_wins_when_merging() {
+        let dir = std::env::temp_dir().join(format!("kubeconfig-merge-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let first = kubeconfig_file(&dir, "first", "https://first:6443", "");
+        let second = kubeconfig_file(
+            &dir,
+            "second",
+            "https://second:6443",
+            "- name: only-second\n  context:\n    cluster: shared\n    user: shared\n",
+        );
+        let missing = dir.join("missing").display().to_string();
+        let list = std::env::join_paths([&first, &missing, &second])
+            .unwrap()
+            .into_string()
+            .unwrap();
+
+        let merged = load_kubeconfig(&list).unwrap();
+        assert_eq!(merged.clusters.len(), 1);
+        assert_eq!(
+            merged.clusters[0]
+                .cluster
+                .as_ref()
+                .unwrap()
+                .server
+                .as_deref(),
+            Some("https://first:6443")
+        );
+        let contexts: Vec<&str> = merged.contexts.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(contexts, ["shared", "only-second"]);
+
+        let origins = context_origins(&list).unwrap();
+        assert_eq!(origins[0].file, first);
+        assert_eq!(origins[1].file, second);
+        assert_eq!(origins[1].cluster_file.as_deref(), Some(first.as_str()));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
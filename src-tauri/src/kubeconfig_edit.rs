@@ -0,0 +1,534 @@
+use crate::app_config;
+use crate::k8s_config::{context_origins, kubeconfig_paths, remove_flattened_kubeconfig};
+use k8s_openapi::chrono::Local;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+// fields of clusters and users that hold file paths, relative to the kubeconfig they are in
+const CLUSTER_PATH_FIELDS: [&str; 1] = ["certificate-authority"];
+const USER_PATH_FIELDS: [&str; 3] = ["client-certificate", "client-key", "tokenFile"];
+// backups hold credentials too, only the most recent ones of each file are kept
+const MAX_BACKUPS: usize = 10;
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct KubeconfigEdit {
+    // the file that was written
+    pub file: String,
+    pub backup: Option<String>,
+    // fingerprint of the files after the edit, to pass to the next edit
+    pub fingerprint: String,
+}
+
+// a fingerprint of the content of every file in the list, to detect changes made elsewhere
+fn fingerprint(kubeconfig_path: &str) -> Result<String, String> {
+    let mut hasher = DefaultHasher::new();
+    for path in kubeconfig_paths(kubeconfig_path) {
+        path.hash(&mut hasher);
+        match std::fs::read(&path) {
+            Ok(content) => content.hash(&mut hasher),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+    Ok(format!("{:016x}", hasher.finish()))
+}
+
+// every edit starts from the fingerprint of the files it was based on
+fn ensure_unchanged(kubeconfig_path: &str, expected: &str) -> Result<(), String> {
+    if fingerprint(kubeconfig_path)? != expected {
+        return Err(
+            "The kubeconfig changed since it was read, reload it before editing".to_string(),
+        );
+    }
+    Ok(())
+}
+
+fn read_yaml(path: &Path) -> Result<Value, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let value: Value = serde_yaml::from_str(&content)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    Ok(match value {
+        Value::Null => Value::Mapping(Mapping::new()),
+        value => value,
+    })
+}
+
+// backups of a file, named "<file>.<timestamp>.bak", oldest first
+fn backup_paths(path: &Path, file_name: &str) -> Vec<PathBuf> {
+    let prefix = format!("{}.", file_name);
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut backups: Vec<PathBuf> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|backup| {
+            backup
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix(&prefix))
+                .and_then(|name| name.strip_suffix(".bak"))
+                .is_some_and(|stamp| {
+                    !stamp.is_empty() && stamp.chars().all(|c| c.is_ascii_digit() || c == '-')
+                })
+        })
+        .collect();
+    backups.sort();
+    backups
+}
+
+// back up the file with a timestamp, then replace it atomically keeping its permissions
+fn write_yaml(path: &Path, value: &Value) -> Result<Option<String>, String> {
+    // write through a symlinked kubeconfig, replacing the link would detach it from its target
+    let resolved;
+    let path = match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => {
+            resolved = std::fs::canonicalize(path)
+                .map_err(|e| format!("Failed to resolve {}: {}", path.display(), e))?;
+            resolved.as_path()
+        }
+        _ => path,
+    };
+    let content = serde_yaml::to_string(value).map_err(|e| e.to_string())?;
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "config".to_string());
+
+    let backup = if path.exists() {
+        // microseconds and a counter keep edits within the same second from sharing a backup
+        let stamp = Local::now().format("%Y%m%d-%H%M%S-%6f").to_string();
+        let backup = (0..)
+            .map(|n| match n {
+                0 => path.with_file_name(format!("{}.{}.bak", file_name, stamp)),
+                n => path.with_file_name(format!("{}.{}-{}.bak", file_name, stamp, n)),
+            })
+            .find(|backup| !backup.exists())
+            .unwrap_or_default();
+        let original = std::fs::read(path).map_err(|e| format!("Failed to back up: {}", e))?;
+        app_config::write_private_file(&backup, &original)?;
+
+        let backups = backup_paths(path, &file_name);
+        for old in backups
+            .iter()
+            .take(backups.len().saturating_sub(MAX_BACKUPS))
+        {
+            let _ = std::fs::remove_file(old);
+        }
+        Some(backup.display().to_string())
+    } else {
+        None
+    };
+
+    let tmp_path = path.with_file_name(format!(".{}.tmp", file_name));
+    // kubeconfigs hold credentials, a new file stays private and an existing one keeps its mode
+    app_config::write_private_file(&tmp_path, content.as_bytes())?;
+    if let Ok(metadata) = std::fs::metadata(path) {
+        std::fs::set_permissions(&tmp_path, metadata.permissions()).map_err(|e| e.to_string())?;
+    }
+    std::fs::rename(&tmp_path, path).map_err(|e| e.to_string())?;
+    Ok(backup)
+}
+
+fn named_list<'a>(config: &'a mut Value, list: &str) -> Result<&'a mut Vec<Value>, String> {
+    let mapping = config
+        .as_mapping_mut()
+        .ok_or("The kubeconfig is not a mapping".to_string())?;
+    let entry = mapping
+        .entry(Value::String(list.to_string()))
+        .or_insert(Value::Sequence(vec![]));
+    if entry.is_null() {
+        *entry = Value::Sequence(vec![]);
+    }
+    entry
+        .as_sequence_mut()
+        .ok_or_else(|| format!("{} is not a list", list))
+}
+
+fn entry_name(entry: &Value) -> Option<&str> {
+    entry.get("name").and_then(|name| name.as_str())
+}
+
+fn find_named<'a>(entries: &'a mut [Value], name: &str) -> Option<&'a mut Value> {
+    entries
+        .iter_mut()
+        .find(|entry| entry_name(entry) == Some(name))
+}
+
+fn set_field(value: &mut Value, field: &str, new_value: Option<Value>) -> Result<(), String> {
+    let mapping = value
+        .as_mapping_mut()
+        .ok_or_else(|| format!("Cannot set {}, the entry is not a mapping", field))?;
+    let key = Value::String(field.to_string());
+    match new_value {
+        Some(new_value) => {
+            mapping.insert(key, new_value);
+        }
+        None => {
+            mapping.remove(&key);
+        }
+    }
+    Ok(())
+}
+
+// the file that defines a context, for a single file or a list of files
+fn context_file(kubeconfig_path: &str, context: &str) -> Result<PathBuf, String> {
+    context_origins(kubeconfig_path)?
+        .into_iter()
+        .find(|origin| origin.context == context)
+        .map(|origin| PathBuf::from(origin.file))
+        .ok_or_else(|| format!("Context {} not found", context))
+}
+
+// read the file of a context, apply a change and write it back
+fn edit_context_file<F>(
+    kubeconfig_path: &str,
+    context: &str,
+    expected_fingerprint: &str,
+    change: F,
+) -> Result<KubeconfigEdit, String>
+where
+    F: FnOnce(&mut Value) -> Result<(), String>,
+{
+    let file = context_file(kubeconfig_path, context)?;
+    edit_file(kubeconfig_path, &file, expected_fingerprint, change)
+}
+
+fn edit_file<F>(
+    kubeconfig_path: &str,
+    file: &Path,
+    expected_fingerprint: &str,
+    change: F,
+) -> Result<KubeconfigEdit, String>
+where
+    F: FnOnce(&mut Value) -> Result<(), String>,
+{
+    ensure_unchanged(kubeconfig_path, expected_fingerprint)?;
+    let mut config = if file.exists() {
+        read_yaml(file)?
+    } else {
+        Value::Mapping(Mapping::new())
+    };
+    change(&mut config)?;
+    // check again right before writing, the change may have taken a while
+    ensure_unchanged(kubeconfig_path, expected_fingerprint)?;
+    let backup = write_yaml(file, &config)?;
+    Ok(KubeconfigEdit {
+        file: file.display().to_string(),
+        backup,
+        fingerprint: fingerprint(kubeconfig_path)?,
+    })
+}
+
+fn absolute_paths(entry: &mut Value, section: &str, fields: &[&str], base: &Path) {
+    let Some(section) = entry.get_mut(section).and_then(|s| s.as_mapping_mut()) else {
+        return;
+    };
+    for field in fields {
+        let key = Value::String(field.to_string());
+        if let Some(Value::String(path)) = section.get_mut(&key) {
+            if Path::new(path.as_str()).is_relative() {
+                *path = base.join(path.as_str()).display().to_string();
+            }
+        }
+    }
+}
+
+// fingerprint of the kubeconfig files, to pass to the edit commands
+#[tauri::command]
+pub fn kubeconfig_fingerprint(kubeconfig_path: &str) -> Result<String, String> {
+    fingerprint(kubeconfig_path)
+}
+
+#[tauri::command]
+pub fn rename_context(
+    kubeconfig_path: String,
+    context: String,
+    new_name: String,
+    fingerprint: String,
+) -> Result<KubeconfigEdit, String> {
+    if new_name.trim().is_empty() {
+        return Err("The new context name is empty".to_string());
+    }
+    if context_origins(&kubeconfig_path)?
+        .iter()
+        .any(|origin| origin.context == new_name)
+    {
+        return Err(format!("Context {} already exists", new_name));
+    }
+    let edit = edit_context_file(&kubeconfig_path, &context, &fingerprint, |config| {
+        let contexts = named_list(config, "contexts")?;
+        let entry = find_named(contexts, &context)
+            .ok_or_else(|| format!("Context {} not found", context))?;
+        set_field(entry, "name", Some(Value::String(new_name.clone())))?;
+        if config.get("current-context").and_then(|c| c.as_str()) == Some(context.as_str()) {
+            set_field(config, "current-context", Some(Value::String(new_name)))?;
+        }
+        Ok(())
+    })?;
+    remove_flattened_kubeconfig(&kubeconfig_path, &context);
+    Ok(edit)
+}
+
+// delete a context like kubectl config delete-context, its cluster and user are kept
+#[tauri::command]
+pub fn delete_context(
+    kubeconfig_path: String,
+    context: String,
+    fingerprint: String,
+) -> Result<KubeconfigEdit, String> {
+    let edit = edit_context_file(&kubeconfig_path, &context, &fingerprint, |config| {
+        named_list(config, "contexts")?.retain(|entry| entry_name(entry) != Some(context.as_str()));
+        if config.get("current-context").and_then(|c| c.as_str()) == Some(context.as_str()) {
+            set_field(
+                config,
+                "current-context",
+                Some(Value::String(String::new())),
+            )?;
+        }
+        Ok(())
+    })?;
+    remove_flattened_kubeconfig(&kubeconfig_path, &context);
+    Ok(edit)
+}
+
+// set or clear (None) the default namespace of a context
+#[tauri::command]
+pub fn set_context_namespace(
+    kubeconfig_path: String,
+    context: String,
+    namespace: Option<String>,
+    fingerprint: String,
+) -> Result<KubeconfigEdit, String> {
+    edit_context_file(&kubeconfig_path, &context, &fingerprint, |config| {
+        let contexts = named_list(config, "contexts")?;
+        let entry = find_named(contexts, &context)
+            .ok_or_else(|| format!("Context {} not found", context))?;
+        let details = entry
+            .as_mapping_mut()
+            .ok_or("Invalid context entry".to_string())?
+            .entry(Value::String("context".to_string()))
+            .or_insert(Value::Mapping(Mapping::new()));
+        let namespace = namespace.filter(|ns| !ns.is_empty()).map(Value::String);
+        set_field(details, "namespace", namespace)
+    })
+}
+
+// switch current-context, written to the first file like kubectl does
+#[tauri::command]
+pub fn set_current_context(
+    kubeconfig_path: String,
+    context: String,
+    fingerprint: String,
+) -> Result<KubeconfigEdit, String> {
+    if !context_origins(&kubeconfig_path)?
+        .iter()
+        .any(|origin| origin.context == context)
+    {
+        return Err(format!("Context {} not found", context));
+    }
+    let file = kubeconfig_paths(&kubeconfig_path)
+        .into_iter()
+        .next()
+        .ok_or("No kubeconfig file".to_string())?;
+    edit_file(&kubeconfig_path, &file, &fingerprint, |config| {
+        set_field(config, "current-context", Some(Value::String(context)))
+    })
+}
+
+// copy a context with its cluster and user from another kubeconfig file into the first file,
+// optionally under a new context name
+#[tauri::command]
+pub fn import_context(
+    kubeconfig_path: String,
+    source_path: String,
+    context: String,
+    new_name: Option<String>,
+    fingerprint: String,
+) -> Result<KubeconfigEdit, String> {
+    let source_path = PathBuf::from(source_path);
+    let mut source = read_yaml(&source_path)?;
+    let base = source_path
+        .parent()
+        .map(|dir| dir.to_path_buf())
+        .unwrap_or_default();
+
+    let mut context_entry = find_named(named_list(&mut source, "contexts")?, &context)
+        .cloned()
+        .ok_or_else(|| format!("Context {} not found in {}", context, source_path.display()))?;
+    let cluster_name = context_entry
+        .get("context")
+        .and_then(|c| c.get("cluster"))
+        .and_then(|c| c.as_str())
+        .map(|c| c.to_string());
+    let user_name = context_entry
+        .get("context")
+        .and_then(|c| c.get("user"))
+        .and_then(|u| u.as_str())
+        .map(|u| u.to_string());
+    let mut cluster_entry = match &cluster_name {
+        Some(name) => find_named(named_list(&mut source, "clusters")?, name).cloned(),
+        None => None,
+    };
+    let mut user_entry = match &user_name {
+        Some(name) => find_named(named_list(&mut source, "users")?, name).cloned(),
+        None => None,
+    };
+    if let Some(cluster) = cluster_entry.as_mut() {
+        absolute_paths(cluster, "cluster", &CLUSTER_PATH_FIELDS, &base);
+    }
+    if let Some(user) = user_entry.as_mut() {
+        absolute_paths(user, "user", &USER_PATH_FIELDS, &base);
+    }
+    let name = new_name
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or(context);
+    set_field(
+        &mut context_entry,
+        "name",
+        Some(Value::String(name.clone())),
+    )?;
+
+    let file = kubeconfig_paths(&kubeconfig_path)
+        .into_iter()
+        .next()
+        .ok_or("No kubeconfig file".to_string())?;
+    edit_file(&kubeconfig_path, &file, &fingerprint, |config| {
+        if find_named(named_list(config, "contexts")?, &name).is_some() {
+            return Err(format!("Context {} already exists", name));
+        }
+        // clusters and users are shared by name, an identical entry is reused
+        for (list, entry) in [("clusters", cluster_entry), ("users", user_entry)] {
+            let Some(entry) = entry else {
+                continue;
+            };
+            let entries = named_list(config, list)?;
+            let entry_name = entry_name(&entry).unwrap_or_default().to_string();
+            match find_named(entries, &entry_name) {
+                Some(existing) if *existing == entry => {}
+                Some(_) => {
+                    return Err(format!(
+                        "A different entry named {} already exists in {}",
+                        entry_name, list
+                    ))
+                }
+                None => entries.push(entry),
+            }
+        }
+        named_list(config, "contexts")?.push(context_entry);
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn set_current_context(config: &mut Value) -> Result<(), String> {
+        set_field(config, "current-context", Some(Value::String("dev".into())))
+    }
+
+    #[test]
+    fn refuses_edit_after_outside_change() {
+        let dir = temp_dir("kubeconfig-edit-changed");
+        let file = dir.join("config");
+        std::fs::write(&file, "current-context: prod\n").unwrap();
+        let kubeconfig_path = file.display().to_string();
+
+        let stale = fingerprint(&kubeconfig_path).unwrap();
+        std::fs::write(&file, "current-context: staging\n").unwrap();
+        assert!(edit_file(&kubeconfig_path, &file, &stale, set_current_context).is_err());
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "current-context: staging\n"
+        );
+
+        let current = fingerprint(&kubeconfig_path).unwrap();
+        let edit = edit_file(&kubeconfig_path, &file, &current, set_current_context).unwrap();
+        assert!(edit.backup.is_some());
+        assert_eq!(edit.fingerprint, fingerprint(&kubeconfig_path).unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn new_files_and_backups_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        let dir = temp_dir("kubeconfig-edit-mode");
+        let file = dir.join("config");
+        let value: Value = serde_yaml::from_str("current-context: dev").unwrap();
+
+        assert_eq!(write_yaml(&file, &value).unwrap(), None);
+        assert_eq!(mode(&file), 0o600);
+
+        // an existing file keeps the mode chosen by its owner
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o640)).unwrap();
+        let backup = write_yaml(&file, &value).unwrap().unwrap();
+        assert_eq!(mode(&file), 0o640);
+        assert_eq!(mode(Path::new(&backup)), 0o600);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn every_edit_gets_its_own_backup_and_old_ones_are_pruned() {
+        let dir = temp_dir("kubeconfig-edit-backups");
+        let file = dir.join("config");
+        std::fs::write(&file, "current-context: original\n").unwrap();
+        std::fs::write(dir.join("config.old.bak"), "kept").unwrap();
+
+        let first = write_yaml(&file, &serde_yaml::from_str("current-context: a").unwrap())
+            .unwrap()
+            .unwrap();
+        let second = write_yaml(&file, &serde_yaml::from_str("current-context: b").unwrap())
+            .unwrap()
+            .unwrap();
+        assert_ne!(first, second);
+        assert_eq!(
+            std::fs::read_to_string(&first).unwrap(),
+            "current-context: original\n"
+        );
+
+        for _ in 0..MAX_BACKUPS + 3 {
+            write_yaml(&file, &serde_yaml::from_str("current-context: c").unwrap()).unwrap();
+        }
+        assert_eq!(backup_paths(&file, "config").len(), MAX_BACKUPS);
+        assert!(!Path::new(&first).exists());
+        // files that only look similar are not backups
+        assert!(dir.join("config.old.bak").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_kubeconfigs_are_written_through() {
+        let dir = temp_dir("kubeconfig-edit-symlink");
+        let target = dir.join("shared-config");
+        let link = dir.join("config");
+        std::fs::write(&target, "current-context: prod\n").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let value: Value = serde_yaml::from_str("current-context: dev").unwrap();
+        write_yaml(&link, &value).unwrap();
+        assert!(std::fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(
+            std::fs::read_to_string(&target).unwrap(),
+            "current-context: dev\n"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,327 @@
+use crate::audit::AuditEvent;
+use crate::k8s_client;
+use crate::protection::Action;
+use crate::resources::{dynamic_api_for, ResourceTarget, TargetResult};
+use kube::api::{Patch, PatchParams};
+use kube::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+
+// total size limit of all annotations on an object, as enforced by the api server
+const MAX_ANNOTATIONS_SIZE: usize = 256 * 1024;
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MetadataChanges {
+    pub set_labels: BTreeMap<String, String>,
+    pub remove_labels: Vec<String>,
+    pub set_annotations: BTreeMap<String, String>,
+    pub remove_annotations: Vec<String>,
+}
+
+fn is_alphanumeric(c: char) -> bool {
+    c.is_ascii_alphanumeric()
+}
+
+// a qualified name: up to 63 alphanumeric characters, '-', '_' or '.', starting and ending with an alphanumeric
+fn validate_qualified_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > 63 {
+        return Err(format!("name part must be 1-63 characters: {:?}", name));
+    }
+    if !name
+        .chars()
+        .all(|c| is_alphanumeric(c) || c == '-' || c == '_' || c == '.')
+    {
+        return Err(format!(
+            "name part may only contain alphanumerics, '-', '_' or '.': {:?}",
+            name
+        ));
+    }
+    if !name.starts_with(is_alphanumeric) || !name.ends_with(is_alphanumeric) {
+        return Err(format!(
+            "name part must start and end with an alphanumeric character: {:?}",
+            name
+        ));
+    }
+    Ok(())
+}
+
+// a DNS-1123 subdomain: lowercase dot separated labels, at most 253 characters
+fn validate_dns_subdomain(prefix: &str) -> Result<(), String> {
+    if prefix.is_empty() || prefix.len() > 253 {
+        return Err(format!("prefix must be 1-253 characters: {:?}", prefix));
+    }
+    for part in prefix.split('.') {
+        let valid = !part.is_empty()
+            && part.len() <= 63
+            && part
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+            && !part.starts_with('-')
+            && !part.ends_with('-');
+        if !valid {
+            return Err(format!(
+                "prefix must be a lowercase DNS subdomain: {:?}",
+                prefix
+            ));
+        }
+    }
+    Ok(())
+}
+
+// label and annotation keys share the same syntax: an optional DNS subdomain prefix and a name
+pub(crate) fn validate_key(key: &str) -> Result<(), String> {
+    let result = match key.split_once('/') {
+        Some((prefix, name)) => validate_dns_subdomain(prefix).and(validate_qualified_name(name)),
+        None => validate_qualified_name(key),
+    };
+    result.map_err(|e| format!("invalid key {:?}: {}", key, e))
+}
+
+pub(crate) fn validate_label_value(key: &str, value: &str) -> Result<(), String> {
+    if value.is_empty() {
+        return Ok(());
+    }
+    validate_qualified_name(value).map_err(|e| format!("invalid value for label {:?}: {}", key, e))
+}
+
+impl MetadataChanges {
+    pub fn is_empty(&self) -> bool {
+        self.set_labels.is_empty()
+            && self.remove_labels.is_empty()
+            && self.set_annotations.is_empty()
+            && self.remove_annotations.is_empty()
+    }
+
+    // check every key and value against the kubernetes syntax rules, reporting all problems at once
+    pub fn validate(&self) -> Result<(), String> {
+        let mut errors = vec![];
+
+        for (key, value) in &self.set_labels {
+            if let Err(e) = validate_key(key).and(validate_label_value(key, value)) {
+                errors.push(e);
+            }
+        }
+        for key in self
+            .remove_labels
+            .iter()
+            .chain(self.set_annotations.keys())
+            .chain(self.remove_annotations.iter())
+        {
+            if let Err(e) = validate_key(key) {
+                errors.push(e);
+            }
+        }
+
+        let annotations_size: usize = self
+            .set_annotations
+            .iter()
+            .map(|(k, v)| k.len() + v.len())
+            .sum();
+        if annotations_size > MAX_ANNOTATIONS_SIZE {
+            errors.push(format!(
+                "annotations must not exceed {} bytes in total",
+                MAX_ANNOTATIONS_SIZE
+            ));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+
+    // a merge patch where removed keys are set to null
+    pub fn to_merge_patch(&self) -> Value {
+        fn merge_map(set: &BTreeMap<String, String>, remove: &[String]) -> Map<String, Value> {
+            let mut map: Map<String, Value> =
+                remove.iter().map(|k| (k.clone(), Value::Null)).collect();
+            map.extend(
+                set.iter()
+                    .map(|(k, v)| (k.clone(), Value::String(v.clone()))),
+            );
+            map
+        }
+
+        let mut metadata = Map::new();
+        if !self.set_labels.is_empty() || !self.remove_labels.is_empty() {
+            metadata.insert(
+                "labels".to_string(),
+                Value::Object(merge_map(&self.set_labels, &self.remove_labels)),
+            );
+        }
+        if !self.set_annotations.is_empty() || !self.remove_annotations.is_empty() {
+            metadata.insert(
+                "annotations".to_string(),
+                Value::Object(merge_map(&self.set_annotations, &self.remove_annotations)),
+            );
+        }
+        json!({ "metadata": metadata })
+    }
+}
+
+pub(crate) async fn apply_metadata_changes(
+    client: Client,
+    target: &ResourceTarget,
+    patch: &Value,
+) -> Result<(), String> {
+    let api = dynamic_api_for(client, target).await?;
+    api.patch(&target.name, &PatchParams::default(), &Patch::Merge(patch))
+        .await
+        .map_err(|e| {
+            format!(
+                "Failed to update metadata of {} {}: {}",
+                target.kind, target.name, e
+            )
+        })?;
+    Ok(())
+}
+
+// add, change and remove labels and annotations of any resource
+#[tauri::command]
+pub async fn update_resource_metadata(
+    kubeconfig_path: String,
+    context: String,
+    target: ResourceTarget,
+    changes: MetadataChanges,
+    confirmation: Option<String>,
+) -> Result<(), String> {
+    let audit = AuditEvent::new(
+        &kubeconfig_path,
+        &context,
+        Action::UpdateMetadata,
+        Some(&target.namespace),
+        Some(&target.kind),
+        Some(&target.name),
+        json!({ "changes": changes }),
+    );
+    audit
+        .run(confirmation.as_deref(), async move {
+            changes.validate()?;
+            if changes.is_empty() {
+                return Ok(());
+            }
+
+            let client = k8s_client::create_k8s_client(kubeconfig_path, context).await?;
+            apply_metadata_changes(client, &target, &changes.to_merge_patch()).await
+        })
+        .await
+}
+
+// apply the same label and annotation changes to several resources
+#[tauri::command]
+pub async fn bulk_update_resource_metadata(
+    kubeconfig_path: String,
+    context: String,
+    targets: Vec<ResourceTarget>,
+    changes: MetadataChanges,
+    confirmation: Option<String>,
+    request_id: Option<String>,
+) -> Result<Vec<TargetResult>, String> {
+    let audit = AuditEvent::new(
+        &kubeconfig_path,
+        &context,
+        Action::UpdateMetadata,
+        None,
+        None,
+        None,
+        json!({ "targets": targets, "changes": changes }),
+    );
+    audit
+        .run(confirmation.as_deref(), async move {
+            // nothing is sent when the changes are invalid, so the results stay all-or-nothing for syntax errors
+            changes.validate()?;
+
+            let (client, _request) =
+                k8s_client::create_tracked_client(kubeconfig_path, context, request_id).await?;
+            let patch = changes.to_merge_patch();
+
+            let mut results = Vec::with_capacity(targets.len());
+            for target in targets {
+                let outcome = if changes.is_empty() {
+                    Ok(())
+                } else {
+                    apply_metadata_changes(client.clone(), &target, &patch).await
+                };
+                results.push(TargetResult::new(target, outcome));
+            }
+            Ok(results)
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_valid_keys() {
+        for key in [
+            "app",
+            "app.kubernetes.io/name",
+            "example.com/some_key.v1",
+            "a",
+            &"k".repeat(63),
+        ] {
+            assert!(validate_key(key).is_ok(), "{}", key);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_keys() {
+        for key in [
+            "",
+            "/name",
+            "prefix/",
+            "Example.com/name",
+            "-prefix.com/name",
+            "a..b/name",
+            "-name",
+            "name-",
+            "na me",
+            "a/b/c",
+            &"k".repeat(64),
+            &format!("{}/name", "p".repeat(254)),
+        ] {
+            assert!(validate_key(key).is_err(), "{}", key);
+        }
+    }
+
+    #[test]
+    fn empty_label_value_is_allowed() {
+        assert!(validate_label_value("app", "").is_ok());
+        assert!(validate_label_value("app", "web-1.2_3").is_ok());
+        assert!(validate_label_value("app", "has space").is_err());
+        assert!(validate_label_value("app", &"v".repeat(64)).is_err());
+    }
+
+    #[test]
+    fn validate_reports_all_problems() {
+        let changes = MetadataChanges {
+            set_labels: BTreeMap::from([
+                ("ok".to_string(), "fine".to_string()),
+                ("bad key".to_string(), "fine".to_string()),
+                ("app".to_string(), "bad value".to_string()),
+            ]),
+            remove_annotations: vec!["-x".to_string()],
+            ..Default::default()
+        };
+        let errors = changes.validate().unwrap_err();
+        assert_eq!(errors.split("; ").count(), 3, "{}", errors);
+        assert!(MetadataChanges::default().validate().is_ok());
+    }
+
+    #[test]
+    fn validate_limits_annotation_size() {
+        let changes = MetadataChanges {
+            set_annotations: BTreeMap::from([(
+                "big".to_string(),
+                "x".repeat(MAX_ANNOTATIONS_SIZE),
+            )]),
+            ..Default::default()
+        };
+        assert!(changes.validate().unwrap_err().contains("annotations"));
+    }
+}
@@ -1,11 +1,36 @@
+mod app_config;
+mod audit;
+mod auth;
+mod bulk;
+mod connection;
+mod copy;
 mod credentials;
+mod diagnostics;
+mod finalizers;
 mod k8s_client;
 mod k8s_config;
+mod kubeconfig_edit;
 mod kubectl;
+mod labels;
+mod metrics;
+mod metrics_history;
 mod namespaces;
 mod nodes;
+mod overview;
+mod permissions;
 mod pods;
+mod problems;
+mod prometheus;
+mod protection;
+mod quantity;
+mod rbac;
+mod requests;
 mod resources;
+mod scheduling;
+mod sessions;
+mod trash;
+
+use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -16,24 +41,89 @@ pub fn run() {
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_opener::init())
+        .setup(|app| {
+            app_config::init(app.path().app_config_dir()?);
+            k8s_config::prune_flattened_kubeconfigs();
+            Ok(())
+        })
+        .manage(sessions::SessionManager::default())
+        .manage(metrics_history::MetricsHistory::default())
+        .manage(permissions::PermissionCache::default())
         .invoke_handler(tauri::generate_handler![
+            app_config::get_context_settings,
+            audit::query_audit_log,
+            audit::export_audit_log,
+            audit::set_audit_retention,
+            auth::cluster_auth_summary,
+            auth::retry_context_login,
+            bulk::bulk_delete_resources,
+            bulk::bulk_restart_resources,
+            bulk::bulk_scale_resources,
+            connection::set_connection_settings,
+            copy::copy_from_pod,
+            copy::copy_to_pod,
             credentials::set_secret,
             credentials::get_secret,
             credentials::remove_secret,
+            diagnostics::diagnose_context,
+            finalizers::get_finalizers,
+            finalizers::remove_finalizers,
+            kubeconfig_edit::kubeconfig_fingerprint,
+            kubeconfig_edit::rename_context,
+            kubeconfig_edit::delete_context,
+            kubeconfig_edit::set_context_namespace,
+            kubeconfig_edit::set_current_context,
+            kubeconfig_edit::import_context,
             kubectl::is_kubectl_installed,
+            labels::update_resource_metadata,
+            labels::bulk_update_resource_metadata,
+            metrics::get_pod_metrics,
+            metrics::get_node_metrics,
+            metrics::get_namespace_metrics,
+            metrics_history::start_metrics_sampler,
+            metrics_history::stop_metrics_sampler,
+            metrics_history::metrics_sampler_status,
+            metrics_history::metrics_history,
+            k8s_config::default_kubeconfig_path,
             k8s_config::read_kubeconfig,
+            k8s_config::kubeconfig_context_origins,
             k8s_config::cluster_config_auth,
             k8s_config::cluster_info,
             k8s_config::open_cluster_info_on_terminal,
             namespaces::list_namespaces,
+            namespaces::context_namespaces,
+            namespaces::set_last_namespace,
+            namespaces::set_context_namespaces,
             nodes::list_pods_on_node,
             nodes::debug_node,
+            nodes::start_node_debug_session,
             nodes::cordon_node,
             nodes::drain_node,
             nodes::uncordon_node,
+            nodes::set_node_taint,
+            nodes::remove_node_taint,
+            nodes::update_node_labels,
+            nodes::update_node_annotations,
+            nodes::preview_taint_eviction,
+            overview::cluster_overview,
+            permissions::check_permissions,
+            permissions::clear_permission_cache,
             pods::open_pod_shell,
             pods::debug_pod,
             pods::get_pod_logs,
+            pods::start_exec_session,
+            pods::start_pod_debug_session,
+            problems::find_problems,
+            prometheus::set_prometheus_endpoint,
+            prometheus::list_prometheus_templates,
+            prometheus::prometheus_query_range,
+            prometheus::prometheus_template_query,
+            protection::set_context_protection,
+            rbac::subject_permissions,
+            rbac::who_can,
+            rbac::find_risky_rbac_grants,
+            requests::cancel_request,
+            requests::cancel_context_requests,
             resources::get_resource,
             resources::list_resource,
             resources::list_resource_events,
@@ -42,7 +132,20 @@ pub fn run() {
             resources::restart_resource,
             resources::open_resource_events_in_terminal,
             resources::open_resource_logs_in_terminal,
+            scheduling::explain_pending_pod,
+            sessions::write_session_input,
+            sessions::resize_session,
+            sessions::close_session,
+            trash::list_deleted_resources,
+            trash::restore_deleted,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                // remove debug pods and other leftovers of open sessions
+                let sessions = app.state::<sessions::SessionManager>();
+                tauri::async_runtime::block_on(sessions.shutdown());
+            }
+        });
 }
@@ -0,0 +1,348 @@
+use crate::k8s_client;
+use crate::quantity::{percent, quantity_value};
+use k8s_openapi::api::core::v1::{Container, Node, Pod};
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use kube::api::{ApiResource, DynamicObject, GroupVersionKind, ListParams};
+use kube::{Api, Client};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Deserialize, Debug, Default)]
+struct MetricsUsage {
+    #[serde(default)]
+    cpu: Option<Quantity>,
+    #[serde(default)]
+    memory: Option<Quantity>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ContainerMetrics {
+    name: String,
+    #[serde(default)]
+    usage: MetricsUsage,
+}
+
+#[derive(Deserialize, Debug)]
+struct PodMetricsData {
+    #[serde(default)]
+    containers: Vec<ContainerMetrics>,
+}
+
+#[derive(Deserialize, Debug)]
+struct NodeMetricsData {
+    #[serde(default)]
+    usage: MetricsUsage,
+}
+
+// cpu in cores and memory in bytes, with the configured requests and limits when known
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceUsage {
+    pub cpu: f64,
+    pub memory: f64,
+    pub cpu_request: Option<f64>,
+    pub cpu_limit: Option<f64>,
+    pub memory_request: Option<f64>,
+    pub memory_limit: Option<f64>,
+    pub cpu_request_percent: Option<f64>,
+    pub cpu_limit_percent: Option<f64>,
+    pub memory_request_percent: Option<f64>,
+    pub memory_limit_percent: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerUsage {
+    pub name: String,
+    pub usage: ResourceUsage,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PodUsage {
+    pub namespace: String,
+    pub name: String,
+    pub usage: ResourceUsage,
+    pub containers: Vec<ContainerUsage>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NamespaceUsage {
+    pub namespace: String,
+    pub pod_count: usize,
+    pub usage: ResourceUsage,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeUsage {
+    pub name: String,
+    pub cpu: f64,
+    pub memory: f64,
+    pub cpu_allocatable: Option<f64>,
+    pub memory_allocatable: Option<f64>,
+    pub cpu_percent: Option<f64>,
+    pub memory_percent: Option<f64>,
+}
+
+impl ResourceUsage {
+    fn with_percentages(mut self) -> Self {
+        self.cpu_request_percent = percent(self.cpu, self.cpu_request);
+        self.cpu_limit_percent = percent(self.cpu, self.cpu_limit);
+        self.memory_request_percent = percent(self.memory, self.memory_request);
+        self.memory_limit_percent = percent(self.memory, self.memory_limit);
+        self
+    }
+
+    // add up usages, a missing request counts as zero while a missing limit means unlimited
+    fn sum<'a>(usages: impl IntoIterator<Item = &'a ResourceUsage>) -> ResourceUsage {
+        fn add_request(total: Option<f64>, value: Option<f64>) -> Option<f64> {
+            match (total, value) {
+                (None, None) => None,
+                (total, value) => Some(total.unwrap_or(0.0) + value.unwrap_or(0.0)),
+            }
+        }
+        fn add_limit(total: Option<f64>, value: Option<f64>) -> Option<f64> {
+            Some(total? + value?)
+        }
+
+        let mut usages = usages.into_iter();
+        let Some(first) = usages.next() else {
+            return ResourceUsage::default();
+        };
+        usages
+            .fold(first.clone(), |total, usage| ResourceUsage {
+                cpu: total.cpu + usage.cpu,
+                memory: total.memory + usage.memory,
+                cpu_request: add_request(total.cpu_request, usage.cpu_request),
+                cpu_limit: add_limit(total.cpu_limit, usage.cpu_limit),
+                memory_request: add_request(total.memory_request, usage.memory_request),
+                memory_limit: add_limit(total.memory_limit, usage.memory_limit),
+                ..Default::default()
+            })
+            .with_percentages()
+    }
+}
+
+fn pod_metrics_resource() -> ApiResource {
+    ApiResource::from_gvk_with_plural(
+        &GroupVersionKind::gvk("metrics.k8s.io", "v1beta1", "PodMetrics"),
+        "pods",
+    )
+}
+
+fn node_metrics_resource() -> ApiResource {
+    ApiResource::from_gvk_with_plural(
+        &GroupVersionKind::gvk("metrics.k8s.io", "v1beta1", "NodeMetrics"),
+        "nodes",
+    )
+}
+
+// the metrics API only exists when metrics-server (or an equivalent) is installed
+fn metrics_error(e: kube::Error) -> String {
+    match e {
+        kube::Error::Api(response) if response.code == 404 || response.code == 503 => {
+            "Metrics API is not available, metrics-server is probably not installed in this cluster"
+                .to_string()
+        }
+        e => format!("Failed to read metrics: {}", e),
+    }
+}
+
+fn resource_value(resources: Option<&BTreeMap<String, Quantity>>, name: &str) -> Option<f64> {
+    resources.and_then(|r| r.get(name)).and_then(quantity_value)
+}
+
+fn container_usage(metrics: &ContainerMetrics, spec: Option<&Container>) -> ContainerUsage {
+    let resources = spec.and_then(|c| c.resources.as_ref());
+    let requests = resources.and_then(|r| r.requests.as_ref());
+    let limits = resources.and_then(|r| r.limits.as_ref());
+
+    ContainerUsage {
+        name: metrics.name.clone(),
+        usage: ResourceUsage {
+            cpu: metrics
+                .usage
+                .cpu
+                .as_ref()
+                .and_then(quantity_value)
+                .unwrap_or(0.0),
+            memory: metrics
+                .usage
+                .memory
+                .as_ref()
+                .and_then(quantity_value)
+                .unwrap_or(0.0),
+            cpu_request: resource_value(requests, "cpu"),
+            cpu_limit: resource_value(limits, "cpu"),
+            memory_request: resource_value(requests, "memory"),
+            memory_limit: resource_value(limits, "memory"),
+            ..Default::default()
+        }
+        .with_percentages(),
+    }
+}
+
+// current usage of every pod in a namespace ("all" for every namespace),
+// joined with the requests and limits of their containers
+pub(crate) async fn collect_pod_usage(
+    client: Client,
+    namespace: &str,
+) -> Result<Vec<PodUsage>, String> {
+    let list_all_namespaces = namespace == "all";
+    let metrics_api = k8s_client::dynamic_api(
+        client.clone(),
+        &pod_metrics_resource(),
+        !list_all_namespaces,
+        namespace,
+    );
+    let metrics: Vec<DynamicObject> = metrics_api
+        .list(&ListParams::default())
+        .await
+        .map_err(metrics_error)?
+        .items;
+    let pods: HashMap<(String, String), Pod> =
+        k8s_client::list_resources::<Pod>(client, namespace, list_all_namespaces)
+            .await?
+            .into_iter()
+            .map(|pod| {
+                let key = (
+                    pod.metadata.namespace.clone().unwrap_or_default(),
+                    pod.metadata.name.clone().unwrap_or_default(),
+                );
+                (key, pod)
+            })
+            .collect();
+
+    let usages = metrics
+        .into_iter()
+        .filter_map(|obj| {
+            let namespace = obj.metadata.namespace.clone().unwrap_or_default();
+            let name = obj.metadata.name.clone().unwrap_or_default();
+            let data: PodMetricsData = serde_json::from_value(obj.data).ok()?;
+            let specs = pods
+                .get(&(namespace.clone(), name.clone()))
+                .and_then(|pod| pod.spec.as_ref())
+                .map(|spec| spec.containers.as_slice())
+                .unwrap_or_default();
+
+            let containers: Vec<ContainerUsage> = data
+                .containers
+                .iter()
+                .map(|c| container_usage(c, specs.iter().find(|s| s.name == c.name)))
+                .collect();
+            Some(PodUsage {
+                namespace,
+                name,
+                usage: ResourceUsage::sum(containers.iter().map(|c| &c.usage)),
+                containers,
+            })
+        })
+        .collect();
+    Ok(usages)
+}
+
+pub(crate) async fn collect_node_usage(client: Client) -> Result<Vec<NodeUsage>, String> {
+    let metrics_api: Api<DynamicObject> = Api::all_with(client.clone(), &node_metrics_resource());
+    let metrics = metrics_api
+        .list(&ListParams::default())
+        .await
+        .map_err(metrics_error)?
+        .items;
+    let nodes: HashMap<String, Node> = k8s_client::list_cluster_resources::<Node>(client)
+        .await?
+        .into_iter()
+        .map(|node| (node.metadata.name.clone().unwrap_or_default(), node))
+        .collect();
+
+    let usages = metrics
+        .into_iter()
+        .filter_map(|obj| {
+            let name = obj.metadata.name.clone().unwrap_or_default();
+            let data: NodeMetricsData = serde_json::from_value(obj.data).ok()?;
+            let allocatable = nodes
+                .get(&name)
+                .and_then(|node| node.status.as_ref())
+                .and_then(|status| status.allocatable.as_ref());
+            let cpu = data
+                .usage
+                .cpu
+                .as_ref()
+                .and_then(quantity_value)
+                .unwrap_or(0.0);
+            let memory = data
+                .usage
+                .memory
+                .as_ref()
+                .and_then(quantity_value)
+                .unwrap_or(0.0);
+            let cpu_allocatable = resource_value(allocatable, "cpu");
+            let memory_allocatable = resource_value(allocatable, "memory");
+
+            Some(NodeUsage {
+                name,
+                cpu,
+                memory,
+                cpu_allocatable,
+                memory_allocatable,
+                cpu_percent: percent(cpu, cpu_allocatable),
+                memory_percent: percent(memory, memory_allocatable),
+            })
+        })
+        .collect();
+    Ok(usages)
+}
+
+// cpu and memory usage of pods and their containers, like kubectl top pod
+#[tauri::command]
+pub async fn get_pod_metrics(
+    kubeconfig_path: String,
+    context: String,
+    namespace: String,
+    request_id: Option<String>,
+) -> Result<Vec<PodUsage>, String> {
+    let (client, _request) =
+        k8s_client::create_tracked_client(kubeconfig_path, context, request_id).await?;
+    collect_pod_usage(client, &namespace).await
+}
+
+// cpu and memory usage of nodes against their allocatable resources, like kubectl top node
+#[tauri::command]
+pub async fn get_node_metrics(
+    kubeconfig_path: String,
+    context: String,
+    request_id: Option<String>,
+) -> Result<Vec<NodeUsage>, String> {
+    let (client, _request) =
+        k8s_client::create_tracked_client(kubeconfig_path, context, request_id).await?;
+    collect_node_usage(client).await
+}
+
+// cpu and memory usage summed per namespace
+#[tauri::command]
+pub async fn get_namespace_metrics(
+    kubeconfig_path: String,
+    context: String,
+    request_id: Option<String>,
+) -> Result<Vec<NamespaceUsage>, String> {
+    let (client, _request) =
+        k8s_client::create_tracked_client(kubeconfig_path, context, request_id).await?;
+    let pods = collect_pod_usage(client, "all").await?;
+
+    let mut by_namespace: BTreeMap<String, Vec<ResourceUsage>> = BTreeMap::new();
+    for pod in pods {
+        by_namespace
+            .entry(pod.namespace)
+            .or_default()
+            .push(pod.usage);
+    }
+    Ok(by_namespace
+        .into_iter()
+        .map(|(namespace, usages)| NamespaceUsage {
+            namespace,
+            pod_count: usages.len(),
+            usage: ResourceUsage::sum(&usages),
+        })
+        .collect())
+}
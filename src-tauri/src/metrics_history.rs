@@ -0,0 +1,268 @@
+use crate::k8s_client;
+use crate::metrics::{collect_node_usage, collect_pod_usage};
+use k8s_openapi::chrono::Utc;
+use kube::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::State;
+
+const DEFAULT_INTERVAL_SECONDS: u64 = 30;
+const MIN_INTERVAL_SECONDS: u64 = 5;
+const DEFAULT_RETENTION_MINUTES: u64 = 60;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricsSample {
+    // unix timestamp in seconds
+    pub timestamp: i64,
+    // cores
+    pub cpu: f64,
+    // bytes
+    pub memory: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum MetricsKind {
+    Pod,
+    Node,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SamplerStatus {
+    pub kubeconfig_path: String,
+    pub context: String,
+    pub interval_seconds: u64,
+    pub retention_minutes: u64,
+    pub last_sample: Option<i64>,
+    pub last_error: Option<String>,
+}
+
+// samples of one context, pods are keyed by "namespace/name"
+#[derive(Default)]
+struct ContextHistory {
+    pods: HashMap<String, VecDeque<MetricsSample>>,
+    nodes: HashMap<String, VecDeque<MetricsSample>>,
+}
+
+// a context is identified by its kubeconfig too, two files may both name a context "prod"
+type ContextKey = (String, String);
+
+struct Sampler {
+    task: JoinHandle<()>,
+    status: Arc<Mutex<SamplerStatus>>,
+}
+
+// background polling of pod and node metrics for the selected contexts
+#[derive(Default)]
+pub struct MetricsHistory {
+    samplers: Mutex<HashMap<ContextKey, Sampler>>,
+    history: Arc<Mutex<HashMap<ContextKey, ContextHistory>>>,
+}
+
+fn record(
+    series: &mut HashMap<String, VecDeque<MetricsSample>>,
+    samples: Vec<(String, MetricsSample)>,
+    oldest: i64,
+) {
+    for (key, sample) in samples {
+        series.entry(key).or_default().push_back(sample);
+    }
+    // drop expired samples, and series of objects that are gone
+    series.retain(|_, samples| {
+        while samples.front().is_some_and(|s| s.timestamp < oldest) {
+            samples.pop_front();
+        }
+        !samples.is_empty()
+    });
+}
+
+async fn sample_once(
+    client: Client,
+    key: &ContextKey,
+    history: &Mutex<HashMap<ContextKey, ContextHistory>>,
+    retention_minutes: u64,
+) -> Result<i64, String> {
+    let (pods, nodes) = futures::join!(
+        collect_pod_usage(client.clone(), "all"),
+        collect_node_usage(client)
+    );
+    let (pods, nodes) = (pods?, nodes?);
+
+    let timestamp = Utc::now().timestamp();
+    let oldest = timestamp - (retention_minutes * 60) as i64;
+    let pod_samples = pods
+        .into_iter()
+        .map(|pod| {
+            let sample = MetricsSample {
+                timestamp,
+                cpu: pod.usage.cpu,
+                memory: pod.usage.memory,
+            };
+            (format!("{}/{}", pod.namespace, pod.name), sample)
+        })
+        .collect();
+    let node_samples = nodes
+        .into_iter()
+        .map(|node| {
+            let sample = MetricsSample {
+                timestamp,
+                cpu: node.cpu,
+                memory: node.memory,
+            };
+            (node.name, sample)
+        })
+        .collect();
+
+    let mut history = history.lock().map_err(|e| e.to_string())?;
+    let context_history = history.entry(key.clone()).or_default();
+    record(&mut context_history.pods, pod_samples, oldest);
+    record(&mut context_history.nodes, node_samples, oldest);
+    Ok(timestamp)
+}
+
+async fn run_sampler(
+    kubeconfig_path: String,
+    context: String,
+    history: Arc<Mutex<HashMap<ContextKey, ContextHistory>>>,
+    status: Arc<Mutex<SamplerStatus>>,
+    interval: Duration,
+    retention_minutes: u64,
+) {
+    let key = (kubeconfig_path.clone(), context.clone());
+    let mut client: Option<Client> = None;
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    loop {
+        ticker.tick().await;
+
+        if client.is_none() {
+            client = k8s_client::create_k8s_client(kubeconfig_path.clone(), context.clone())
+                .await
+                .ok();
+        }
+        let result = match client.clone() {
+            Some(client) => sample_once(client, &key, &history, retention_minutes).await,
+            None => Err(format!("Failed to connect to context {}", context)),
+        };
+
+        if let Ok(mut status) = status.lock() {
+            match result {
+                Ok(timestamp) => {
+                    status.last_sample = Some(timestamp);
+                    status.last_error = None;
+                }
+                Err(e) => {
+                    // reconnect on the next tick in case the credentials changed
+                    client = None;
+                    status.last_error = Some(e);
+                }
+            }
+        }
+    }
+}
+
+// start sampling metrics of a context in the background, restarting it with new settings if already running
+#[tauri::command]
+pub async fn start_metrics_sampler(
+    state: State<'_, MetricsHistory>,
+    kubeconfig_path: String,
+    context: String,
+    interval_seconds: Option<u64>,
+    retention_minutes: Option<u64>,
+) -> Result<SamplerStatus, String> {
+    let interval_seconds = interval_seconds
+        .unwrap_or(DEFAULT_INTERVAL_SECONDS)
+        .max(MIN_INTERVAL_SECONDS);
+    let retention_minutes = retention_minutes
+        .unwrap_or(DEFAULT_RETENTION_MINUTES)
+        .max(1);
+    let status = Arc::new(Mutex::new(SamplerStatus {
+        kubeconfig_path: kubeconfig_path.clone(),
+        context: context.clone(),
+        interval_seconds,
+        retention_minutes,
+        last_sample: None,
+        last_error: None,
+    }));
+
+    let key = (kubeconfig_path.clone(), context.clone());
+    let task = tauri::async_runtime::spawn(run_sampler(
+        kubeconfig_path,
+        context,
+        state.history.clone(),
+        status.clone(),
+        Duration::from_secs(interval_seconds),
+        retention_minutes,
+    ));
+
+    let mut samplers = state.samplers.lock().map_err(|e| e.to_string())?;
+    if let Some(previous) = samplers.insert(
+        key,
+        Sampler {
+            task,
+            status: status.clone(),
+        },
+    ) {
+        previous.task.abort();
+    }
+    let status = status.lock().map_err(|e| e.to_string())?.clone();
+    Ok(status)
+}
+
+// stop sampling a context, keeping the history collected so far
+#[tauri::command]
+pub async fn stop_metrics_sampler(
+    state: State<'_, MetricsHistory>,
+    kubeconfig_path: String,
+    context: String,
+) -> Result<(), String> {
+    let mut samplers = state.samplers.lock().map_err(|e| e.to_string())?;
+    if let Some(sampler) = samplers.remove(&(kubeconfig_path, context)) {
+        sampler.task.abort();
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn metrics_sampler_status(
+    state: State<'_, MetricsHistory>,
+) -> Result<Vec<SamplerStatus>, String> {
+    let samplers = state.samplers.lock().map_err(|e| e.to_string())?;
+    Ok(samplers
+        .values()
+        .filter_map(|sampler| sampler.status.lock().ok().map(|s| s.clone()))
+        .collect())
+}
+
+// sampled cpu and memory of a pod or node, oldest first
+#[tauri::command]
+pub async fn metrics_history(
+    state: State<'_, MetricsHistory>,
+    kubeconfig_path: String,
+    context: String,
+    kind: MetricsKind,
+    namespace: Option<String>,
+    name: String,
+) -> Result<Vec<MetricsSample>, String> {
+    let history = state.history.lock().map_err(|e| e.to_string())?;
+    let Some(context_history) = history.get(&(kubeconfig_path, context)) else {
+        return Ok(vec![]);
+    };
+
+    let samples = match kind {
+        MetricsKind::Pod => {
+            let key = format!("{}/{}", namespace.unwrap_or_default(), name);
+            context_history.pods.get(&key)
+        }
+        MetricsKind::Node => context_history.nodes.get(&name),
+    };
+    Ok(samples
+        .map(|samples| samples.iter().copied().collect())
+        .unwrap_or_default())
+}
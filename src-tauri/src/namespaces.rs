@@ -1,6 +1,30 @@
+use crate::app_config;
 use crate::k8s_client;
+use crate::k8s_config::load_kubeconfig;
+use k8s_openapi::api::authorization::v1::{
+    ResourceRule, SelfSubjectRulesReview, SelfSubjectRulesReviewSpec,
+};
 use k8s_openapi::api::core::v1::Namespace;
-use kube::Api;
+use kube::api::{ListParams, PostParams};
+use kube::{Api, Client};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+// api groups of the rules every authenticated user has to review their own access
+const SELF_REVIEW_GROUPS: [&str; 2] = ["authorization.k8s.io", "authentication.k8s.io"];
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextNamespaces {
+    // the namespace set on the kubeconfig context, "default" when none is set
+    pub default_namespace: String,
+    pub last_namespace: Option<String>,
+    pub namespaces: Vec<String>,
+    // "cluster" when listed from the API, otherwise "fallback"
+    pub source: String,
+    // why listing namespaces failed, and which fallback checks failed, when falling back
+    pub error: Option<String>,
+}
 
 // list all pods in a namespace
 #[tauri::command]
@@ -18,3 +42,188 @@ pub async fn list_namespaces(
         .map_err(|e| e.to_string())?;
     Ok(namespaces_list.items)
 }
+
+pub(crate) fn context_default_namespace(kubeconfig_path: &str, context: &str) -> Option<String> {
+    load_kubeconfig(kubeconfig_path)
+        .ok()?
+        .contexts
+        .into_iter()
+        .find(|c| c.name == context)
+        .and_then(|c| c.context)
+        .and_then(|c| c.namespace)
+        .filter(|ns| !ns.is_empty())
+}
+
+// namespaces set on the other contexts of the same cluster and user, they usually are
+// namespaces the identity works in
+fn sibling_context_namespaces(kubeconfig_path: &str, context: &str) -> BTreeSet<String> {
+    let Ok(kubeconfig) = load_kubeconfig(kubeconfig_path) else {
+        return BTreeSet::new();
+    };
+    let target = |name: &str| {
+        kubeconfig
+            .contexts
+            .iter()
+            .find(|c| c.name == name)
+            .and_then(|c| c.context.as_ref())
+            .map(|c| (c.cluster.clone(), c.user.clone()))
+    };
+    let Some(own) = target(context) else {
+        return BTreeSet::new();
+    };
+    kubeconfig
+        .contexts
+        .iter()
+        .filter(|c| c.name != context && target(&c.name).as_ref() == Some(&own))
+        .filter_map(|c| c.context.as_ref().and_then(|c| c.namespace.clone()))
+        .filter(|ns| !ns.is_empty())
+        .collect()
+}
+
+// whether the rules grant anything beyond reviewing one's own access
+fn grants_namespace_access(rules: &[ResourceRule]) -> bool {
+    rules.iter().any(|rule| {
+        rule.api_groups
+            .iter()
+            .flatten()
+            .any(|group| !SELF_REVIEW_GROUPS.contains(&group.as_str()))
+    })
+}
+
+// a SelfSubjectRulesReview needs no permission, unlike listing RoleBindings
+async fn has_namespace_access(client: &Client, namespace: &str) -> Result<bool, String> {
+    let reviews: Api<SelfSubjectRulesReview> = Api::all(client.clone());
+    let review = SelfSubjectRulesReview {
+        spec: SelfSubjectRulesReviewSpec {
+            namespace: Some(namespace.to_string()),
+        },
+        ..Default::default()
+    };
+    let response = reviews
+        .create(&PostParams::default(), &review)
+        .await
+        .map_err(|e| format!("Failed to review access to namespace {}: {}", namespace, e))?;
+    Ok(response
+        .status
+        .is_some_and(|status| grants_namespace_access(&status.resource_rules)))
+}
+
+// namespaces for the selector: all of them when the identity may list namespaces, otherwise
+// the context's namespace, the configured ones and the namespaces of sibling contexts the
+// identity has access to
+#[tauri::command]
+pub async fn context_namespaces(
+    kubeconfig_path: String,
+    context: String,
+) -> Result<ContextNamespaces, String> {
+    let settings = app_config::context_settings(&kubeconfig_path, &context);
+    let default_namespace = context_default_namespace(&kubeconfig_path, &context)
+        .unwrap_or_else(|| "default".to_string());
+    let siblings = sibling_context_namespaces(&kubeconfig_path, &context);
+    let client = k8s_client::create_k8s_client(kubeconfig_path, context).await?;
+
+    let namespaces: Api<Namespace> = Api::all(client.clone());
+    let (namespaces, source, error) = match namespaces.list(&ListParams::default()).await {
+        Ok(list) => {
+            let names = list
+                .items
+                .into_iter()
+                .filter_map(|ns| ns.metadata.name)
+                .collect::<Vec<_>>();
+            (names, "cluster", None)
+        }
+        Err(kube::Error::Api(response)) if response.code == 403 => {
+            let mut names: BTreeSet<String> = settings.namespaces.iter().cloned().collect();
+            names.insert(default_namespace.clone());
+            names.extend(settings.last_namespace.clone());
+            // inferred namespaces are only offered when the identity can do something there
+            let mut errors = vec![response.message];
+            let inferred: Vec<String> = siblings.difference(&names).cloned().collect();
+            for namespace in inferred {
+                match has_namespace_access(&client, &namespace).await {
+                    Ok(true) => {
+                        names.insert(namespace);
+                    }
+                    Ok(false) => {}
+                    Err(e) => errors.push(e),
+                }
+            }
+            (
+                names.into_iter().collect(),
+                "fallback",
+                Some(errors.join("; ")),
+            )
+        }
+        Err(e) => return Err(e.to_string()),
+    };
+
+    Ok(ContextNamespaces {
+        default_namespace,
+        last_namespace: settings.last_namespace,
+        namespaces,
+        source: source.to_string(),
+        error,
+    })
+}
+
+// remember the namespace selected for a context
+#[tauri::command]
+pub async fn set_last_namespace(
+    kubeconfig_path: String,
+    context: String,
+    namespace: Option<String>,
+) -> Result<(), String> {
+    app_config::update_context(&kubeconfig_path, &context, |settings| {
+        settings.last_namespace = namespace.filter(|ns| !ns.is_empty())
+    })?;
+    Ok(())
+}
+
+// namespaces to offer for a context whose identity cannot list namespaces
+#[tauri::command]
+pub async fn set_context_namespaces(
+    kubeconfig_path: String,
+    context: String,
+    namespaces: Vec<String>,
+) -> Result<(), String> {
+    app_config::update_context(&kubeconfig_path, &context, |settings| {
+        settings.namespaces = namespaces
+            .into_iter()
+            .map(|ns| ns.trim().to_string())
+            .filter(|ns| !ns.is_empty())
+            .collect()
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(groups: &[&str], resources: &[&str]) -> ResourceRule {
+        ResourceRule {
+            verbs: vec!["create".to_string()],
+            api_groups: Some(groups.iter().map(|g| g.to_string()).collect()),
+            resources: Some(resources.iter().map(|r| r.to_string()).collect()),
+            resource_names: None,
+        }
+    }
+
+    #[test]
+    fn self_review_rules_grant_no_access() {
+        let basic = [
+            rule(
+                &["authorization.k8s.io"],
+                &["selfsubjectaccessreviews", "selfsubjectrulesreviews"],
+            ),
+            rule(&["authentication.k8s.io"], &["selfsubjectreviews"]),
+        ];
+        assert!(!grants_namespace_access(&basic));
+        assert!(!grants_namespace_access(&[]));
+
+        let mut with_pods = basic.to_vec();
+        with_pods.push(rule(&[""], &["pods"]));
+        assert!(grants_namespace_access(&with_pods));
+        assert!(grants_namespace_access(&[rule(&["*"], &["*"])]));
+    }
+}
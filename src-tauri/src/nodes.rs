@@ -1,7 +1,30 @@
+use crate::audit::AuditEvent;
 use crate::k8s_client;
-use k8s_openapi::api::core::v1::{Node, Pod};
-use kube::api::ListParams;
+use crate::pods::{wait_for_container_running, DebugProfile};
+use crate::protection::Action;
+use crate::sessions::SessionManager;
+use k8s_openapi::api::core::v1::{
+    Container, HostPathVolumeSource, Node, Pod, PodSpec, Taint, Toleration, Volume, VolumeMount,
+};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, Time};
+use k8s_openapi::chrono::Utc;
+use kube::api::{AttachParams, DeleteParams, ListParams, Patch, PatchParams, PostParams};
 use kube::Api;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use tauri::{AppHandle, State};
+
+const NODE_DEBUG_CONTAINER: &str = "debugger";
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EvictionCandidate {
+    pub namespace: String,
+    pub name: String,
+    // None when the pod is evicted immediately, otherwise its tolerationSeconds
+    pub evicted_after_seconds: Option<i64>,
+}
 
 #[tauri::command]
 pub async fn list_pods_on_node(
@@ -23,13 +46,28 @@ pub async fn debug_node(
     context: String,
     node_name: String,
     image: String,
+    confirmation: Option<String>,
 ) -> Result<(), String> {
-    let cmd_string = format!(
-        "--kubeconfig {} --context {} debug node/{} -it --image {}",
-        kubeconfig_path, context, node_name, image,
+    let audit = AuditEvent::new(
+        &kubeconfig_path,
+        &context,
+        Action::Debug,
+        None,
+        Some("Node"),
+        Some(&node_name),
+        json!({ "image": image }),
     );
-    crate::kubectl::run_kubectl_command(&cmd_string)?;
-    Ok(())
+    audit
+        .run(confirmation.as_deref(), async move {
+            let kubeconfig = crate::k8s_config::kubectl_kubeconfig(&kubeconfig_path, &context)?;
+            let cmd_string = format!(
+                "--kubeconfig {} --context {} debug node/{} -it --image {}",
+                kubeconfig, context, node_name, image,
+            );
+            crate::kubectl::run_kubectl_command(&cmd_string)?;
+            Ok(())
+        })
+        .await
 }
 
 // cordon a node by name
@@ -38,11 +76,25 @@ pub async fn cordon_node(
     kubeconfig_path: String,
     context: String,
     node_name: String,
+    confirmation: Option<String>,
 ) -> Result<(), String> {
-    let client = k8s_client::create_k8s_client(kubeconfig_path, context).await?;
-    let nodes: Api<Node> = Api::all(client);
-    nodes.cordon(&node_name).await.map_err(|e| e.to_string())?;
-    Ok(())
+    let audit = AuditEvent::new(
+        &kubeconfig_path,
+        &context,
+        Action::Cordon,
+        None,
+        Some("Node"),
+        Some(&node_name),
+        json!({}),
+    );
+    audit
+        .run(confirmation.as_deref(), async move {
+            let client = k8s_client::create_k8s_client(kubeconfig_path, context).await?;
+            let nodes: Api<Node> = Api::all(client);
+            nodes.cordon(&node_name).await.map_err(|e| e.to_string())?;
+            Ok(())
+        })
+        .await
 }
 
 // drain a node by name
@@ -51,13 +103,28 @@ pub async fn drain_node(
     kubeconfig_path: String,
     context: String,
     node_name: String,
+    confirmation: Option<String>,
 ) -> Result<(), String> {
-    let cmd_string = format!(
-        "--kubeconfig {} --context {} drain {} --ignore-daemonsets",
-        kubeconfig_path, context, node_name,
+    let audit = AuditEvent::new(
+        &kubeconfig_path,
+        &context,
+        Action::Drain,
+        None,
+        Some("Node"),
+        Some(&node_name),
+        json!({}),
     );
-    crate::kubectl::run_kubectl_command(&cmd_string)?;
-    Ok(())
+    audit
+        .run(confirmation.as_deref(), async move {
+            let kubeconfig = crate::k8s_config::kubectl_kubeconfig(&kubeconfig_path, &context)?;
+            let cmd_string = format!(
+                "--kubeconfig {} --context {} drain {} --ignore-daemonsets",
+                kubeconfig, context, node_name,
+            );
+            crate::kubectl::run_kubectl_command(&cmd_string)?;
+            Ok(())
+        })
+        .await
 }
 
 // uncordon a node by name
@@ -66,12 +133,697 @@ pub async fn uncordon_node(
     kubeconfig_path: String,
     context: String,
     node_name: String,
+    confirmation: Option<String>,
 ) -> Result<(), String> {
+    let audit = AuditEvent::new(
+        &kubeconfig_path,
+        &context,
+        Action::Uncordon,
+        None,
+        Some("Node"),
+        Some(&node_name),
+        json!({}),
+    );
+    audit
+        .run(confirmation.as_deref(), async move {
+            let client = k8s_client::create_k8s_client(kubeconfig_path, context).await?;
+            let nodes: Api<Node> = Api::all(client);
+            nodes
+                .uncordon(&node_name)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(())
+        })
+        .await
+}
+
+// escape a map key so it can be used as a json pointer segment
+pub(crate) fn json_pointer_escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+// json patch operations that set and remove keys of a metadata map (labels or annotations)
+pub(crate) fn metadata_map_ops(
+    field: &str,
+    current: Option<&BTreeMap<String, String>>,
+    set: &BTreeMap<String, String>,
+    remove: &[String],
+) -> Vec<Value> {
+    let path = format!("/metadata/{}", field);
+    match current {
+        // the map does not exist yet, so it has to be added as a whole
+        None => {
+            if set.is_empty() {
+                vec![]
+            } else {
+                vec![json!({ "op": "add", "path": path, "value": set })]
+            }
+        }
+        Some(current) => {
+            let mut ops: Vec<Value> = set
+                .iter()
+                .map(|(k, v)| {
+                    json!({
+                        "op": "add",
+                        "path": format!("{}/{}", path, json_pointer_escape(k)),
+                        "value": v,
+                    })
+                })
+                .collect();
+            ops.extend(
+                remove
+                    .iter()
+                    .filter(|k| current.contains_key(*k) && !set.contains_key(*k))
+                    .map(|k| {
+                        json!({
+                            "op": "remove",
+                            "path": format!("{}/{}", path, json_pointer_escape(k)),
+                        })
+                    }),
+            );
+            ops
+        }
+    }
+}
+
+// node-debugger-<node>-<suffix>, with the node name shortened to keep within the 63 character limit
+fn node_debug_pod_name(node_name: &str, timestamp: i64) -> String {
+    let suffix = format!("{:x}", timestamp);
+    let max_node_len = 63 - "node-debugger-".len() - suffix.len() - 1;
+    let node_part: String = node_name.chars().take(max_node_len).collect();
+    format!(
+        "node-debugger-{}-{}",
+        node_part.trim_end_matches(['-', '.']),
+        suffix
+    )
+}
+
+// check if a toleration matches a taint, following the scheduler's rules
+pub(crate) fn toleration_matches(toleration: &Toleration, taint: &Taint) -> bool {
+    if let Some(effect) = toleration.effect.as_deref() {
+        if !effect.is_empty() && effect != taint.effect {
+            return false;
+        }
+    }
+
+    match toleration.operator.as_deref() {
+        Some("Exists") => match toleration.key.as_deref() {
+            None | Some("") => true,
+            Some(key) => key == taint.key,
+        },
+        // operator defaults to Equal
+        _ => {
+            toleration.key.as_deref() == Some(taint.key.as_str())
+                && toleration.value.as_deref().unwrap_or_default()
+                    == taint.value.as_deref().unwrap_or_default()
+        }
+    }
+}
+
+// find the toleration of a pod that matches a taint, if any
+pub(crate) fn find_toleration<'a>(pod: &'a Pod, taint: &Taint) -> Option<&'a Toleration> {
+    pod.spec
+        .as_ref()
+        .and_then(|spec| spec.tolerations.as_ref())
+        .and_then(|tolerations| tolerations.iter().find(|t| toleration_matches(t, taint)))
+}
+
+// fetch the node and apply a json patch guarded by its resourceVersion
+async fn patch_node<F>(
+    kubeconfig_path: String,
+    context: String,
+    node_name: &str,
+    resource_version: Option<String>,
+    build_ops: F,
+) -> Result<Node, String>
+where
+    F: FnOnce(&Node) -> Result<Vec<Value>, String>,
+{
     let client = k8s_client::create_k8s_client(kubeconfig_path, context).await?;
     let nodes: Api<Node> = Api::all(client);
+    let node = nodes.get(node_name).await.map_err(|e| e.to_string())?;
+
+    // prefer the version the caller has seen, so concurrent edits are rejected
+    let resource_version = match resource_version {
+        Some(rv) if !rv.is_empty() => rv,
+        _ => node
+            .metadata
+            .resource_version
+            .clone()
+            .ok_or_else(|| format!("Node {} has no resourceVersion", node_name))?,
+    };
+
+    let ops = build_ops(&node)?;
+    if ops.is_empty() {
+        return Ok(node);
+    }
+
+    let mut all_ops = vec![json!({
+        "op": "test",
+        "path": "/metadata/resourceVersion",
+        "value": resource_version,
+    })];
+    all_ops.extend(ops);
+    let patch: json_patch::Patch =
+        serde_json::from_value(Value::Array(all_ops)).map_err(|e| e.to_string())?;
+
     nodes
-        .uncordon(&node_name)
+        .patch(
+            node_name,
+            &PatchParams::default(),
+            &Patch::Json::<()>(patch),
+        )
         .await
-        .map_err(|e| e.to_string())?;
-    Ok(())
+        .map_err(|e| format!("Failed to patch node {}: {}", node_name, e))
+}
+
+// add a taint to a node, or update the taint with the same key and effect
+#[tauri::command]
+pub async fn set_node_taint(
+    kubeconfig_path: String,
+    context: String,
+    node_name: String,
+    taint: Taint,
+    resource_version: Option<String>,
+    confirmation: Option<String>,
+) -> Result<Node, String> {
+    let audit = AuditEvent::new(
+        &kubeconfig_path,
+        &context,
+        Action::Taint,
+        None,
+        Some("Node"),
+        Some(&node_name),
+        json!({ "taint": taint }),
+    );
+    audit
+        .run(confirmation.as_deref(), async move {
+            if taint.key.is_empty() {
+                return Err("Taint key cannot be empty".to_string());
+            }
+            if !["NoSchedule", "PreferNoSchedule", "NoExecute"].contains(&taint.effect.as_str()) {
+                return Err(format!("Invalid taint effect: {}", taint.effect));
+            }
+
+            let mut taint = taint;
+            if taint.effect == "NoExecute" && taint.time_added.is_none() {
+                taint.time_added = Some(Time(Utc::now()));
+            }
+
+            patch_node(
+                kubeconfig_path,
+                context,
+                &node_name,
+                resource_version,
+                |node| {
+                    let mut taints = node
+                        .spec
+                        .as_ref()
+                        .and_then(|spec| spec.taints.clone())
+                        .unwrap_or_default();
+                    match taints
+                        .iter_mut()
+                        .find(|t| t.key == taint.key && t.effect == taint.effect)
+                    {
+                        Some(existing) => *existing = taint,
+                        None => taints.push(taint),
+                    }
+                    Ok(vec![
+                        json!({ "op": "add", "path": "/spec/taints", "value": taints }),
+                    ])
+                },
+            )
+            .await
+        })
+        .await
+}
+
+// remove a taint from a node by key and effect
+#[tauri::command]
+pub async fn remove_node_taint(
+    kubeconfig_path: String,
+    context: String,
+    node_name: String,
+    key: String,
+    effect: String,
+    resource_version: Option<String>,
+    confirmation: Option<String>,
+) -> Result<Node, String> {
+    let audit = AuditEvent::new(
+        &kubeconfig_path,
+        &context,
+        Action::Taint,
+        None,
+        Some("Node"),
+        Some(&node_name),
+        json!({ "key": key, "effect": effect }),
+    );
+    audit
+        .run(confirmation.as_deref(), async move {
+            patch_node(
+                kubeconfig_path,
+                context,
+                &node_name,
+                resource_version,
+                |node| {
+                    let taints = node
+                        .spec
+                        .as_ref()
+                        .and_then(|spec| spec.taints.clone())
+                        .unwrap_or_default();
+                    let remaining: Vec<Taint> = taints
+                        .iter()
+                        .filter(|t| !(t.key == key && t.effect == effect))
+                        .cloned()
+                        .collect();
+                    if remaining.len() == taints.len() {
+                        return Err(format!(
+                            "Taint {}:{} not found on node {}",
+                            key, effect, node_name
+                        ));
+                    }
+                    Ok(vec![
+                        json!({ "op": "add", "path": "/spec/taints", "value": remaining }),
+                    ])
+                },
+            )
+            .await
+        })
+        .await
+}
+
+// add, update and remove labels of a node
+#[tauri::command]
+pub async fn update_node_labels(
+    kubeconfig_path: String,
+    context: String,
+    node_name: String,
+    labels: BTreeMap<String, String>,
+    remove: Vec<String>,
+    resource_version: Option<String>,
+    confirmation: Option<String>,
+) -> Result<Node, String> {
+    let audit = AuditEvent::new(
+        &kubeconfig_path,
+        &context,
+        Action::UpdateMetadata,
+        None,
+        Some("Node"),
+        Some(&node_name),
+        json!({ "labels": labels, "remove": remove }),
+    );
+    audit
+        .run(confirmation.as_deref(), async move {
+            patch_node(
+                kubeconfig_path,
+                context,
+                &node_name,
+                resource_version,
+                |node| {
+                    Ok(metadata_map_ops(
+                        "labels",
+                        node.metadata.labels.as_ref(),
+                        &labels,
+                        &remove,
+                    ))
+                },
+            )
+            .await
+        })
+        .await
+}
+
+// add, update and remove annotations of a node
+#[tauri::command]
+pub async fn update_node_annotations(
+    kubeconfig_path: String,
+    context: String,
+    node_name: String,
+    annotations: BTreeMap<String, String>,
+    remove: Vec<String>,
+    resource_version: Option<String>,
+    confirmation: Option<String>,
+) -> Result<Node, String> {
+    let audit = AuditEvent::new(
+        &kubeconfig_path,
+        &context,
+        Action::UpdateMetadata,
+        None,
+        Some("Node"),
+        Some(&node_name),
+        json!({ "annotations": annotations, "remove": remove }),
+    );
+    audit
+        .run(confirmation.as_deref(), async move {
+            patch_node(
+                kubeconfig_path,
+                context,
+                &node_name,
+                resource_version,
+                |node| {
+                    Ok(metadata_map_ops(
+                        "annotations",
+                        node.metadata.annotations.as_ref(),
+                        &annotations,
+                        &remove,
+                    ))
+                },
+            )
+            .await
+        })
+        .await
+}
+
+// list the running pods on a node that a new NoExecute taint would evict
+#[tauri::command]
+pub async fn preview_taint_eviction(
+    kubeconfig_path: String,
+    context: String,
+    node_name: String,
+    taint: Taint,
+) -> Result<Vec<EvictionCandidate>, String> {
+    if taint.effect != "NoExecute" {
+        // only NoExecute taints evict pods that are already running
+        return Ok(vec![]);
+    }
+
+    let pods = list_pods_on_node(kubeconfig_path, context, node_name).await?;
+    let candidates = pods
+        .iter()
+        .filter(|pod| {
+            let phase = pod.status.as_ref().and_then(|s| s.phase.as_deref());
+            !matches!(phase, Some("Succeeded") | Some("Failed"))
+        })
+        .filter_map(|pod| {
+            let evicted_after_seconds = match find_toleration(pod, &taint) {
+                // tolerated forever
+                Some(toleration) if toleration.toleration_seconds.is_none() => return None,
+                Some(toleration) => toleration.toleration_seconds,
+                None => None,
+            };
+            Some(EvictionCandidate {
+                namespace: pod.metadata.namespace.clone().unwrap_or_default(),
+                name: pod.metadata.name.clone().unwrap_or_default(),
+                evicted_after_seconds,
+            })
+        })
+        .collect();
+    Ok(candidates)
+}
+
+// a pod pinned to the node sharing its namespaces, with the host filesystem at /host
+fn node_debug_pod(name: &str, node_name: &str, image: String, profile: DebugProfile) -> Pod {
+    Pod {
+        metadata: ObjectMeta {
+            name: Some(name.to_string()),
+            labels: Some(BTreeMap::from([(
+                "app.kubernetes.io/managed-by".to_string(),
+                "kubeintel".to_string(),
+            )])),
+            ..Default::default()
+        },
+        spec: Some(PodSpec {
+            node_name: Some(node_name.to_string()),
+            host_pid: Some(true),
+            host_network: Some(true),
+            host_ipc: Some(true),
+            restart_policy: Some("Never".to_string()),
+            // run on the node whatever taints it has
+            tolerations: Some(vec![Toleration {
+                operator: Some("Exists".to_string()),
+                ..Default::default()
+            }]),
+            containers: vec![Container {
+                name: NODE_DEBUG_CONTAINER.to_string(),
+                image: Some(image),
+                stdin: Some(true),
+                tty: Some(true),
+                security_context: profile.security_context(),
+                volume_mounts: Some(vec![VolumeMount {
+                    name: "host-root".to_string(),
+                    mount_path: "/host".to_string(),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            }],
+            volumes: Some(vec![Volume {
+                name: "host-root".to_string(),
+                host_path: Some(HostPathVolumeSource {
+                    path: "/".to_string(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+// debug a node from a privileged pod running on it, returning the pod name
+// the pod is deleted once the session ends or the app quits
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn start_node_debug_session(
+    app: AppHandle,
+    sessions: State<'_, SessionManager>,
+    kubeconfig_path: String,
+    context: String,
+    node_name: String,
+    image: String,
+    profile: Option<DebugProfile>,
+    namespace: Option<String>,
+    session_id: String,
+    confirmation: Option<String>,
+) -> Result<String, String> {
+    let audit = AuditEvent::new(
+        &kubeconfig_path,
+        &context,
+        Action::Debug,
+        namespace.as_deref(),
+        Some("Node"),
+        Some(&node_name),
+        json!({ "image": image, "profile": profile }),
+    );
+    audit
+        .run(confirmation.as_deref(), async move {
+            let profile = profile.unwrap_or_default();
+            if !matches!(
+                profile,
+                DebugProfile::General | DebugProfile::Sysadmin | DebugProfile::Netadmin
+            ) {
+                return Err(format!(
+                    "Profile {:?} is not supported for node debugging",
+                    profile
+                ));
+            }
+            // checked before the privileged pod exists, start cleans up if the id is taken later
+            if sessions.contains(&session_id) {
+                return Err(format!("Session {} already exists", session_id));
+            }
+
+            let client = k8s_client::create_k8s_client(kubeconfig_path, context).await?;
+            let namespace = namespace
+                .filter(|n| !n.is_empty())
+                .unwrap_or_else(|| "default".to_string());
+            let pods: Api<Pod> = Api::namespaced(client, &namespace);
+
+            let pod_name = node_debug_pod_name(&node_name, Utc::now().timestamp_millis());
+            pods.create(
+                &PostParams::default(),
+                &node_debug_pod(&pod_name, &node_name, image, profile),
+            )
+            .await
+            .map_err(|e| format!("Failed to create debug pod on node {}: {}", node_name, e))?;
+
+            let cleanup_pods = pods.clone();
+            let cleanup_name = pod_name.clone();
+            let cleanup = async move {
+                let dp = DeleteParams {
+                    grace_period_seconds: Some(0),
+                    ..Default::default()
+                };
+                let _ = cleanup_pods.delete(&cleanup_name, &dp).await;
+            };
+
+            let attached =
+                match wait_for_container_running(&pods, &pod_name, NODE_DEBUG_CONTAINER).await {
+                    Ok(()) => pods
+                        .attach(
+                            &pod_name,
+                            &AttachParams::interactive_tty().container(NODE_DEBUG_CONTAINER),
+                        )
+                        .await
+                        .map_err(|e| format!("Failed to attach to debug pod: {}", e)),
+                    Err(e) => Err(e),
+                };
+
+            match attached {
+                Ok(process) => {
+                    sessions.start(app, session_id, process, Some(Box::pin(cleanup)))?;
+                    Ok(pod_name)
+                }
+                Err(e) => {
+                    // do not leave a privileged pod behind
+                    cleanup.await;
+                    Err(e)
+                }
+            }
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn taint(key: &str, value: Option<&str>, effect: &str) -> Taint {
+        Taint {
+            key: key.to_string(),
+            value: value.map(str::to_string),
+            effect: effect.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn toleration(
+        key: Option<&str>,
+        operator: Option<&str>,
+        value: Option<&str>,
+        effect: Option<&str>,
+    ) -> Toleration {
+        Toleration {
+            key: key.map(str::to_string),
+            operator: operator.map(str::to_string),
+            value: value.map(str::to_string),
+            effect: effect.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn equal_toleration_needs_key_and_value() {
+        let taint = taint("dedicated", Some("gpu"), "NoSchedule");
+        assert!(toleration_matches(
+            &toleration(Some("dedicated"), None, Some("gpu"), None),
+            &taint
+        ));
+        assert!(toleration_matches(
+            &toleration(
+                Some("dedicated"),
+                Some("Equal"),
+                Some("gpu"),
+                Some("NoSchedule")
+            ),
+            &taint
+        ));
+        assert!(!toleration_matches(
+            &toleration(Some("dedicated"), None, Some("cpu"), None),
+            &taint
+        ));
+        assert!(!toleration_matches(
+            &toleration(Some("other"), None, Some("gpu"), None),
+            &taint
+        ));
+    }
+
+    #[test]
+    fn exists_toleration_ignores_value() {
+        let taint = taint("dedicated", Some("gpu"), "NoSchedule");
+        assert!(toleration_matches(
+            &toleration(Some("dedicated"), Some("Exists"), None, None),
+            &taint
+        ));
+        // an empty key with Exists tolerates every taint
+        assert!(toleration_matches(
+            &toleration(None, Some("Exists"), None, None),
+            &taint
+        ));
+        assert!(!toleration_matches(
+            &toleration(Some("other"), Some("Exists"), None, None),
+            &taint
+        ));
+    }
+
+    #[test]
+    fn toleration_effect_must_match() {
+        let taint = taint("dedicated", None, "NoExecute");
+        assert!(!toleration_matches(
+            &toleration(Some("dedicated"), Some("Exists"), None, Some("NoSchedule")),
+            &taint
+        ));
+        assert!(toleration_matches(
+            &toleration(Some("dedicated"), Some("Exists"), None, Some("")),
+            &taint
+        ));
+        assert!(toleration_matches(
+            &toleration(Some("dedicated"), None, None, Some("NoExecute")),
+            &taint
+        ));
+    }
+
+    fn map(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn missing_map_is_added_whole() {
+        let set = map(&[("team", "a")]);
+        assert_eq!(
+            metadata_map_ops("labels", None, &set, &["old".to_string()]),
+            vec![json!({ "op": "add", "path": "/metadata/labels", "value": { "team": "a" } })]
+        );
+        assert!(
+            metadata_map_ops("labels", None, &BTreeMap::new(), &["old".to_string()]).is_empty()
+        );
+    }
+
+    #[test]
+    fn existing_map_gets_escaped_key_ops() {
+        let current = map(&[
+            ("app.kubernetes.io/name", "web"),
+            ("keep", "x"),
+            ("team", "a"),
+        ]);
+        let set = map(&[("team", "b")]);
+        let remove = vec![
+            "app.kubernetes.io/name".to_string(),
+            "missing".to_string(),
+            // set wins over remove
+            "team".to_string(),
+        ];
+        assert_eq!(
+            metadata_map_ops("annotations", Some(&current), &set, &remove),
+            vec![
+                json!({ "op": "add", "path": "/metadata/annotations/team", "value": "b" }),
+                json!({ "op": "remove", "path": "/metadata/annotations/app.kubernetes.io~1name" }),
+            ]
+        );
+    }
+
+    #[test]
+    fn node_debug_pod_name_fits_limit() {
+        assert_eq!(
+            node_debug_pod_name("node-1", 0x18f),
+            "node-debugger-node-1-18f"
+        );
+        let long = "gke-production-cluster-default-pool-1a2b3c4d-xyz9.us-central1-a.internal";
+        let name = node_debug_pod_name(long, Utc::now().timestamp_millis());
+        assert!(name.len() <= 63, "{}", name);
+        assert!(name.starts_with("node-debugger-gke-production-cluster-"));
+        // no separator is left dangling before the suffix
+        let name = node_debug_pod_name(&format!("{}-.rest", "n".repeat(36)), 0x19a00000000);
+        assert_eq!(
+            name,
+            format!("node-debugger-{}-19a00000000", "n".repeat(36))
+        );
+    }
+
+    #[test]
+    fn json_pointer_escapes_tilde_before_slash() {
+        assert_eq!(json_pointer_escape("a~/b"), "a~0~1b");
+    }
 }
@@ -0,0 +1,429 @@
+use crate::k8s_client;
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, StatefulSet};
+use k8s_openapi::api::core::v1::{ComponentStatus, Event, Node, Pod};
+use k8s_openapi::chrono::{DateTime, Duration, Utc};
+use kube::api::ListParams;
+use kube::{Api, Client};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerVersion {
+    pub git_version: String,
+    pub platform: String,
+    pub go_version: String,
+    pub build_date: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeSummary {
+    pub total: usize,
+    pub ready: usize,
+    pub not_ready: usize,
+    pub unschedulable: usize,
+    // node count per role, "<none>" for nodes without a role label
+    pub roles: BTreeMap<String, usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PodSummary {
+    pub total: usize,
+    pub phases: BTreeMap<String, usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FailingWorkload {
+    pub kind: String,
+    pub namespace: String,
+    pub name: String,
+    pub desired: i32,
+    pub ready: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WarningEventSummary {
+    pub total: usize,
+    pub by_reason: BTreeMap<String, usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthCheck {
+    pub name: String,
+    pub ok: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiServerReadiness {
+    pub ready: bool,
+    pub checks: Vec<HealthCheck>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentHealth {
+    pub name: String,
+    pub healthy: bool,
+    pub message: Option<String>,
+}
+
+// a part of the overview that could not be loaded, e.g. because of missing permissions
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SectionError {
+    pub section: String,
+    pub error: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ClusterOverview {
+    pub version: Option<ServerVersion>,
+    pub nodes: Option<NodeSummary>,
+    pub pods: Option<PodSummary>,
+    pub failing_workloads: Option<Vec<FailingWorkload>>,
+    pub warning_events: Option<WarningEventSummary>,
+    pub readiness: Option<ApiServerReadiness>,
+    pub components: Option<Vec<ComponentHealth>>,
+    pub errors: Vec<SectionError>,
+}
+
+pub(crate) fn node_is_ready(node: &Node) -> bool {
+    node.status
+        .as_ref()
+        .and_then(|status| status.conditions.as_ref())
+        .and_then(|conditions| conditions.iter().find(|c| c.type_ == "Ready"))
+        .is_some_and(|c| c.status == "True")
+}
+
+// roles from the node-role.kubernetes.io/<role> labels, like kubectl get nodes
+pub(crate) fn node_roles(node: &Node) -> Vec<String> {
+    let Some(labels) = node.metadata.labels.as_ref() else {
+        return vec![];
+    };
+    labels
+        .iter()
+        .filter_map(|(key, value)| {
+            if let Some(role) = key.strip_prefix("node-role.kubernetes.io/") {
+                Some(role.to_string())
+            } else if key == "kubernetes.io/role" {
+                Some(value.clone())
+            } else {
+                None
+            }
+        })
+        .filter(|role| !role.is_empty())
+        .collect()
+}
+
+// when an event was last seen, whichever of the timestamps is set
+pub(crate) fn event_time(event: &Event) -> Option<DateTime<Utc>> {
+    event
+        .series
+        .as_ref()
+        .and_then(|series| series.last_observed_time.as_ref())
+        .map(|t| t.0)
+        .or_else(|| event.last_timestamp.as_ref().map(|t| t.0))
+        .or_else(|| event.event_time.as_ref().map(|t| t.0))
+        .or_else(|| event.metadata.creation_timestamp.as_ref().map(|t| t.0))
+}
+
+async fn server_version(client: Client) -> Result<ServerVersion, String> {
+    let info = client
+        .apiserver_version()
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(ServerVersion {
+        git_version: info.git_version,
+        platform: info.platform,
+        go_version: info.go_version,
+        build_date: info.build_date,
+    })
+}
+
+async fn node_summary(client: Client) -> Result<NodeSummary, String> {
+    let nodes = k8s_client::list_cluster_resources::<Node>(client).await?;
+    let mut summary = NodeSummary {
+        total: nodes.len(),
+        ..Default::default()
+    };
+    for node in &nodes {
+        if node_is_ready(node) {
+            summary.ready += 1;
+        } else {
+            summary.not_ready += 1;
+        }
+        if node
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.unschedulable)
+            .unwrap_or(false)
+        {
+            summary.unschedulable += 1;
+        }
+        let roles = node_roles(node);
+        if roles.is_empty() {
+            *summary.roles.entry("<none>".to_string()).or_default() += 1;
+        }
+        for role in roles {
+            *summary.roles.entry(role).or_default() += 1;
+        }
+    }
+    Ok(summary)
+}
+
+async fn pod_summary(client: Client) -> Result<PodSummary, String> {
+    let pods = k8s_client::list_resources::<Pod>(client, "all", true).await?;
+    let mut summary = PodSummary {
+        total: pods.len(),
+        ..Default::default()
+    };
+    for pod in &pods {
+        let phase = pod
+            .status
+            .as_ref()
+            .and_then(|status| status.phase.clone())
+            .unwrap_or_else(|| "Unknown".to_string());
+        *summary.phases.entry(phase).or_default() += 1;
+    }
+    Ok(summary)
+}
+
+fn failing_workload(
+    kind: &str,
+    meta: &kube::api::ObjectMeta,
+    desired: i32,
+    ready: i32,
+) -> Option<FailingWorkload> {
+    (ready < desired).then(|| FailingWorkload {
+        kind: kind.to_string(),
+        namespace: meta.namespace.clone().unwrap_or_default(),
+        name: meta.name.clone().unwrap_or_default(),
+        desired,
+        ready,
+    })
+}
+
+// workloads with fewer ready replicas than desired
+async fn failing_workloads(client: Client) -> Result<Vec<FailingWorkload>, String> {
+    let (deployments, statefulsets, daemonsets) = futures::join!(
+        k8s_client::list_resources::<Deployment>(client.clone(), "all", true),
+        k8s_client::list_resources::<StatefulSet>(client.clone(), "all", true),
+        k8s_client::list_resources::<DaemonSet>(client, "all", true)
+    );
+
+    let mut failing = vec![];
+    for deployment in deployments? {
+        let desired = deployment
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.replicas)
+            .unwrap_or(1);
+        let ready = deployment
+            .status
+            .as_ref()
+            .and_then(|status| status.available_replicas)
+            .unwrap_or(0);
+        failing.extend(failing_workload(
+            "Deployment",
+            &deployment.metadata,
+            desired,
+            ready,
+        ));
+    }
+    for statefulset in statefulsets? {
+        let desired = statefulset
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.replicas)
+            .unwrap_or(1);
+        let ready = statefulset
+            .status
+            .as_ref()
+            .and_then(|status| status.ready_replicas)
+            .unwrap_or(0);
+        failing.extend(failing_workload(
+            "StatefulSet",
+            &statefulset.metadata,
+            desired,
+            ready,
+        ));
+    }
+    for daemonset in daemonsets? {
+        let (desired, ready) = daemonset
+            .status
+            .as_ref()
+            .map(|status| (status.desired_number_scheduled, status.number_ready))
+            .unwrap_or((0, 0));
+        failing.extend(failing_workload(
+            "DaemonSet",
+            &daemonset.metadata,
+            desired,
+            ready,
+        ));
+    }
+    Ok(failing)
+}
+
+async fn warning_events(client: Client) -> Result<WarningEventSummary, String> {
+    let events: Api<Event> = Api::all(client);
+    let list = events
+        .list(&ListParams::default().fields("type=Warning"))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let since = Utc::now() - Duration::hours(1);
+    let mut summary = WarningEventSummary::default();
+    for event in list
+        .items
+        .iter()
+        .filter(|event| event_time(event).is_some_and(|time| time >= since))
+    {
+        summary.total += 1;
+        let reason = event
+            .reason
+            .clone()
+            .unwrap_or_else(|| "Unknown".to_string());
+        *summary.by_reason.entry(reason).or_default() += 1;
+    }
+    Ok(summary)
+}
+
+// parse the "[+]ping ok" / "[-]etcd failed: reason withheld" lines of /readyz?verbose
+fn parse_readyz(body: &str) -> Vec<HealthCheck> {
+    body.lines()
+        .filter_map(|line| {
+            let (ok, rest) = if let Some(rest) = line.strip_prefix("[+]") {
+                (true, rest)
+            } else {
+                (false, line.strip_prefix("[-]")?)
+            };
+            let name = rest.split_whitespace().next()?.to_string();
+            Some(HealthCheck { name, ok })
+        })
+        .collect()
+}
+
+async fn api_server_readiness(client: Client) -> Result<ApiServerReadiness, String> {
+    let request = http::Request::get("/readyz?verbose")
+        .body(vec![])
+        .map_err(|e| e.to_string())?;
+    // an unready API server answers with an error status and the same verbose body
+    let (status, body) = k8s_client::request_raw(&client, request).await?;
+    if status.is_success() || status.is_server_error() {
+        return Ok(ApiServerReadiness {
+            ready: status.is_success(),
+            checks: parse_readyz(&body),
+        });
+    }
+    let message = serde_json::from_str::<kube::core::ErrorResponse>(&body)
+        .map(|response| response.message)
+        .unwrap_or_else(|_| body.trim().to_string());
+    Err(format!(
+        "Readiness check failed with {}: {}",
+        status, message
+    ))
+}
+
+// componentstatuses is deprecated and empty on many managed clusters, but still useful where it works
+async fn component_health(client: Client) -> Result<Vec<ComponentHealth>, String> {
+    let components = k8s_client::list_cluster_resources::<ComponentStatus>(client).await?;
+    Ok(components
+        .into_iter()
+        .map(|component| {
+            let healthy = component
+                .conditions
+                .as_ref()
+                .and_then(|conditions| conditions.iter().find(|c| c.type_ == "Healthy"));
+            ComponentHealth {
+                name: component.metadata.name.unwrap_or_default(),
+                healthy: healthy.is_some_and(|c| c.status == "True"),
+                message: healthy.and_then(|c| c.error.clone().or_else(|| c.message.clone())),
+            }
+        })
+        .collect())
+}
+
+fn section<T>(name: &str, result: Result<T, String>, errors: &mut Vec<SectionError>) -> Option<T> {
+    result
+        .map_err(|error| {
+            errors.push(SectionError {
+                section: name.to_string(),
+                error,
+            })
+        })
+        .ok()
+}
+
+// health summary of a cluster for its landing page, sections that fail are reported in errors
+#[tauri::command]
+pub async fn cluster_overview(
+    kubeconfig_path: String,
+    context: String,
+    request_id: Option<String>,
+) -> Result<ClusterOverview, String> {
+    let (client, _request) =
+        k8s_client::create_tracked_client(kubeconfig_path, context, request_id).await?;
+    let (version, nodes, pods, workloads, events, readiness, components) = futures::join!(
+        server_version(client.clone()),
+        node_summary(client.clone()),
+        pod_summary(client.clone()),
+        failing_workloads(client.clone()),
+        warning_events(client.clone()),
+        api_server_readiness(client.clone()),
+        component_health(client)
+    );
+
+    let mut errors = vec![];
+    Ok(ClusterOverview {
+        version: section("version", version, &mut errors),
+        nodes: section("nodes", nodes, &mut errors),
+        pods: section("pods", pods, &mut errors),
+        failing_workloads: section("failingWorkloads", workloads, &mut errors),
+        warning_events: section("warningEvents", events, &mut errors),
+        readiness: section("readiness", readiness, &mut errors),
+        components: section("components", components, &mut errors),
+        errors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_failing_readyz() {
+        let body = "[+]ping ok\n\
+                    [+]log ok\n\
+                    [-]etcd failed: reason withheld\n\
+                    [+]etcd-readiness ok\n\
+                    [+]informer-sync ok\n\
+                    [+]poststarthook/start-apiserver-admission-initializer ok\n\
+                    [-]poststarthook/rbac/bootstrap-roles failed: reason withheld\n\
+                    [+]shutdown ok\n\
+                    readyz check failed\n";
+        let checks = parse_readyz(body);
+        assert_eq!(checks.len(), 8);
+        let failed: Vec<&str> = checks
+            .iter()
+            .filter(|c| !c.ok)
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(failed, ["etcd", "poststarthook/rbac/bootstrap-roles"]);
+        assert!(checks.iter().any(|c| c.ok && c.name == "etcd-readiness"));
+    }
+
+    #[test]
+    fn ignores_lines_without_check_marker() {
+        assert!(parse_readyz("ok").is_empty());
+        assert!(parse_readyz("").is_empty());
+        // the quoted form kube puts in error messages carries no usable lines
+        assert!(parse_readyz(r#""[+]ping ok\n[-]etcd failed\n""#).is_empty());
+    }
+}
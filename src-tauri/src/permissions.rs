@@ -0,0 +1,240 @@
+use crate::k8s_client;
+use futures::{stream, StreamExt};
+use k8s_openapi::api::authorization::v1::{
+    ResourceAttributes, SelfSubjectAccessReview, SelfSubjectAccessReviewSpec,
+};
+use kube::api::PostParams;
+use kube::{Api, Client};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::State;
+
+// how long an answer is reused before asking the API server again
+const CACHE_TTL: Duration = Duration::from_secs(300);
+const REVIEW_CONCURRENCY: usize = 10;
+
+// one "can I" question, e.g. verb "delete" on resource "pods" in namespace "default"
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionCheck {
+    pub verb: String,
+    // api group, empty for the core group
+    #[serde(default)]
+    pub group: String,
+    pub resource: String,
+    pub subresource: Option<String>,
+    // None for cluster scoped resources, or to ask across all namespaces
+    pub namespace: Option<String>,
+    pub name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionDecision {
+    pub allowed: bool,
+    pub reason: Option<String>,
+}
+
+// cached answers of one context with the time they were checked
+type ContextDecisions = HashMap<PermissionCheck, (Instant, PermissionDecision)>;
+
+// a context is identified by its kubeconfig too, names like "admin" repeat across files
+type ContextKey = (String, String);
+
+// SelfSubjectAccessReview answers per context, they only change with RBAC or credentials
+#[derive(Default)]
+pub struct PermissionCache {
+    entries: Mutex<HashMap<ContextKey, ContextDecisions>>,
+}
+
+impl PermissionCache {
+    fn get(
+        &self,
+        kubeconfig_path: &str,
+        context: &str,
+        check: &PermissionCheck,
+    ) -> Option<PermissionDecision> {
+        let entries = self.entries.lock().ok()?;
+        let key = (kubeconfig_path.to_string(), context.to_string());
+        let (checked_at, decision) = entries.get(&key)?.get(check)?;
+        (checked_at.elapsed() < CACHE_TTL).then(|| decision.clone())
+    }
+
+    fn insert(
+        &self,
+        kubeconfig_path: &str,
+        context: &str,
+        check: PermissionCheck,
+        decision: PermissionDecision,
+    ) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries
+                .entry((kubeconfig_path.to_string(), context.to_string()))
+                .or_default()
+                .insert(check, (Instant::now(), decision));
+        }
+    }
+
+    // forget the answers of the matching contexts, None matches any
+    pub fn clear(&self, kubeconfig_path: Option<&str>, context: Option<&str>) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.retain(|(path, name), _| {
+                kubeconfig_path.is_some_and(|p| p != path) || context.is_some_and(|c| c != name)
+            });
+        }
+    }
+}
+
+async fn review(client: Client, check: &PermissionCheck) -> Result<PermissionDecision, String> {
+    let reviews: Api<SelfSubjectAccessReview> = Api::all(client);
+    let request = SelfSubjectAccessReview {
+        spec: SelfSubjectAccessReviewSpec {
+            resource_attributes: Some(ResourceAttributes {
+                verb: Some(check.verb.clone()),
+                group: Some(check.group.clone()),
+                resource: Some(check.resource.clone()),
+                subresource: check.subresource.clone(),
+                namespace: check.namespace.clone(),
+                name: check.name.clone(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let response = reviews
+        .create(&PostParams::default(), &request)
+        .await
+        .map_err(|e| e.to_string())?;
+    let status = response.status.unwrap_or_default();
+    Ok(PermissionDecision {
+        allowed: status.allowed && !status.denied.unwrap_or(false),
+        reason: status.reason.or(status.evaluation_error),
+    })
+}
+
+// answer a batch of "can I" checks keyed by an id chosen by the frontend, so it can
+// disable the actions the current identity cannot perform
+#[tauri::command]
+pub async fn check_permissions(
+    cache: State<'_, PermissionCache>,
+    kubeconfig_path: String,
+    context: String,
+    checks: BTreeMap<String, PermissionCheck>,
+) -> Result<BTreeMap<String, PermissionDecision>, String> {
+    let mut decisions = BTreeMap::new();
+    let mut pending = vec![];
+    for (key, check) in checks {
+        match cache.get(&kubeconfig_path, &context, &check) {
+            Some(decision) => {
+                decisions.insert(key, decision);
+            }
+            None => pending.push((key, check)),
+        }
+    }
+    if pending.is_empty() {
+        return Ok(decisions);
+    }
+
+    let client = k8s_client::create_k8s_client(kubeconfig_path.clone(), context.clone()).await?;
+    let results: Vec<_> = stream::iter(pending)
+        .map(|(key, check)| {
+            let client = client.clone();
+            async move {
+                let result = review(client, &check).await;
+                (key, check, result)
+            }
+        })
+        .buffer_unordered(REVIEW_CONCURRENCY)
+        .collect()
+        .await;
+
+    for (key, check, result) in results {
+        // a failed review is reported as denied but not cached, so it is asked again next time
+        let decision = match result {
+            Ok(decision) => {
+                cache.insert(&kubeconfig_path, &context, check, decision.clone());
+                decision
+            }
+            Err(e) => PermissionDecision {
+                allowed: false,
+                reason: Some(e),
+            },
+        };
+        decisions.insert(key, decision);
+    }
+    Ok(decisions)
+}
+
+// forget cached answers, e.g. after switching credentials; all contexts when none is given
+#[tauri::command]
+pub async fn clear_permission_cache(
+    cache: State<'_, PermissionCache>,
+    kubeconfig_path: Option<String>,
+    context: Option<String>,
+) -> Result<(), String> {
+    cache.clear(kubeconfig_path.as_deref(), context.as_deref());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(verb: &str) -> PermissionCheck {
+        PermissionCheck {
+            verb: verb.to_string(),
+            group: String::new(),
+            resource: "pods".to_string(),
+            subresource: None,
+            namespace: Some("default".to_string()),
+            name: None,
+        }
+    }
+
+    fn decision(allowed: bool) -> PermissionDecision {
+        PermissionDecision {
+            allowed,
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn same_context_name_in_other_kubeconfig_is_separate() {
+        let cache = PermissionCache::default();
+        cache.insert(
+            "/home/a/.kube/config",
+            "admin",
+            check("delete"),
+            decision(true),
+        );
+        assert!(cache
+            .get("/home/a/.kube/config", "admin", &check("delete"))
+            .is_some_and(|d| d.allowed));
+        assert!(cache
+            .get("/tmp/other.yaml", "admin", &check("delete"))
+            .is_none());
+    }
+
+    #[test]
+    fn clear_matches_path_and_context() {
+        let cache = PermissionCache::default();
+        cache.insert("a", "admin", check("get"), decision(true));
+        cache.insert("a", "dev", check("get"), decision(true));
+        cache.insert("b", "admin", check("get"), decision(true));
+
+        cache.clear(Some("a"), Some("admin"));
+        assert!(cache.get("a", "admin", &check("get")).is_none());
+        assert!(cache.get("a", "dev", &check("get")).is_some());
+        assert!(cache.get("b", "admin", &check("get")).is_some());
+
+        cache.clear(None, Some("admin"));
+        assert!(cache.get("b", "admin", &check("get")).is_none());
+        assert!(cache.get("a", "dev", &check("get")).is_some());
+
+        cache.clear(None, None);
+        assert!(cache.get("a", "dev", &check("get")).is_none());
+    }
+}
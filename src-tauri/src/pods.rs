@@ -1,7 +1,67 @@
+use crate::audit::AuditEvent;
 use crate::k8s_client;
 use crate::kubectl::run_kubectl_command;
-use k8s_openapi::api::core::v1::Pod;
-use kube::api::{Api, LogParams};
+use crate::protection::Action;
+use crate::sessions::SessionManager;
+use k8s_openapi::api::core::v1::{
+    Capabilities, EphemeralContainer, Pod, SeccompProfile, SecurityContext,
+};
+use k8s_openapi::chrono::Utc;
+use kube::api::{Api, AttachParams, LogParams, Patch, PatchParams};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Duration;
+use tauri::{AppHandle, State};
+
+// how long to wait for a debug container to start, pulling the image can take a while
+const DEBUG_CONTAINER_TIMEOUT: Duration = Duration::from_secs(120);
+
+// security profiles matching the ones of kubectl debug --profile
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DebugProfile {
+    #[default]
+    General,
+    Baseline,
+    Restricted,
+    Netadmin,
+    Sysadmin,
+}
+
+impl DebugProfile {
+    pub(crate) fn security_context(&self) -> Option<SecurityContext> {
+        let add_capabilities = |caps: &[&str]| SecurityContext {
+            capabilities: Some(Capabilities {
+                add: Some(caps.iter().map(|c| c.to_string()).collect()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        match self {
+            DebugProfile::General => Some(add_capabilities(&["SYS_PTRACE"])),
+            DebugProfile::Baseline => None,
+            DebugProfile::Restricted => Some(SecurityContext {
+                run_as_non_root: Some(true),
+                allow_privilege_escalation: Some(false),
+                capabilities: Some(Capabilities {
+                    drop: Some(vec!["ALL".to_string()]),
+                    ..Default::default()
+                }),
+                seccomp_profile: Some(SeccompProfile {
+                    type_: "RuntimeDefault".to_string(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            DebugProfile::Netadmin => Some(add_capabilities(&["NET_ADMIN", "NET_RAW"])),
+            DebugProfile::Sysadmin => Some(SecurityContext {
+                privileged: Some(true),
+                ..Default::default()
+            }),
+        }
+    }
+}
 
 // debug a pod by name in a namespace
 #[tauri::command]
@@ -12,38 +72,68 @@ pub async fn debug_pod(
     pod_name: String,
     image: String,
     target: Option<String>,
+    confirmation: Option<String>,
 ) -> Result<(), String> {
-    let cmd_string = format!(
-        "--kubeconfig {} --context {} debug {} -it -n {} --image {}",
-        kubeconfig_path, context, pod_name, namespace, image,
+    let audit = AuditEvent::new(
+        &kubeconfig_path,
+        &context,
+        Action::Debug,
+        Some(&namespace),
+        Some("Pod"),
+        Some(&pod_name),
+        json!({ "image": image, "target": target }),
     );
-    // if target is Some, add it to the command
-    // otherwise ignore
-    let cmd_string = match target {
-        None => cmd_string,
-        Some(ref t) if t.is_empty() => cmd_string,
-        Some(t) => format!("{} --target {}", cmd_string, t),
-    };
+    audit
+        .run(confirmation.as_deref(), async move {
+            let kubeconfig = crate::k8s_config::kubectl_kubeconfig(&kubeconfig_path, &context)?;
+            let cmd_string = format!(
+                "--kubeconfig {} --context {} debug {} -it -n {} --image {}",
+                kubeconfig, context, pod_name, namespace, image,
+            );
+            // if target is Some, add it to the command
+            // otherwise ignore
+            let cmd_string = match target {
+                None => cmd_string,
+                Some(ref t) if t.is_empty() => cmd_string,
+                Some(t) => format!("{} --target {}", cmd_string, t),
+            };
 
-    run_kubectl_command(&cmd_string)?;
-    Ok(())
+            run_kubectl_command(&cmd_string)?;
+            Ok(())
+        })
+        .await
 }
 
 #[tauri::command]
-pub fn open_pod_shell(
+pub async fn open_pod_shell(
     kubeconfig_path: String,
     context: String,
     namespace: String,
     pod_name: String,
     container_name: String,
     cmd_shell: String,
+    confirmation: Option<String>,
 ) -> Result<(), String> {
-    let cmd_string = format!(
-        "--kubeconfig {} --context {} exec -it {} -n {} --container {} -- {}",
-        kubeconfig_path, context, pod_name, namespace, container_name, cmd_shell
+    let audit = AuditEvent::new(
+        &kubeconfig_path,
+        &context,
+        Action::Exec,
+        Some(&namespace),
+        Some("Pod"),
+        Some(&pod_name),
+        json!({ "container": container_name, "command": cmd_shell }),
     );
-    run_kubectl_command(&cmd_string)?;
-    Ok(())
+    audit
+        .run(confirmation.as_deref(), async move {
+            let kubeconfig = crate::k8s_config::kubectl_kubeconfig(&kubeconfig_path, &context)?;
+            let cmd_string = format!(
+                "--kubeconfig {} --context {} exec -it {} -n {} --container {} -- {}",
+                kubeconfig, context, pod_name, namespace, container_name, cmd_shell
+            );
+            run_kubectl_command(&cmd_string)?;
+            Ok(())
+        })
+        .await
 }
 
 #[tauri::command]
@@ -73,3 +163,179 @@ pub async fn get_pod_logs(
         .map_err(|e| e.to_string())?;
     Ok(logs)
 }
+
+// start an interactive shell in a container without going through kubectl
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn start_exec_session(
+    app: AppHandle,
+    sessions: State<'_, SessionManager>,
+    kubeconfig_path: String,
+    context: String,
+    namespace: String,
+    pod_name: String,
+    container_name: String,
+    command: Vec<String>,
+    session_id: String,
+    confirmation: Option<String>,
+) -> Result<(), String> {
+    let audit = AuditEvent::new(
+        &kubeconfig_path,
+        &context,
+        Action::Exec,
+        Some(&namespace),
+        Some("Pod"),
+        Some(&pod_name),
+        json!({ "container": container_name, "command": command }),
+    );
+    audit
+        .run(confirmation.as_deref(), async move {
+            let client = k8s_client::create_k8s_client(kubeconfig_path, context).await?;
+            let pods: Api<Pod> = Api::namespaced(client, &namespace);
+
+            let process = pods
+                .exec(
+                    &pod_name,
+                    command,
+                    &AttachParams::interactive_tty().container(container_name),
+                )
+                .await
+                .map_err(|e| format!("Failed to exec into pod {}: {}", pod_name, e))?;
+            sessions.start(app, session_id, process, None)
+        })
+        .await
+}
+
+// wait until a container, ephemeral or not, is running, failing early when it cannot start
+pub(crate) async fn wait_for_container_running(
+    pods: &Api<Pod>,
+    pod_name: &str,
+    container_name: &str,
+) -> Result<(), String> {
+    let deadline = tokio::time::Instant::now() + DEBUG_CONTAINER_TIMEOUT;
+    loop {
+        let pod = pods.get(pod_name).await.map_err(|e| e.to_string())?;
+        let state = pod
+            .status
+            .and_then(|status| {
+                status
+                    .container_statuses
+                    .unwrap_or_default()
+                    .into_iter()
+                    .chain(status.ephemeral_container_statuses.unwrap_or_default())
+                    .find(|s| s.name == container_name)
+            })
+            .and_then(|status| status.state);
+
+        if let Some(state) = state {
+            if state.running.is_some() {
+                return Ok(());
+            }
+            if let Some(terminated) = state.terminated {
+                return Err(format!(
+                    "Debug container {} exited with code {}: {}",
+                    container_name,
+                    terminated.exit_code,
+                    terminated.reason.unwrap_or_default()
+                ));
+            }
+            if let Some(waiting) = state.waiting {
+                let reason = waiting.reason.unwrap_or_default();
+                if [
+                    "ErrImagePull",
+                    "ImagePullBackOff",
+                    "InvalidImageName",
+                    "CreateContainerError",
+                ]
+                .contains(&reason.as_str())
+                {
+                    return Err(format!(
+                        "Debug container {} cannot start: {} {}",
+                        container_name,
+                        reason,
+                        waiting.message.unwrap_or_default()
+                    ));
+                }
+            }
+        }
+
+        if tokio::time::Instant::now() >= deadline {
+            return Err(format!(
+                "Timed out waiting for debug container {} to start",
+                container_name
+            ));
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+// add an ephemeral debug container to a pod and attach to it, returning the container name
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn start_pod_debug_session(
+    app: AppHandle,
+    sessions: State<'_, SessionManager>,
+    kubeconfig_path: String,
+    context: String,
+    namespace: String,
+    pod_name: String,
+    image: String,
+    target: Option<String>,
+    command: Option<Vec<String>>,
+    profile: Option<DebugProfile>,
+    session_id: String,
+    confirmation: Option<String>,
+) -> Result<String, String> {
+    let audit = AuditEvent::new(
+        &kubeconfig_path,
+        &context,
+        Action::Debug,
+        Some(&namespace),
+        Some("Pod"),
+        Some(&pod_name),
+        json!({ "image": image, "target": target, "command": command, "profile": profile }),
+    );
+    audit
+        .run(confirmation.as_deref(), async move {
+            let client = k8s_client::create_k8s_client(kubeconfig_path, context).await?;
+            let pods: Api<Pod> = Api::namespaced(client, &namespace);
+
+            let container_name = format!("debugger-{:x}", Utc::now().timestamp_millis());
+            let container = EphemeralContainer {
+                name: container_name.clone(),
+                image: Some(image),
+                command: command.filter(|c| !c.is_empty()),
+                stdin: Some(true),
+                tty: Some(true),
+                // sharing the process namespace of the target lets the debugger see its processes
+                target_container_name: target.filter(|t| !t.is_empty()),
+                security_context: profile.unwrap_or_default().security_context(),
+                ..Default::default()
+            };
+            let patch = serde_json::json!({
+                "spec": {
+                    "ephemeralContainers": [container]
+                }
+            });
+            pods.patch_ephemeral_containers(
+                &pod_name,
+                &PatchParams::default(),
+                &Patch::Strategic(patch),
+            )
+            .await
+            .map_err(|e| format!("Failed to add debug container to {}: {}", pod_name, e))?;
+
+            wait_for_container_running(&pods, &pod_name, &container_name).await?;
+
+            let process = pods
+                .attach(
+                    &pod_name,
+                    &AttachParams::interactive_tty().container(container_name.clone()),
+                )
+                .await
+                .map_err(|e| format!("Failed to attach to debug container: {}", e))?;
+            sessions.start(app, session_id, process, None)?;
+            Ok(container_name)
+        })
+        .await
+}
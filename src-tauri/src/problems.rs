@@ -0,0 +1,565 @@
+use crate::k8s_client;
+use crate::overview::{event_time, node_is_ready, SectionError};
+use crate::resources::ResourceTarget;
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::batch::v1::Job;
+use k8s_openapi::api::core::v1::{ContainerStatus, Event, Node, PersistentVolumeClaim, Pod};
+use kube::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// events kept as evidence per object, most recent first
+const MAX_EVENTS_PER_FINDING: usize = 5;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    Critical,
+    Warning,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ProblemCategory {
+    CrashLoopBackOff,
+    ImagePullBackOff,
+    OOMKilled,
+    Unschedulable,
+    UnavailableReplicas,
+    FailedJob,
+    NodeNotReady,
+    NodePressure,
+    UnboundPvc,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EventEvidence {
+    pub event_type: Option<String>,
+    pub reason: Option<String>,
+    pub message: Option<String>,
+    pub count: Option<i32>,
+    // RFC 3339
+    pub last_seen: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TerminationEvidence {
+    pub container: String,
+    pub reason: Option<String>,
+    pub exit_code: i32,
+    pub finished_at: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Finding {
+    pub category: ProblemCategory,
+    pub severity: Severity,
+    // the object to open in the UI
+    pub target: ResourceTarget,
+    pub message: String,
+    pub last_termination: Option<TerminationEvidence>,
+    pub events: Vec<EventEvidence>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProblemReport {
+    pub findings: Vec<Finding>,
+    // kinds that could not be scanned, e.g. because of missing permissions
+    pub errors: Vec<SectionError>,
+}
+
+fn target(kind: &str, meta: &kube::api::ObjectMeta) -> ResourceTarget {
+    ResourceTarget {
+        namespace: meta.namespace.clone().unwrap_or_default(),
+        kind: kind.to_string(),
+        api_version: None,
+        name: meta.name.clone().unwrap_or_default(),
+    }
+}
+
+fn finding(
+    category: ProblemCategory,
+    severity: Severity,
+    target: ResourceTarget,
+    message: String,
+) -> Finding {
+    Finding {
+        category,
+        severity,
+        target,
+        message,
+        last_termination: None,
+        events: vec![],
+    }
+}
+
+fn last_termination(status: &ContainerStatus) -> Option<TerminationEvidence> {
+    let terminated = status
+        .last_state
+        .as_ref()
+        .and_then(|state| state.terminated.as_ref())
+        .or_else(|| {
+            status
+                .state
+                .as_ref()
+                .and_then(|state| state.terminated.as_ref())
+        })?;
+    Some(TerminationEvidence {
+        container: status.name.clone(),
+        reason: terminated.reason.clone(),
+        exit_code: terminated.exit_code,
+        finished_at: terminated.finished_at.as_ref().map(|t| t.0.to_rfc3339()),
+    })
+}
+
+fn pod_findings(pod: &Pod) -> Vec<Finding> {
+    let mut findings = vec![];
+    let Some(status) = pod.status.as_ref() else {
+        return findings;
+    };
+
+    let statuses = status
+        .init_container_statuses
+        .iter()
+        .flatten()
+        .chain(status.container_statuses.iter().flatten());
+    for container in statuses {
+        let waiting = container
+            .state
+            .as_ref()
+            .and_then(|state| state.waiting.as_ref());
+        let waiting_reason = waiting.and_then(|w| w.reason.as_deref()).unwrap_or("");
+        let waiting_message = waiting.and_then(|w| w.message.clone()).unwrap_or_default();
+        let termination = last_termination(container);
+
+        match waiting_reason {
+            "CrashLoopBackOff" => findings.push(Finding {
+                last_termination: termination.clone(),
+                ..finding(
+                    ProblemCategory::CrashLoopBackOff,
+                    Severity::Critical,
+                    target("Pod", &pod.metadata),
+                    format!(
+                        "Container {} is crash looping ({} restarts)",
+                        container.name, container.restart_count
+                    ),
+                )
+            }),
+            "ImagePullBackOff" | "ErrImagePull" | "InvalidImageName" => findings.push(finding(
+                ProblemCategory::ImagePullBackOff,
+                Severity::Critical,
+                target("Pod", &pod.metadata),
+                format!(
+                    "Container {} cannot pull image {}: {}",
+                    container.name, container.image, waiting_message
+                ),
+            )),
+            _ => {}
+        }
+
+        // a container that is running and ready again has recovered from an earlier kill
+        let recovered = container.ready
+            && container
+                .state
+                .as_ref()
+                .is_some_and(|state| state.running.is_some());
+        if !recovered
+            && termination
+                .as_ref()
+                .is_some_and(|t| t.reason.as_deref() == Some("OOMKilled"))
+        {
+            findings.push(Finding {
+                last_termination: termination,
+                ..finding(
+                    ProblemCategory::OOMKilled,
+                    Severity::Warning,
+                    target("Pod", &pod.metadata),
+                    format!(
+                        "Container {} was killed for running out of memory",
+                        container.name
+                    ),
+                )
+            });
+        }
+    }
+
+    if status.phase.as_deref() == Some("Pending") {
+        let unschedulable = status.conditions.iter().flatten().find(|c| {
+            c.type_ == "PodScheduled"
+                && c.status == "False"
+                && c.reason.as_deref() == Some("Unschedulable")
+        });
+        if let Some(condition) = unschedulable {
+            findings.push(finding(
+                ProblemCategory::Unschedulable,
+                Severity::Warning,
+                target("Pod", &pod.metadata),
+                condition
+                    .message
+                    .clone()
+                    .unwrap_or_else(|| "Pod cannot be scheduled".to_string()),
+            ));
+        }
+    }
+    findings
+}
+
+fn deployment_findings(deployment: &Deployment) -> Option<Finding> {
+    let status = deployment.status.as_ref()?;
+    let unavailable = status.unavailable_replicas.unwrap_or(0);
+    if unavailable == 0 {
+        return None;
+    }
+    let desired = deployment
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.replicas)
+        .unwrap_or(1);
+    Some(finding(
+        ProblemCategory::UnavailableReplicas,
+        Severity::Warning,
+        target("Deployment", &deployment.metadata),
+        format!("{} of {} replicas are unavailable", unavailable, desired),
+    ))
+}
+
+fn job_findings(job: &Job) -> Option<Finding> {
+    let failed = job
+        .status
+        .as_ref()?
+        .conditions
+        .iter()
+        .flatten()
+        .find(|c| c.type_ == "Failed" && c.status == "True")?;
+    Some(finding(
+        ProblemCategory::FailedJob,
+        Severity::Warning,
+        target("Job", &job.metadata),
+        format!(
+            "Job failed: {}",
+            failed
+                .message
+                .clone()
+                .or_else(|| failed.reason.clone())
+                .unwrap_or_default()
+        ),
+    ))
+}
+
+fn node_findings(node: &Node) -> Vec<Finding> {
+    let mut findings = vec![];
+    if !node_is_ready(node) {
+        findings.push(finding(
+            ProblemCategory::NodeNotReady,
+            Severity::Critical,
+            target("Node", &node.metadata),
+            "Node is not ready".to_string(),
+        ));
+    }
+    let conditions = node
+        .status
+        .as_ref()
+        .and_then(|status| status.conditions.as_ref());
+    for condition in conditions.into_iter().flatten() {
+        let pressure = matches!(
+            condition.type_.as_str(),
+            "MemoryPressure" | "DiskPressure" | "PIDPressure" | "NetworkUnavailable"
+        );
+        if pressure && condition.status == "True" {
+            findings.push(finding(
+                ProblemCategory::NodePressure,
+                Severity::Warning,
+                target("Node", &node.metadata),
+                format!(
+                    "{}: {}",
+                    condition.type_,
+                    condition.message.clone().unwrap_or_default()
+                ),
+            ));
+        }
+    }
+    findings
+}
+
+fn pvc_findings(pvc: &PersistentVolumeClaim) -> Option<Finding> {
+    let phase = pvc
+        .status
+        .as_ref()
+        .and_then(|status| status.phase.clone())
+        .unwrap_or_else(|| "Pending".to_string());
+    if phase == "Bound" {
+        return None;
+    }
+    let severity = if phase == "Lost" {
+        Severity::Critical
+    } else {
+        Severity::Warning
+    };
+    Some(finding(
+        ProblemCategory::UnboundPvc,
+        severity,
+        target("PersistentVolumeClaim", &pvc.metadata),
+        format!("PersistentVolumeClaim is {}", phase),
+    ))
+}
+
+// events of each object keyed by (kind, namespace, name), most recent first
+async fn events_by_object(
+    client: Client,
+    namespace: &str,
+) -> Result<HashMap<(String, String, String), Vec<Event>>, String> {
+    let list_all_namespaces = namespace == "all";
+    let events =
+        k8s_client::list_resources::<Event>(client, namespace, list_all_namespaces).await?;
+
+    let mut by_object: HashMap<(String, String, String), Vec<Event>> = HashMap::new();
+    for event in events {
+        let object = &event.involved_object;
+        let key = (
+            object.kind.clone().unwrap_or_default(),
+            object.namespace.clone().unwrap_or_default(),
+            object.name.clone().unwrap_or_default(),
+        );
+        by_object.entry(key).or_default().push(event);
+    }
+    for events in by_object.values_mut() {
+        events.sort_by_key(|event| std::cmp::Reverse(event_time(event)));
+    }
+    Ok(by_object)
+}
+
+fn event_evidence(event: &Event) -> EventEvidence {
+    EventEvidence {
+        event_type: event.type_.clone(),
+        reason: event.reason.clone(),
+        message: event.message.clone(),
+        count: event.count,
+        last_seen: event_time(event).map(|t| t.to_rfc3339()),
+    }
+}
+
+fn scan<T>(kind: &str, result: Result<Vec<T>, String>, errors: &mut Vec<SectionError>) -> Vec<T> {
+    result.unwrap_or_else(|error| {
+        errors.push(SectionError {
+            section: kind.to_string(),
+            error,
+        });
+        vec![]
+    })
+}
+
+// scan pods, deployments, jobs, nodes and PVCs of a namespace ("all" for every namespace)
+// for common problems, with the related events as evidence
+#[tauri::command]
+pub async fn find_problems(
+    kubeconfig_path: String,
+    context: String,
+    namespace: Option<String>,
+    request_id: Option<String>,
+) -> Result<ProblemReport, String> {
+    let namespace = namespace.unwrap_or_else(|| "all".to_string());
+    let all = namespace == "all";
+    let (client, _request) =
+        k8s_client::create_tracked_client(kubeconfig_path, context, request_id).await?;
+
+    let (pods, deployments, jobs, pvcs, nodes, events) = futures::join!(
+        k8s_client::list_resources::<Pod>(client.clone(), &namespace, all),
+        k8s_client::list_resources::<Deployment>(client.clone(), &namespace, all),
+        k8s_client::list_resources::<Job>(client.clone(), &namespace, all),
+        k8s_client::list_resources::<PersistentVolumeClaim>(client.clone(), &namespace, all),
+        async {
+            // nodes are only relevant for the cluster wide view
+            if all {
+                k8s_client::list_cluster_resources::<Node>(client.clone()).await
+            } else {
+                Ok(vec![])
+            }
+        },
+        events_by_object(client.clone(), &namespace)
+    );
+
+    let mut errors = vec![];
+    let mut findings: Vec<Finding> = vec![];
+    for pod in scan("Pod", pods, &mut errors) {
+        findings.extend(pod_findings(&pod));
+    }
+    for deployment in scan("Deployment", deployments, &mut errors) {
+        findings.extend(deployment_findings(&deployment));
+    }
+    for job in scan("Job", jobs, &mut errors) {
+        findings.extend(job_findings(&job));
+    }
+    for pvc in scan("PersistentVolumeClaim", pvcs, &mut errors) {
+        findings.extend(pvc_findings(&pvc));
+    }
+    for node in scan("Node", nodes, &mut errors) {
+        findings.extend(node_findings(&node));
+    }
+
+    // findings without events are still useful, so a failed event list is only reported
+    let events = match events {
+        Ok(events) => events,
+        Err(error) => {
+            errors.push(SectionError {
+                section: "Event".to_string(),
+                error,
+            });
+            HashMap::new()
+        }
+    };
+    for finding in findings.iter_mut() {
+        let key = (
+            finding.target.kind.clone(),
+            finding.target.namespace.clone(),
+            finding.target.name.clone(),
+        );
+        if let Some(object_events) = events.get(&key) {
+            finding.events = object_events
+                .iter()
+                .take(MAX_EVENTS_PER_FINDING)
+                .map(event_evidence)
+                .collect();
+        }
+    }
+
+    findings.sort_by(|a, b| {
+        a.severity
+            .cmp(&b.severity)
+            .then_with(|| a.target.namespace.cmp(&b.target.namespace))
+            .then_with(|| a.target.name.cmp(&b.target.name))
+    });
+    Ok(ProblemReport { findings, errors })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn pod(container_status: Value) -> Pod {
+        serde_json::from_value(json!({
+            "metadata": { "name": "web-0", "namespace": "shop" },
+            "status": { "phase": "Running", "containerStatuses": [container_status] }
+        }))
+        .unwrap()
+    }
+
+    fn categories(findings: &[Finding]) -> Vec<ProblemCategory> {
+        findings.iter().map(|f| f.category).collect()
+    }
+
+    #[test]
+    fn crash_looping_containers_carry_their_last_termination() {
+        let findings = pod_findings(&pod(json!({
+            "name": "app",
+            "image": "shop/web:1.2",
+            "imageID": "",
+            "ready": false,
+            "restartCount": 7,
+            "state": { "waiting": { "reason": "CrashLoopBackOff" } },
+            "lastState": { "terminated": { "exitCode": 1, "reason": "Error" } }
+        })));
+
+        assert_eq!(
+            categories(&findings),
+            vec![ProblemCategory::CrashLoopBackOff]
+        );
+        assert_eq!(findings[0].severity, Severity::Critical);
+        assert!(findings[0].message.contains("7 restarts"));
+        assert_eq!(findings[0].last_termination.as_ref().unwrap().exit_code, 1);
+        assert_eq!(findings[0].target.name, "web-0");
+    }
+
+    #[test]
+    fn image_pull_failures_are_reported() {
+        for reason in ["ImagePullBackOff", "ErrImagePull", "InvalidImageName"] {
+            let findings = pod_findings(&pod(json!({
+                "name": "app",
+                "image": "shop/web:missing",
+                "imageID": "",
+                "ready": false,
+                "restartCount": 0,
+                "state": { "waiting": { "reason": reason, "message": "not found" } }
+            })));
+            assert_eq!(
+                categories(&findings),
+                vec![ProblemCategory::ImagePullBackOff]
+            );
+            assert!(findings[0].message.contains("shop/web:missing"));
+        }
+    }
+
+    #[test]
+    fn oom_kills_are_reported_until_the_container_recovers() {
+        let oom_killed = json!({ "terminated": { "exitCode": 137, "reason": "OOMKilled" } });
+        let waiting = pod_findings(&pod(json!({
+            "name": "app",
+            "image": "shop/web:1.2",
+            "imageID": "",
+            "ready": false,
+            "restartCount": 1,
+            "state": { "waiting": { "reason": "ContainerCreating" } },
+            "lastState": oom_killed
+        })));
+        assert_eq!(categories(&waiting), vec![ProblemCategory::OOMKilled]);
+
+        let recovered = pod_findings(&pod(json!({
+            "name": "app",
+            "image": "shop/web:1.2",
+            "imageID": "",
+            "ready": true,
+            "restartCount": 1,
+            "state": { "running": { "startedAt": "2026-03-01T12:00:00Z" } },
+            "lastState": oom_killed
+        })));
+        assert!(recovered.is_empty());
+    }
+
+    #[test]
+    fn not_ready_nodes_are_critical() {
+        let node: Node = serde_json::from_value(json!({
+            "metadata": { "name": "worker-1" },
+            "status": { "conditions": [
+                { "type": "Ready", "status": "False" },
+                { "type": "DiskPressure", "status": "True", "message": "disk is full" }
+            ] }
+        }))
+        .unwrap();
+        let findings = node_findings(&node);
+        assert_eq!(
+            categories(&findings),
+            vec![ProblemCategory::NodeNotReady, ProblemCategory::NodePressure]
+        );
+        assert_eq!(findings[0].severity, Severity::Critical);
+
+        let ready: Node = serde_json::from_value(json!({
+            "metadata": { "name": "worker-2" },
+            "status": { "conditions": [{ "type": "Ready", "status": "True" }] }
+        }))
+        .unwrap();
+        assert!(node_findings(&ready).is_empty());
+    }
+
+    #[test]
+    fn unbound_claims_are_reported() {
+        let claim = |phase: Option<&str>| -> PersistentVolumeClaim {
+            serde_json::from_value(json!({
+                "metadata": { "name": "data", "namespace": "shop" },
+                "status": { "phase": phase }
+            }))
+            .unwrap()
+        };
+        let pending = pvc_findings(&claim(Some("Pending"))).unwrap();
+        assert_eq!(pending.category, ProblemCategory::UnboundPvc);
+        assert_eq!(pending.severity, Severity::Warning);
+        assert!(pvc_findings(&claim(None)).is_some());
+        assert_eq!(
+            pvc_findings(&claim(Some("Lost"))).unwrap().severity,
+            Severity::Critical
+        );
+        assert!(pvc_findings(&claim(Some("Bound"))).is_none());
+    }
+}
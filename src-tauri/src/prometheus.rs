@@ -0,0 +1,563 @@
+use crate::app_config;
+use crate::k8s_client;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// where to reach Prometheus for a context
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PrometheusEndpoint {
+    // a URL reachable from this machine, e.g. http://localhost:9090
+    #[serde(rename_all = "camelCase")]
+    Url { url: String },
+    // a service in the cluster, reached through the API server's service proxy
+    #[serde(rename_all = "camelCase")]
+    Service {
+        namespace: String,
+        service: String,
+        // port name or number
+        port: String,
+        #[serde(default)]
+        https: bool,
+        // e.g. "/prometheus" when served under a sub path
+        #[serde(default)]
+        path_prefix: Option<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeSeries {
+    pub metric: BTreeMap<String, String>,
+    // (unix timestamp in seconds, value)
+    pub values: Vec<(f64, f64)>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum QueryTemplate {
+    PodCpuUsage,
+    PodCpuThrottling,
+    PodMemoryWorkingSet,
+    PodOomKills,
+    PodRestartRate,
+    PodNetworkReceive,
+    PodNetworkTransmit,
+    WorkloadCpuUsage,
+    WorkloadMemoryWorkingSet,
+    WorkloadRestartRate,
+    WorkloadNetworkReceive,
+    WorkloadNetworkTransmit,
+    NodeCpuUsage,
+    NodeMemoryWorkingSet,
+    NodeNetworkReceive,
+    NodeNetworkTransmit,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryTemplateInfo {
+    pub template: QueryTemplate,
+    // "pod", "workload" or "node"
+    pub scope: String,
+    pub title: String,
+    pub unit: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct PrometheusResponse {
+    status: String,
+    #[serde(default)]
+    data: Option<PrometheusData>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PrometheusData {
+    result_type: String,
+    #[serde(default)]
+    result: Vec<PrometheusSeries>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PrometheusSeries {
+    #[serde(default)]
+    metric: BTreeMap<String, String>,
+    #[serde(default)]
+    values: Vec<(f64, String)>,
+}
+
+const ALL_TEMPLATES: [QueryTemplate; 16] = [
+    QueryTemplate::PodCpuUsage,
+    QueryTemplate::PodCpuThrottling,
+    QueryTemplate::PodMemoryWorkingSet,
+    QueryTemplate::PodOomKills,
+    QueryTemplate::PodRestartRate,
+    QueryTemplate::PodNetworkReceive,
+    QueryTemplate::PodNetworkTransmit,
+    QueryTemplate::WorkloadCpuUsage,
+    QueryTemplate::WorkloadMemoryWorkingSet,
+    QueryTemplate::WorkloadRestartRate,
+    QueryTemplate::WorkloadNetworkReceive,
+    QueryTemplate::WorkloadNetworkTransmit,
+    QueryTemplate::NodeCpuUsage,
+    QueryTemplate::NodeMemoryWorkingSet,
+    QueryTemplate::NodeNetworkReceive,
+    QueryTemplate::NodeNetworkTransmit,
+];
+
+// escape a value for use inside a PromQL string literal
+fn promql_string(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// characters of the random suffixes of generated names and pod-template-hash values
+const NAME_SUFFIX_CHARS: &str = "[bcdfghjklmnpqrstvwxz2456789]";
+// error bodies longer than this are cut in messages
+const MAX_ERROR_BODY: usize = 300;
+
+// escape a value for use inside a PromQL regex matcher
+fn promql_regex(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    promql_string(&escaped)
+}
+
+impl QueryTemplate {
+    fn info(&self) -> QueryTemplateInfo {
+        let (scope, title, unit) = match self {
+            QueryTemplate::PodCpuUsage => ("pod", "CPU usage", "cores"),
+            QueryTemplate::PodCpuThrottling => ("pod", "CPU throttling", "ratio"),
+            QueryTemplate::PodMemoryWorkingSet => ("pod", "Memory working set", "bytes"),
+            QueryTemplate::PodOomKills => ("pod", "OOM kills", "count"),
+            QueryTemplate::PodRestartRate => ("pod", "Restarts per hour", "count"),
+            QueryTemplate::PodNetworkReceive => ("pod", "Network receive", "bytes/s"),
+            QueryTemplate::PodNetworkTransmit => ("pod", "Network transmit", "bytes/s"),
+            QueryTemplate::WorkloadCpuUsage => ("workload", "CPU usage", "cores"),
+            QueryTemplate::WorkloadMemoryWorkingSet => ("workload", "Memory working set", "bytes"),
+            QueryTemplate::WorkloadRestartRate => ("workload", "Restarts per hour", "count"),
+            QueryTemplate::WorkloadNetworkReceive => ("workload", "Network receive", "bytes/s"),
+            QueryTemplate::WorkloadNetworkTransmit => ("workload", "Network transmit", "bytes/s"),
+            QueryTemplate::NodeCpuUsage => ("node", "CPU usage", "cores"),
+            QueryTemplate::NodeMemoryWorkingSet => ("node", "Memory working set", "bytes"),
+            QueryTemplate::NodeNetworkReceive => ("node", "Network receive", "bytes/s"),
+            QueryTemplate::NodeNetworkTransmit => ("node", "Network transmit", "bytes/s"),
+        };
+        QueryTemplateInfo {
+            template: *self,
+            scope: scope.to_string(),
+            title: title.to_string(),
+            unit: unit.to_string(),
+        }
+    }
+
+    // the PromQL for a pod, a workload (matched by its pods' names) or a node
+    fn query(&self, namespace: &str, name: &str) -> String {
+        let ns = promql_string(namespace);
+        let pod = format!(r#"namespace="{}", pod="{}""#, ns, promql_string(name));
+        let workload = format!(
+            r#"namespace="{}", pod=~"{}""#,
+            ns,
+            workload_pods_regex(name)
+        );
+        let node = format!(r#"node="{}""#, promql_string(name));
+
+        match self {
+            QueryTemplate::PodCpuUsage => format!(
+                r#"sum by (container) (rate(container_cpu_usage_seconds_total{{{}, container!=""}}[5m]))"#,
+                pod
+            ),
+            QueryTemplate::PodCpuThrottling => format!(
+                r#"sum by (container) (rate(container_cpu_cfs_throttled_periods_total{{{0}, container!=""}}[5m])) / sum by (container) (rate(container_cpu_cfs_periods_total{{{0}, container!=""}}[5m]))"#,
+                pod
+            ),
+            QueryTemplate::PodMemoryWorkingSet => format!(
+                r#"sum by (container) (container_memory_working_set_bytes{{{}, container!=""}})"#,
+                pod
+            ),
+            QueryTemplate::PodOomKills => format!(
+                r#"sum by (container) (increase(container_oom_events_total{{{}, container!=""}}[1h]))"#,
+                pod
+            ),
+            QueryTemplate::PodRestartRate => format!(
+                r#"sum by (container) (increase(kube_pod_container_status_restarts_total{{{}}}[1h]))"#,
+                pod
+            ),
+            QueryTemplate::PodNetworkReceive => format!(
+                r#"sum(rate(container_network_receive_bytes_total{{{}}}[5m]))"#,
+                pod
+            ),
+            QueryTemplate::PodNetworkTransmit => format!(
+                r#"sum(rate(container_network_transmit_bytes_total{{{}}}[5m]))"#,
+                pod
+            ),
+            QueryTemplate::WorkloadCpuUsage => format!(
+                r#"sum by (pod) (rate(container_cpu_usage_seconds_total{{{}, container!=""}}[5m]))"#,
+                workload
+            ),
+            QueryTemplate::WorkloadMemoryWorkingSet => format!(
+                r#"sum by (pod) (container_memory_working_set_bytes{{{}, container!=""}})"#,
+                workload
+            ),
+            QueryTemplate::WorkloadRestartRate => format!(
+                r#"sum by (pod) (increase(kube_pod_container_status_restarts_total{{{}}}[1h]))"#,
+                workload
+            ),
+            QueryTemplate::WorkloadNetworkReceive => format!(
+                r#"sum by (pod) (rate(container_network_receive_bytes_total{{{}}}[5m]))"#,
+                workload
+            ),
+            QueryTemplate::WorkloadNetworkTransmit => format!(
+                r#"sum by (pod) (rate(container_network_transmit_bytes_total{{{}}}[5m]))"#,
+                workload
+            ),
+            QueryTemplate::NodeCpuUsage => format!(
+                r#"sum(rate(container_cpu_usage_seconds_total{{{}, id="/"}}[5m]))"#,
+                node
+            ),
+            QueryTemplate::NodeMemoryWorkingSet => format!(
+                r#"sum(container_memory_working_set_bytes{{{}, id="/"}})"#,
+                node
+            ),
+            QueryTemplate::NodeNetworkReceive => format!(
+                r#"sum(rate(container_network_receive_bytes_total{{{}, id="/"}}[5m]))"#,
+                node
+            ),
+            QueryTemplate::NodeNetworkTransmit => format!(
+                r#"sum(rate(container_network_transmit_bytes_total{{{}, id="/"}}[5m]))"#,
+                node
+            ),
+        }
+    }
+}
+
+// the names of the pods a workload creates: <name>-<hash>-<suffix> for Deployments,
+// <name>-<time>-<suffix> for CronJobs, <name>-<suffix> for DaemonSets and Jobs and
+// <name>-<ordinal> for StatefulSets; a plain prefix would also match workload "api-gateway" for "api"
+fn workload_pods_regex(name: &str) -> String {
+    format!(
+        "{0}-((({1}{{1,10}}|[0-9]+)-)?{1}{{5}}|[0-9]+)",
+        promql_regex(name),
+        NAME_SUFFIX_CHARS
+    )
+}
+
+// the service proxy path of the query endpoint, the prefix may come with or without slashes
+fn service_proxy_path(
+    namespace: &str,
+    service: &str,
+    port: &str,
+    https: bool,
+    path_prefix: Option<&str>,
+) -> String {
+    let prefix = path_prefix.unwrap_or_default().trim().trim_matches('/');
+    format!(
+        "/api/v1/namespaces/{}/services/{}:{}:{}/proxy{}{}/api/v1/query_range",
+        namespace,
+        if https { "https" } else { "http" },
+        service,
+        port,
+        if prefix.is_empty() { "" } else { "/" },
+        prefix
+    )
+}
+
+fn query_string(query: &str, start: f64, end: f64, step: f64) -> String {
+    url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("query", query)
+        .append_pair("start", &start.to_string())
+        .append_pair("end", &end.to_string())
+        .append_pair("step", &step.to_string())
+        .finish()
+}
+
+fn parse_response(status: StatusCode, body: &str) -> Result<Vec<TimeSeries>, String> {
+    // Prometheus reports bad queries as JSON with an error status, proxies in front of it
+    // answer with plain text or HTML
+    let response: PrometheusResponse = match serde_json::from_str(body) {
+        Ok(response) => response,
+        Err(_) if !status.is_success() => {
+            let body = body.trim();
+            let body = match body.char_indices().nth(MAX_ERROR_BODY) {
+                Some((end, _)) => format!("{}...", &body[..end]),
+                None => body.to_string(),
+            };
+            return Err(format!("Prometheus returned {}: {}", status, body));
+        }
+        Err(e) => return Err(format!("Unexpected response from Prometheus: {}", e)),
+    };
+    if response.status != "success" {
+        return Err(format!(
+            "Prometheus query failed: {}",
+            response.error.unwrap_or(response.status)
+        ));
+    }
+
+    let data = response
+        .data
+        .ok_or("Prometheus returned no data".to_string())?;
+    if data.result_type != "matrix" {
+        return Err(format!("Unexpected result type: {}", data.result_type));
+    }
+    Ok(data
+        .result
+        .into_iter()
+        .map(|series| TimeSeries {
+            metric: series.metric,
+            values: series
+                .values
+                .into_iter()
+                // NaN and Inf are returned as strings that do not parse to a finite value
+                .filter_map(|(timestamp, value)| {
+                    value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|v| v.is_finite())
+                        .map(|v| (timestamp, v))
+                })
+                .collect(),
+        })
+        .collect())
+}
+
+async fn query_url(url: &str, params: &str) -> Result<Vec<TimeSeries>, String> {
+    let url = format!(
+        "{}/api/v1/query_range?{}",
+        url.trim_end_matches('/'),
+        params
+    );
+    let response = reqwest::get(&url)
+        .await
+        .map_err(|e| format!("Failed to reach Prometheus: {}", e))?;
+    let status = response.status();
+    let body = response.text().await.map_err(|e| e.to_string())?;
+    parse_response(status, &body)
+}
+
+async fn query_range(
+    kubeconfig_path: String,
+    context: String,
+    query: &str,
+    start: f64,
+    end: f64,
+    step: f64,
+) -> Result<Vec<TimeSeries>, String> {
+    if step <= 0.0 || end < start {
+        return Err("Invalid time range".to_string());
+    }
+    let endpoint = app_config::context_settings(&kubeconfig_path, &context)
+        .prometheus
+        .ok_or_else(|| format!("No Prometheus endpoint configured for context {}", context))?;
+    let params = query_string(query, start, end, step);
+
+    match endpoint {
+        PrometheusEndpoint::Url { url } => query_url(&url, &params).await,
+        PrometheusEndpoint::Service {
+            namespace,
+            service,
+            port,
+            https,
+            path_prefix,
+        } => {
+            let client = k8s_client::create_k8s_client(kubeconfig_path, context).await?;
+            let path = format!(
+                "{}?{}",
+                service_proxy_path(&namespace, &service, &port, https, path_prefix.as_deref()),
+                params
+            );
+            let request = http::Request::get(path)
+                .body(vec![])
+                .map_err(|e| e.to_string())?;
+            let (status, body) = k8s_client::request_raw(&client, request)
+                .await
+                .map_err(|e| format!("Failed to reach Prometheus through the API server: {}", e))?;
+            parse_response(status, &body)
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn set_prometheus_endpoint(
+    kubeconfig_path: String,
+    context: String,
+    endpoint: Option<PrometheusEndpoint>,
+) -> Result<(), String> {
+    app_config::update_context(&kubeconfig_path, &context, |settings| {
+        settings.prometheus = endpoint
+    })?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_prometheus_templates() -> Vec<QueryTemplateInfo> {
+    ALL_TEMPLATES.iter().map(|t| t.info()).collect()
+}
+
+// run a PromQL range query, times are unix timestamps and step is in seconds
+#[tauri::command]
+pub async fn prometheus_query_range(
+    kubeconfig_path: String,
+    context: String,
+    query: String,
+    start: f64,
+    end: f64,
+    step: f64,
+) -> Result<Vec<TimeSeries>, String> {
+    query_range(kubeconfig_path, context, &query, start, end, step).await
+}
+
+// run one of the built-in queries for a pod, workload or node page
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn prometheus_template_query(
+    kubeconfig_path: String,
+    context: String,
+    template: QueryTemplate,
+    namespace: Option<String>,
+    name: String,
+    start: f64,
+    end: f64,
+    step: f64,
+) -> Result<Vec<TimeSeries>, String> {
+    let query = template.query(&namespace.unwrap_or_default(), &name);
+    query_range(kubeconfig_path, context, &query, start, end, step).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    const MATRIX: &str = r#"{"status":"success","data":{"resultType":"matrix","result":[{"metric":{"pod":"api-7d9f8b6c5-x2k4p"},"values":[[1700000000,"0.5"],[1700000015,"NaN"],[1700000030,"+Inf"],[1700000045,"1.25"]]}]}}"#;
+
+    #[test]
+    fn escapes_string_literals() {
+        assert_eq!(promql_string(r#"a"b\c"#), r#"a\"b\\c"#);
+    }
+
+    #[test]
+    fn escapes_regex_metacharacters() {
+        assert_eq!(promql_regex("web.v1"), r"web\\.v1");
+        assert_eq!(promql_regex("a+b(c)"), r"a\\+b\\(c\\)");
+        assert_eq!(promql_regex("plain-name"), "plain-name");
+    }
+
+    // Prometheus anchors regexes, so the pattern has to match the whole pod name
+    fn selects(name: &str, pod: &str) -> bool {
+        let pattern = workload_pods_regex(name).replace(r"\\", r"\");
+        regex::Regex::new(&format!("^(?:{})$", pattern))
+            .unwrap()
+            .is_match(pod)
+    }
+
+    #[test]
+    fn workload_regex_matches_its_own_pods_only() {
+        assert!(selects("api", "api-7d9f8b6c5-x2k4p"));
+        assert!(selects("api", "api-x2k4p"));
+        assert!(selects("api", "api-0"));
+        assert!(selects("api", "api-28401230-x2k4p"));
+        assert!(selects("web.v1", "web.v1-x2k4p"));
+        assert!(!selects("web.v1", "webxv1-x2k4p"));
+        assert!(!selects("api", "api-gateway-7d9f8b6c5-x2k4p"));
+        assert!(!selects("api", "api-gateway-x2k4p"));
+        assert!(!selects("api", "api-v2-0"));
+    }
+
+    #[test]
+    fn service_proxy_path_normalises_prefix() {
+        let expected = "/api/v1/namespaces/monitoring/services/https:prometheus:9090/proxy/prom/api/v1/query_range";
+        for prefix in ["prom", "/prom", "/prom/", " prom/ "] {
+            assert_eq!(
+                service_proxy_path("monitoring", "prometheus", "9090", true, Some(prefix)),
+                expected
+            );
+        }
+        assert_eq!(
+            service_proxy_path("monitoring", "prometheus", "web", false, Some("/")),
+            "/api/v1/namespaces/monitoring/services/http:prometheus:web/proxy/api/v1/query_range"
+        );
+    }
+
+    #[test]
+    fn drops_non_finite_values() {
+        let series = parse_response(StatusCode::OK, MATRIX).unwrap();
+        assert_eq!(series.len(), 1);
+        assert_eq!(
+            series[0].values,
+            vec![(1700000000.0, 0.5), (1700000045.0, 1.25)]
+        );
+    }
+
+    // a stand-in for Prometheus that answers one request and hands back its request line
+    async fn stand_in(
+        status: &'static str,
+        body: &'static str,
+    ) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![];
+            let mut buf = [0u8; 1024];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                let n = socket.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            let request = String::from_utf8_lossy(&request);
+            request.lines().next().unwrap_or_default().to_string()
+        });
+        (url, handle)
+    }
+
+    #[tokio::test]
+    async fn queries_url_endpoint() {
+        let (url, request) = stand_in("200 OK", MATRIX).await;
+        let params = query_string(r#"up{job="api"}"#, 1700000000.0, 1700000060.0, 15.0);
+        let series = query_url(&url, &params).await.unwrap();
+        assert_eq!(series[0].metric["pod"], "api-7d9f8b6c5-x2k4p");
+        assert_eq!(
+            request.await.unwrap(),
+            format!("GET /api/v1/query_range?{} HTTP/1.1", params)
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_prometheus_errors() {
+        let (url, _) = stand_in(
+            "400 Bad Request",
+            r#"{"status":"error","errorType":"bad_data","error":"parse error at char 3"}"#,
+        )
+        .await;
+        let error = query_url(&url, "query=up(").await.unwrap_err();
+        assert_eq!(error, "Prometheus query failed: parse error at char 3");
+    }
+
+    #[tokio::test]
+    async fn reports_status_of_non_json_errors() {
+        let (url, _) = stand_in("502 Bad Gateway", "upstream connect error").await;
+        let error = query_url(&url, "query=up").await.unwrap_err();
+        assert_eq!(
+            error,
+            "Prometheus returned 502 Bad Gateway: upstream connect error"
+        );
+    }
+}
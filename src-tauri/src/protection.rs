@@ -0,0 +1,152 @@
+use crate::app_config;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// how careful the app is with changes to a context's cluster
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum ProtectionLevel {
+    #[default]
+    Unrestricted,
+    // every change needs the context name typed as confirmation
+    ConfirmRequired,
+    // every change is rejected
+    ReadOnly,
+}
+
+// the kinds of changes a command makes to a cluster
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Action {
+    Delete,
+    Scale,
+    Restart,
+    Cordon,
+    Uncordon,
+    Drain,
+    Taint,
+    UpdateMetadata,
+    RemoveFinalizers,
+    Exec,
+    Debug,
+    CopyToPod,
+    CopyFromPod,
+    Restore,
+    // reading a Secret is recorded but allowed on read-only contexts
+    RevealSecret,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Action::Delete => "delete",
+            Action::Scale => "scale",
+            Action::Restart => "restart",
+            Action::Cordon => "cordon",
+            Action::Uncordon => "uncordon",
+            Action::Drain => "drain",
+            Action::Taint => "taint",
+            Action::UpdateMetadata => "update labels and annotations",
+            Action::RemoveFinalizers => "remove finalizers",
+            Action::Exec => "exec",
+            Action::Debug => "debug",
+            Action::CopyToPod => "copy files to a pod",
+            Action::CopyFromPod => "copy files from a pod",
+            Action::Restore => "restore a deleted resource",
+            Action::RevealSecret => "reveal a secret",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// unreadable settings are an error rather than the default, which would allow every change
+pub fn context_protection(kubeconfig_path: &str, context: &str) -> Result<ProtectionLevel, String> {
+    let config = app_config::load()
+        .map_err(|e| format!("Cannot check the protection of context {}: {}", context, e))?;
+    Ok(config.context(kubeconfig_path, context).protection)
+}
+
+// check a mutating command against the context's protection level before it runs;
+// the confirmation is the context name as typed by the user
+pub(crate) fn authorize(
+    kubeconfig_path: &str,
+    context: &str,
+    action: Action,
+    confirmation: Option<&str>,
+) -> Result<(), String> {
+    match context_protection(kubeconfig_path, context)? {
+        ProtectionLevel::Unrestricted => Ok(()),
+        ProtectionLevel::ReadOnly => Err(format!(
+            "Context {} is read-only, {} is not allowed",
+            context, action
+        )),
+        ProtectionLevel::ConfirmRequired if confirmation == Some(context) => Ok(()),
+        ProtectionLevel::ConfirmRequired => Err(format!(
+            "Context {} is protected, type the context name to confirm {}",
+            context, action
+        )),
+    }
+}
+
+// lowering the protection of a context needs the same confirmation as a change to it
+#[tauri::command]
+pub async fn set_context_protection(
+    kubeconfig_path: String,
+    context: String,
+    level: ProtectionLevel,
+    confirmation: Option<String>,
+) -> Result<ProtectionLevel, String> {
+    let current = context_protection(&kubeconfig_path, &context)?;
+    if level < current && confirmation.as_deref() != Some(context.as_str()) {
+        return Err(format!(
+            "Type the context name to lower the protection of {}",
+            context
+        ));
+    }
+    let settings =
+        app_config::update_context(&kubeconfig_path, &context, |s| s.protection = level)?;
+    Ok(settings.protection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn unreadable_settings_block_changes() {
+        let settings = app_config::init_for_tests().join("settings.json");
+        std::fs::create_dir_all(settings.parent().unwrap()).unwrap();
+        std::fs::write(&settings, "{ not json").unwrap();
+
+        let rejected = authorize(
+            "/home/me/.kube/config",
+            "prod",
+            Action::Delete,
+            Some("prod"),
+        );
+        assert!(rejected
+            .unwrap_err()
+            .contains("Cannot check the protection"));
+        let lowered = set_context_protection(
+            "/home/me/.kube/config".into(),
+            "prod".into(),
+            ProtectionLevel::Unrestricted,
+            Some("prod".into()),
+        )
+        .await;
+        assert!(lowered.is_err());
+        assert_eq!(std::fs::read_to_string(&settings).unwrap(), "{ not json");
+        std::fs::remove_file(&settings).unwrap();
+    }
+
+    #[test]
+    fn levels_are_ordered_from_least_to_most_protective() {
+        assert_eq!(ProtectionLevel::default(), ProtectionLevel::Unrestricted);
+        assert!(ProtectionLevel::Unrestricted < ProtectionLevel::ConfirmRequired);
+        assert!(ProtectionLevel::ConfirmRequired < ProtectionLevel::ReadOnly);
+        assert_eq!(
+            serde_json::to_string(&ProtectionLevel::ConfirmRequired).unwrap(),
+            r#""confirmRequired""#
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+
+// parse a quantity such as "250m", "1.5Gi" or "1e3" into its value in base units
+// (cores for cpu, bytes for memory)
+pub fn parse_quantity(quantity: &str) -> Result<f64, String> {
+    let quantity = quantity.trim();
+    let split = quantity
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '+' || c == '-'))
+        .unwrap_or(quantity.len());
+    let (number, suffix) = quantity.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("Invalid quantity: {}", quantity))?;
+
+    let multiplier = match suffix {
+        "" => 1.0,
+        "n" => 1e-9,
+        "u" => 1e-6,
+        "m" => 1e-3,
+        "k" => 1e3,
+        "M" => 1e6,
+        "G" => 1e9,
+        "T" => 1e12,
+        "P" => 1e15,
+        "E" => 1e18,
+        "Ki" => 1024.0,
+        "Mi" => 1024f64.powi(2),
+        "Gi" => 1024f64.powi(3),
+        "Ti" => 1024f64.powi(4),
+        "Pi" => 1024f64.powi(5),
+        "Ei" => 1024f64.powi(6),
+        _ => {
+            // decimal exponent, e.g. "1e3" or "5E-2"
+            let exponent = suffix
+                .strip_prefix(['e', 'E'])
+                .and_then(|e| e.parse::<i32>().ok())
+                .ok_or_else(|| format!("Invalid quantity: {}", quantity))?;
+            10f64.powi(exponent)
+        }
+    };
+    Ok(number * multiplier)
+}
+
+pub fn quantity_value(quantity: &Quantity) -> Option<f64> {
+    parse_quantity(&quantity.0).ok()
+}
+
+// usage as a percentage of a capacity, if there is one
+pub fn percent(value: f64, of: Option<f64>) -> Option<f64> {
+    of.filter(|of| *of > 0.0).map(|of| value / of * 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(quantity: &str, expected: f64) {
+        let value = parse_quantity(quantity).unwrap();
+        assert!(
+            (value - expected).abs() <= expected.abs() * 1e-12,
+            "{} parsed as {}, expected {}",
+            quantity,
+            value,
+            expected
+        );
+    }
+
+    #[test]
+    fn parses_decimal_and_binary_suffixes() {
+        assert_close("250m", 0.25);
+        assert_close("2", 2.0);
+        assert_close("1.5Gi", 1.5 * 1024.0 * 1024.0 * 1024.0);
+        assert_close("128Mi", 128.0 * 1024.0 * 1024.0);
+        assert_close("100k", 100_000.0);
+        assert_close("1G", 1e9);
+        assert_close("500n", 5e-7);
+        assert_close(" 3Ki ", 3072.0);
+    }
+
+    #[test]
+    fn exa_suffix_differs_from_exponent() {
+        assert_close("1E", 1e18);
+        assert_close("1e3", 1000.0);
+        assert_close("5E-2", 0.05);
+        assert_close("12e+2", 1200.0);
+    }
+
+    #[test]
+    fn rejects_invalid_quantities() {
+        for quantity in ["", "Gi", "1.2.3", "10x", "1ee3", "1Ki2"] {
+            assert!(parse_quantity(quantity).is_err(), "{}", quantity);
+        }
+    }
+
+    #[test]
+    fn percent_needs_positive_capacity() {
+        assert_eq!(percent(50.0, Some(200.0)), Some(25.0));
+        assert_eq!(percent(50.0, Some(0.0)), None);
+        assert_eq!(percent(50.0, None), None);
+    }
+}
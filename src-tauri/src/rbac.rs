@@ -0,0 +1,523 @@
+use crate::k8s_client;
+use k8s_openapi::api::rbac::v1::{
+    ClusterRole, ClusterRoleBinding, PolicyRule, Role, RoleBinding, RoleRef, Subject,
+};
+use kube::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+const READ_VERBS: [&str; 3] = ["get", "list", "watch"];
+const ESCALATION_VERBS: [&str; 3] = ["escalate", "bind", "impersonate"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RbacSubject {
+    // User, Group or ServiceAccount
+    pub kind: String,
+    pub name: String,
+    // only for ServiceAccounts
+    pub namespace: Option<String>,
+}
+
+// the binding and role a permission comes from
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GrantSource {
+    pub binding_kind: String,
+    pub binding_name: String,
+    pub binding_namespace: Option<String>,
+    pub role_kind: String,
+    pub role_name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveRule {
+    pub verbs: Vec<String>,
+    pub api_groups: Vec<String>,
+    pub resources: Vec<String>,
+    pub resource_names: Vec<String>,
+    pub non_resource_urls: Vec<String>,
+    // None when the rule applies to every namespace
+    pub namespace: Option<String>,
+    pub source: GrantSource,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RiskyGrant {
+    // clusterAdmin, wildcard, escalation or secretsRead
+    pub risk: String,
+    pub description: String,
+    pub subjects: Vec<RbacSubject>,
+    pub namespace: Option<String>,
+    pub source: GrantSource,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SubjectPermissions {
+    pub subject: RbacSubject,
+    pub rules: Vec<EffectiveRule>,
+    pub risks: Vec<RiskyGrant>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SubjectAccess {
+    pub subject: RbacSubject,
+    pub namespace: Option<String>,
+    // the object names the access is limited to, empty when it covers every object
+    pub resource_names: Vec<String>,
+    pub source: GrantSource,
+}
+
+// a binding reduced to what the analysis needs
+struct Grant {
+    subjects: Vec<Subject>,
+    namespace: Option<String>,
+    rules: Vec<PolicyRule>,
+    source: GrantSource,
+}
+
+// the rules of aggregated ClusterRoles are already copied into them by the controller
+fn role_rules(
+    role_ref: &RoleRef,
+    namespace: Option<&str>,
+    roles: &HashMap<(String, String), Role>,
+    cluster_roles: &HashMap<String, ClusterRole>,
+) -> Vec<PolicyRule> {
+    if role_ref.kind == "ClusterRole" {
+        return cluster_roles
+            .get(&role_ref.name)
+            .and_then(|role| role.rules.clone())
+            .unwrap_or_default();
+    }
+    namespace
+        .and_then(|ns| roles.get(&(ns.to_string(), role_ref.name.clone())))
+        .and_then(|role| role.rules.clone())
+        .unwrap_or_default()
+}
+
+async fn load_grants(client: Client) -> Result<Vec<Grant>, String> {
+    let (roles, cluster_roles, bindings, cluster_bindings) = futures::join!(
+        k8s_client::list_resources::<Role>(client.clone(), "all", true),
+        k8s_client::list_cluster_resources::<ClusterRole>(client.clone()),
+        k8s_client::list_resources::<RoleBinding>(client.clone(), "all", true),
+        k8s_client::list_cluster_resources::<ClusterRoleBinding>(client)
+    );
+    let roles: HashMap<(String, String), Role> = roles?
+        .into_iter()
+        .map(|role| {
+            let key = (
+                role.metadata.namespace.clone().unwrap_or_default(),
+                role.metadata.name.clone().unwrap_or_default(),
+            );
+            (key, role)
+        })
+        .collect();
+    let cluster_roles: HashMap<String, ClusterRole> = cluster_roles?
+        .into_iter()
+        .map(|role| (role.metadata.name.clone().unwrap_or_default(), role))
+        .collect();
+
+    let mut grants = vec![];
+    for binding in cluster_bindings? {
+        grants.push(Grant {
+            subjects: binding.subjects.clone().unwrap_or_default(),
+            namespace: None,
+            rules: role_rules(&binding.role_ref, None, &roles, &cluster_roles),
+            source: GrantSource {
+                binding_kind: "ClusterRoleBinding".to_string(),
+                binding_name: binding.metadata.name.clone().unwrap_or_default(),
+                binding_namespace: None,
+                role_kind: binding.role_ref.kind.clone(),
+                role_name: binding.role_ref.name.clone(),
+            },
+        });
+    }
+    for binding in bindings? {
+        let namespace = binding.metadata.namespace.clone();
+        grants.push(Grant {
+            subjects: binding.subjects.clone().unwrap_or_default(),
+            rules: role_rules(
+                &binding.role_ref,
+                namespace.as_deref(),
+                &roles,
+                &cluster_roles,
+            ),
+            source: GrantSource {
+                binding_kind: "RoleBinding".to_string(),
+                binding_name: binding.metadata.name.clone().unwrap_or_default(),
+                binding_namespace: namespace.clone(),
+                role_kind: binding.role_ref.kind.clone(),
+                role_name: binding.role_ref.name.clone(),
+            },
+            namespace,
+        });
+    }
+    Ok(grants)
+}
+
+fn to_subject(subject: &Subject, binding_namespace: Option<&str>) -> RbacSubject {
+    let namespace = if subject.kind == "ServiceAccount" {
+        // a ServiceAccount subject without namespace refers to the binding's namespace
+        subject
+            .namespace
+            .clone()
+            .or_else(|| binding_namespace.map(|ns| ns.to_string()))
+    } else {
+        None
+    };
+    RbacSubject {
+        kind: subject.kind.clone(),
+        name: subject.name.clone(),
+        namespace,
+    }
+}
+
+// whether a binding subject applies to a subject, directly or through its implicit groups
+fn subject_matches(
+    binding_subject: &Subject,
+    binding_namespace: Option<&str>,
+    subject: &RbacSubject,
+) -> bool {
+    let candidate = to_subject(binding_subject, binding_namespace);
+    if candidate == *subject {
+        return true;
+    }
+    if candidate.kind != "Group" {
+        return false;
+    }
+    let mut groups = vec!["system:authenticated".to_string()];
+    if subject.kind == "ServiceAccount" {
+        groups.push("system:serviceaccounts".to_string());
+        if let Some(namespace) = &subject.namespace {
+            groups.push(format!("system:serviceaccounts:{}", namespace));
+        }
+    }
+    subject.kind != "Group" && groups.contains(&candidate.name)
+}
+
+fn matches_value(values: &[String], value: &str) -> bool {
+    values.iter().any(|v| v == "*" || v == value)
+}
+
+fn rule_allows(rule: &PolicyRule, verb: &str, group: &str, resource: &str) -> bool {
+    let resources = rule.resources.as_deref().unwrap_or_default();
+    let resource_matches = resources.iter().any(|r| {
+        r == "*"
+            || r == resource
+            // "*/scale" matches the scale subresource of every resource
+            || r.strip_prefix("*/")
+                .is_some_and(|sub| resource.split_once('/').is_some_and(|(_, s)| s == sub))
+    });
+    matches_value(&rule.verbs, verb)
+        && matches_value(rule.api_groups.as_deref().unwrap_or_default(), group)
+        && resource_matches
+}
+
+// the names the matching rules limit the access to, empty when one of them allows
+// every name; None when no rule matches
+fn allowed_names(
+    rules: &[PolicyRule],
+    verb: &str,
+    group: &str,
+    resource: &str,
+) -> Option<Vec<String>> {
+    let mut names = BTreeSet::new();
+    let mut matched = false;
+    for rule in rules
+        .iter()
+        .filter(|rule| rule_allows(rule, verb, group, resource))
+    {
+        let rule_names = rule.resource_names.as_deref().unwrap_or_default();
+        if rule_names.is_empty() {
+            return Some(vec![]);
+        }
+        matched = true;
+        names.extend(rule_names.iter().cloned());
+    }
+    matched.then(|| names.into_iter().collect())
+}
+
+fn risks_of(grant: &Grant) -> Vec<(String, String)> {
+    let mut risks = vec![];
+    if grant.source.role_kind == "ClusterRole" && grant.source.role_name == "cluster-admin" {
+        let scope = if grant.namespace.is_some() {
+            "in its namespace"
+        } else {
+            "on the whole cluster"
+        };
+        risks.push((
+            "clusterAdmin".to_string(),
+            format!("Binds cluster-admin {}", scope),
+        ));
+        return risks;
+    }
+    for rule in &grant.rules {
+        let verbs = &rule.verbs;
+        let resources = rule.resources.as_deref().unwrap_or_default();
+        let groups = rule.api_groups.as_deref().unwrap_or_default();
+        if verbs.iter().any(|v| v == "*") && resources.iter().any(|r| r == "*") {
+            risks.push((
+                "wildcard".to_string(),
+                format!(
+                    "Allows every verb on every resource in api groups {}",
+                    groups.join(", ")
+                ),
+            ));
+        }
+        for verb in ESCALATION_VERBS {
+            if matches_value(verbs, verb) && !resources.is_empty() {
+                risks.push((
+                    "escalation".to_string(),
+                    format!("Allows {} on {}", verb, resources.join(", ")),
+                ));
+            }
+        }
+        if READ_VERBS
+            .iter()
+            .any(|verb| rule_allows(rule, verb, "", "secrets"))
+        {
+            let names = rule.resource_names.as_deref().unwrap_or_default();
+            let which = if names.is_empty() {
+                "all secrets".to_string()
+            } else {
+                format!("secrets {}", names.join(", "))
+            };
+            risks.push((
+                "secretsRead".to_string(),
+                format!("Allows reading {}", which),
+            ));
+        }
+    }
+    risks
+}
+
+fn risky_grants(grant: &Grant) -> Vec<RiskyGrant> {
+    let subjects: Vec<RbacSubject> = grant
+        .subjects
+        .iter()
+        .map(|s| to_subject(s, grant.namespace.as_deref()))
+        .collect();
+    risks_of(grant)
+        .into_iter()
+        .map(|(risk, description)| RiskyGrant {
+            risk,
+            description,
+            subjects: subjects.clone(),
+            namespace: grant.namespace.clone(),
+            source: grant.source.clone(),
+        })
+        .collect()
+}
+
+// everything a user, group or ServiceAccount may do, with the binding each rule comes from
+#[tauri::command]
+pub async fn subject_permissions(
+    kubeconfig_path: String,
+    context: String,
+    mut subject: RbacSubject,
+    request_id: Option<String>,
+) -> Result<SubjectPermissions, String> {
+    if subject.kind != "ServiceAccount" {
+        subject.namespace = None;
+    }
+    let (client, _request) =
+        k8s_client::create_tracked_client(kubeconfig_path, context, request_id).await?;
+    let grants = load_grants(client).await?;
+
+    let mut rules = vec![];
+    let mut risks = vec![];
+    for grant in grants.iter().filter(|grant| {
+        grant
+            .subjects
+            .iter()
+            .any(|s| subject_matches(s, grant.namespace.as_deref(), &subject))
+    }) {
+        for rule in &grant.rules {
+            rules.push(EffectiveRule {
+                verbs: rule.verbs.clone(),
+                api_groups: rule.api_groups.clone().unwrap_or_default(),
+                resources: rule.resources.clone().unwrap_or_default(),
+                resource_names: rule.resource_names.clone().unwrap_or_default(),
+                non_resource_urls: rule.non_resource_urls.clone().unwrap_or_default(),
+                namespace: grant.namespace.clone(),
+                source: grant.source.clone(),
+            });
+        }
+        risks.extend(risky_grants(grant));
+    }
+    Ok(SubjectPermissions {
+        subject,
+        rules,
+        risks,
+    })
+}
+
+// subjects allowed to use a verb on a resource, e.g. who can "delete" "secrets" in a namespace;
+// resource may name a subresource like "pods/exec", namespace None asks about cluster wide access
+#[tauri::command]
+pub async fn who_can(
+    kubeconfig_path: String,
+    context: String,
+    verb: String,
+    resource: String,
+    group: Option<String>,
+    namespace: Option<String>,
+    request_id: Option<String>,
+) -> Result<Vec<SubjectAccess>, String> {
+    let (client, _request) =
+        k8s_client::create_tracked_client(kubeconfig_path, context, request_id).await?;
+    let grants = load_grants(client).await?;
+    let group = group.unwrap_or_default();
+
+    let mut access = vec![];
+    for grant in grants {
+        let in_scope = match (&grant.namespace, &namespace) {
+            (None, _) => true,
+            (Some(granted), Some(asked)) => granted == asked,
+            (Some(_), None) => false,
+        };
+        if !in_scope {
+            continue;
+        }
+        let Some(resource_names) = allowed_names(&grant.rules, &verb, &group, &resource) else {
+            continue;
+        };
+        for subject in &grant.subjects {
+            access.push(SubjectAccess {
+                subject: to_subject(subject, grant.namespace.as_deref()),
+                namespace: grant.namespace.clone(),
+                resource_names: resource_names.clone(),
+                source: grant.source.clone(),
+            });
+        }
+    }
+    Ok(access)
+}
+
+// risky grants across the cluster: cluster-admin bindings, wildcards,
+// escalate/bind/impersonate and secret reads
+#[tauri::command]
+pub async fn find_risky_rbac_grants(
+    kubeconfig_path: String,
+    context: String,
+    request_id: Option<String>,
+) -> Result<Vec<RiskyGrant>, String> {
+    let (client, _request) =
+        k8s_client::create_tracked_client(kubeconfig_path, context, request_id).await?;
+    let grants = load_grants(client).await?;
+    Ok(grants
+        .iter()
+        // bindings of the system components are expected to be powerful
+        .filter(|grant| !grant.source.binding_name.starts_with("system:"))
+        .flat_map(risky_grants)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(verbs: &[&str], groups: &[&str], resources: &[&str], names: &[&str]) -> PolicyRule {
+        let strings = |values: &[&str]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        PolicyRule {
+            verbs: strings(verbs),
+            api_groups: Some(strings(groups)),
+            resources: Some(strings(resources)),
+            resource_names: (!names.is_empty()).then(|| strings(names)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rule_matches_verb_group_and_resource() {
+        let pods = rule(&["get", "list"], &[""], &["pods"], &[]);
+        assert!(rule_allows(&pods, "get", "", "pods"));
+        assert!(!rule_allows(&pods, "delete", "", "pods"));
+        assert!(!rule_allows(&pods, "get", "apps", "pods"));
+        assert!(!rule_allows(&pods, "get", "", "services"));
+        // a subresource needs its own rule
+        assert!(!rule_allows(&pods, "get", "", "pods/exec"));
+    }
+
+    #[test]
+    fn rule_wildcards() {
+        let all = rule(&["*"], &["*"], &["*"], &[]);
+        assert!(rule_allows(&all, "delete", "apps", "deployments"));
+        assert!(rule_allows(&all, "create", "", "pods/exec"));
+
+        let scale = rule(&["update"], &["apps"], &["*/scale"], &[]);
+        assert!(rule_allows(&scale, "update", "apps", "deployments/scale"));
+        assert!(!rule_allows(&scale, "update", "apps", "deployments"));
+        assert!(!rule_allows(&scale, "update", "apps", "deployments/status"));
+    }
+
+    #[test]
+    fn rule_without_groups_matches_nothing() {
+        let rule = PolicyRule {
+            verbs: vec!["get".to_string()],
+            resources: Some(vec!["pods".to_string()]),
+            ..Default::default()
+        };
+        assert!(!rule_allows(&rule, "get", "", "pods"));
+    }
+
+    #[test]
+    fn names_limit_access_unless_a_rule_allows_all() {
+        let limited = rule(&["get"], &[""], &["secrets"], &["b", "a"]);
+        let other = rule(&["get"], &[""], &["secrets"], &["c"]);
+        let unlimited = rule(&["get"], &[""], &["secrets"], &[]);
+        let unrelated = rule(&["get"], &[""], &["pods"], &[]);
+
+        assert_eq!(
+            allowed_names(&[limited.clone(), other.clone()], "get", "", "secrets"),
+            Some(vec!["a".to_string(), "b".to_string(), "c".to_string()])
+        );
+        assert_eq!(
+            allowed_names(&[limited, unlimited], "get", "", "secrets"),
+            Some(vec![])
+        );
+        assert_eq!(allowed_names(&[unrelated], "get", "", "secrets"), None);
+    }
+
+    #[test]
+    fn groups_apply_to_their_members() {
+        let group = |name: &str| Subject {
+            kind: "Group".to_string(),
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let account = RbacSubject {
+            kind: "ServiceAccount".to_string(),
+            name: "builder".to_string(),
+            namespace: Some("ci".to_string()),
+        };
+        assert!(subject_matches(
+            &group("system:serviceaccounts:ci"),
+            None,
+            &account
+        ));
+        assert!(subject_matches(
+            &group("system:authenticated"),
+            None,
+            &account
+        ));
+        assert!(!subject_matches(
+            &group("system:serviceaccounts:prod"),
+            None,
+            &account
+        ));
+
+        // a ServiceAccount subject without namespace is in the binding's namespace
+        let subject = Subject {
+            kind: "ServiceAccount".to_string(),
+            name: "builder".to_string(),
+            ..Default::default()
+        };
+        assert!(subject_matches(&subject, Some("ci"), &account));
+        assert!(!subject_matches(&subject, Some("prod"), &account));
+    }
+}
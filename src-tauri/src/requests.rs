@@ -0,0 +1,359 @@
+use futures::future::BoxFuture;
+use http::{header, Method, Request, Response, StatusCode};
+use kube::client::{AuthError, Body};
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tower::{BoxError, Layer, Service};
+
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_RETRIES: u32 = 3;
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(250);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(5);
+
+// a single attempt took longer than the timeout; reads are tried again
+#[derive(Debug)]
+struct RequestTimedOut(Duration);
+
+impl std::fmt::Display for RequestTimedOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Request timed out after {}s", self.0.as_secs())
+    }
+}
+
+impl std::error::Error for RequestTimedOut {}
+
+// a context is identified by its kubeconfig too, two files may both name a context "prod"
+type ContextKey = (String, String);
+
+// one token per context, replaced when the context's requests are cancelled
+static CONTEXT_TOKENS: LazyLock<Mutex<HashMap<ContextKey, CancellationToken>>> =
+    LazyLock::new(Default::default);
+// tokens of in-flight commands that were given a request id by the frontend
+static REQUEST_TOKENS: LazyLock<Mutex<HashMap<String, CancellationToken>>> =
+    LazyLock::new(Default::default);
+
+pub(crate) fn context_token(kubeconfig_path: &str, context: &str) -> CancellationToken {
+    let key = (kubeconfig_path.to_string(), context.to_string());
+    CONTEXT_TOKENS
+        .lock()
+        .map(|mut tokens| tokens.entry(key).or_default().clone())
+        .unwrap_or_default()
+}
+
+// keeps a request id cancellable while the command that registered it runs
+pub struct RequestGuard {
+    request_id: Option<String>,
+    token: CancellationToken,
+}
+
+impl RequestGuard {
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+}
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        if let (Some(request_id), Ok(mut tokens)) = (&self.request_id, REQUEST_TOKENS.lock()) {
+            tokens.remove(request_id);
+        }
+    }
+}
+
+// register a request id, its token is also cancelled with the context's requests
+pub(crate) fn track(
+    kubeconfig_path: &str,
+    context: &str,
+    request_id: Option<String>,
+) -> RequestGuard {
+    let token = context_token(kubeconfig_path, context).child_token();
+    if let (Some(request_id), Ok(mut tokens)) = (&request_id, REQUEST_TOKENS.lock()) {
+        if let Some(previous) = tokens.insert(request_id.clone(), token.clone()) {
+            previous.cancel();
+        }
+    }
+    RequestGuard { request_id, token }
+}
+
+// timeout, retries and cancellation around every request a client sends
+#[derive(Clone)]
+pub struct RequestLayer {
+    pub timeout: Duration,
+    pub token: CancellationToken,
+}
+
+impl<S> Layer<S> for RequestLayer {
+    type Service = RequestService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RequestService<S> {
+    inner: S,
+    layer: RequestLayer,
+}
+
+// watches, followed logs and exec/attach/port-forward upgrades stay open on purpose
+fn is_streaming(request: &Request<Body>) -> bool {
+    let query = request.uri().query().unwrap_or_default();
+    request.headers().contains_key(header::UPGRADE)
+        || query
+            .split('&')
+            .any(|pair| pair == "watch=true" || pair == "watch=1" || pair == "follow=true")
+}
+
+// the failure happened before the server saw the request, was a dropped connection or a timeout;
+// errors of exec plugins and other auth failures are not worth repeating
+fn is_retryable_error(error: &BoxError) -> bool {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(error.as_ref());
+    let mut retryable = false;
+    while let Some(e) = source {
+        if e.is::<AuthError>() {
+            return false;
+        }
+        retryable |= e.is::<RequestTimedOut>();
+        if let Some(io) = e.downcast_ref::<std::io::Error>() {
+            retryable |= matches!(
+                io.kind(),
+                std::io::ErrorKind::ConnectionRefused
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::NotConnected
+                    | std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::TimedOut
+                    | std::io::ErrorKind::UnexpectedEof
+            );
+        }
+        source = e.source();
+    }
+    retryable
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+// exponential backoff with full jitter, or the server's Retry-After when it sent one
+fn retry_delay<B>(attempt: u32, response: Option<&Response<B>>) -> Duration {
+    let retry_after = response
+        .and_then(|r| r.headers().get(header::RETRY_AFTER))
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .map(Duration::from_secs);
+    if let Some(delay) = retry_after {
+        return delay.min(RETRY_MAX_DELAY);
+    }
+    let ceiling = RETRY_BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(RETRY_MAX_DELAY);
+    rand::thread_rng().gen_range(Duration::ZERO..=ceiling)
+}
+
+impl<S, B> Service<Request<Body>> for RequestService<S>
+where
+    S: Service<Request<Body>, Response = Response<B>, Error = BoxError> + Clone + Send + 'static,
+    S::Future: Send,
+    B: Send + 'static,
+{
+    type Response = Response<B>;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<Response<B>, BoxError>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        // the ready service handles this call, a fresh clone takes its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let RequestLayer { timeout, token } = self.layer.clone();
+
+        Box::pin(async move {
+            if is_streaming(&request) {
+                return tokio::select! {
+                    response = inner.call(request) => response,
+                    _ = token.cancelled() => Err("Request cancelled".into()),
+                };
+            }
+
+            // reads carry no body, so they can be sent again from their head
+            let idempotent = matches!(*request.method(), Method::GET | Method::HEAD);
+            let (parts, body) = request.into_parts();
+            let mut body = Some(body);
+            let mut attempt = 0;
+            loop {
+                let request =
+                    Request::from_parts(parts.clone(), body.take().unwrap_or_else(Body::empty));
+                let outcome = tokio::select! {
+                    outcome = tokio::time::timeout(timeout, async {
+                        futures::future::poll_fn(|cx| inner.poll_ready(cx)).await?;
+                        inner.call(request).await
+                    }) => outcome,
+                    _ = token.cancelled() => return Err("Request cancelled".into()),
+                };
+                let result =
+                    outcome.unwrap_or_else(|_| Err(Box::new(RequestTimedOut(timeout)) as BoxError));
+
+                let retry = idempotent
+                    && attempt < MAX_RETRIES
+                    && match &result {
+                        Ok(response) => is_retryable_status(response.status()),
+                        Err(e) => is_retryable_error(e),
+                    };
+                if !retry {
+                    return result;
+                }
+                let delay = retry_delay(attempt, result.as_ref().ok());
+                attempt += 1;
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = token.cancelled() => return Err("Request cancelled".into()),
+                }
+            }
+        })
+    }
+}
+
+// abort a command started with this request id
+#[tauri::command]
+pub async fn cancel_request(request_id: String) -> Result<bool, String> {
+    let token = REQUEST_TOKENS
+        .lock()
+        .map_err(|e| e.to_string())?
+        .remove(&request_id);
+    Ok(token.map(|token| token.cancel()).is_some())
+}
+
+// abort every pending call of a context, e.g. when the user switches to another one;
+// clients created afterwards get a fresh token
+#[tauri::command]
+pub async fn cancel_context_requests(
+    kubeconfig_path: String,
+    context: String,
+) -> Result<(), String> {
+    let token = CONTEXT_TOKENS
+        .lock()
+        .map_err(|e| e.to_string())?
+        .remove(&(kubeconfig_path, context));
+    if let Some(token) = token {
+        token.cancel();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    fn get(uri: &str) -> Request<Body> {
+        Request::get(uri).body(Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn streaming_requests_are_detected() {
+        assert!(is_streaming(&get("/api/v1/pods?watch=true")));
+        assert!(is_streaming(&get(
+            "/api/v1/namespaces/default/pods/web/log?container=app&follow=true"
+        )));
+        let exec = Request::get("/api/v1/namespaces/default/pods/web/exec")
+            .header(header::UPGRADE, "websocket")
+            .body(Body::empty())
+            .unwrap();
+        assert!(is_streaming(&exec));
+        assert!(!is_streaming(&get(
+            "/api/v1/pods?labelSelector=watch%3Dtrue"
+        )));
+        assert!(!is_streaming(&get("/api/v1/namespaces/default/pods")));
+    }
+
+    #[test]
+    fn retry_after_is_honoured_up_to_the_cap() {
+        let response = |value: &str| {
+            Response::builder()
+                .status(StatusCode::TOO_MANY_REQUESTS)
+                .header(header::RETRY_AFTER, value)
+                .body(())
+                .unwrap()
+        };
+        assert_eq!(retry_delay(0, Some(&response("2"))), Duration::from_secs(2));
+        assert_eq!(retry_delay(0, Some(&response("120"))), RETRY_MAX_DELAY);
+    }
+
+    #[test]
+    fn backoff_stays_below_its_ceiling() {
+        for attempt in 0..6 {
+            let ceiling = RETRY_BASE_DELAY
+                .saturating_mul(2u32.saturating_pow(attempt))
+                .min(RETRY_MAX_DELAY);
+            for _ in 0..20 {
+                assert!(retry_delay::<()>(attempt, None) <= ceiling);
+            }
+        }
+    }
+
+    #[test]
+    fn timeouts_and_dropped_connections_are_retried() {
+        let timed_out: BoxError = Box::new(RequestTimedOut(DEFAULT_REQUEST_TIMEOUT));
+        assert!(is_retryable_error(&timed_out));
+        let reset: BoxError = Box::new(std::io::Error::from(std::io::ErrorKind::ConnectionReset));
+        assert!(is_retryable_error(&reset));
+        let other: BoxError = "invalid certificate".into();
+        assert!(!is_retryable_error(&other));
+    }
+
+    #[tokio::test]
+    async fn cancelling_a_context_leaves_same_named_contexts_alone() {
+        let staging = track("/tmp/requests-test/staging", "prod", None);
+        let production = track("/tmp/requests-test/production", "prod", None);
+
+        cancel_context_requests("/tmp/requests-test/staging".into(), "prod".into())
+            .await
+            .unwrap();
+        assert!(staging.token().is_cancelled());
+        assert!(!production.token().is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn timed_out_reads_are_sent_again() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let counter = calls.clone();
+        let inner = tower::service_fn(move |_: Request<Body>| {
+            let attempt = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if attempt == 0 {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+                Ok::<_, BoxError>(Response::new(()))
+            }
+        });
+        let mut service = RequestLayer {
+            timeout: Duration::from_millis(50),
+            token: CancellationToken::new(),
+        }
+        .layer(inner);
+
+        let response = service.call(get("/api/v1/namespaces")).await;
+        assert!(response.is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
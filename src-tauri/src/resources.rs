@@ -1,5 +1,7 @@
+use crate::audit::AuditEvent;
 use crate::k8s_client;
 use crate::kubectl::run_kubectl_command;
+use crate::protection::Action;
 use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, StatefulSet};
 use k8s_openapi::api::batch::v1::{CronJob, Job};
 use k8s_openapi::api::core::v1::{
@@ -9,10 +11,16 @@ use k8s_openapi::api::core::v1::{
 use k8s_openapi::api::rbac::v1::{ClusterRole, ClusterRoleBinding, Role, RoleBinding};
 use k8s_openapi::chrono::Utc;
 use kube::{
-    api::{Patch, PatchParams},
-    Api,
+    api::{
+        ApiResource, DeleteParams, DynamicObject, GroupVersionKind, Patch, PatchParams,
+        PropagationPolicy,
+    },
+    core::GroupVersion,
+    discovery::{self, Scope},
+    Api, Client,
 };
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(tag = "kind")]
@@ -37,7 +45,7 @@ pub enum KubeResource {
     Event(Event),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde()]
 pub enum ResourceType {
     Pod,
@@ -106,9 +114,174 @@ impl ResourceType {
             ResourceType::Event => "Event".to_string(),
         }
     }
+
+    pub(crate) fn is_namespaced(&self) -> bool {
+        !matches!(
+            self,
+            ResourceType::Node
+                | ResourceType::ClusterRole
+                | ResourceType::ClusterRoleBinding
+                | ResourceType::PersistentVolume
+        )
+    }
+
+    pub(crate) fn api_resource(&self) -> ApiResource {
+        match self {
+            ResourceType::Pod => ApiResource::erase::<Pod>(&()),
+            ResourceType::Deployment => ApiResource::erase::<Deployment>(&()),
+            ResourceType::StatefulSet => ApiResource::erase::<StatefulSet>(&()),
+            ResourceType::DaemonSet => ApiResource::erase::<DaemonSet>(&()),
+            ResourceType::Job => ApiResource::erase::<Job>(&()),
+            ResourceType::CronJob => ApiResource::erase::<CronJob>(&()),
+            ResourceType::Service => ApiResource::erase::<Service>(&()),
+            ResourceType::Node => ApiResource::erase::<Node>(&()),
+            ResourceType::ConfigMap => ApiResource::erase::<ConfigMap>(&()),
+            ResourceType::Secret => ApiResource::erase::<Secret>(&()),
+            ResourceType::ServiceAccount => ApiResource::erase::<ServiceAccount>(&()),
+            ResourceType::Role => ApiResource::erase::<Role>(&()),
+            ResourceType::RoleBinding => ApiResource::erase::<RoleBinding>(&()),
+            ResourceType::ClusterRole => ApiResource::erase::<ClusterRole>(&()),
+            ResourceType::ClusterRoleBinding => ApiResource::erase::<ClusterRoleBinding>(&()),
+            ResourceType::PersistentVolume => ApiResource::erase::<PersistentVolume>(&()),
+            ResourceType::PersistentVolumeClaim => ApiResource::erase::<PersistentVolumeClaim>(&()),
+            ResourceType::Event => ApiResource::erase::<Event>(&()),
+        }
+    }
+}
+
+// a single object of any kind, either a ResourceType or a kind served by the cluster
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceTarget {
+    #[serde(default)]
+    pub namespace: String,
+    pub kind: String,
+    // only needed for kinds that are not a ResourceType, e.g. "argoproj.io/v1alpha1"
+    pub api_version: Option<String>,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetResult {
+    pub target: ResourceTarget,
+    pub success: bool,
+    pub error: Option<String>,
+    // e.g. a delete that could not be snapshotted first
+    pub warning: Option<String>,
+}
+
+impl TargetResult {
+    pub fn new(target: ResourceTarget, outcome: Result<(), String>) -> Self {
+        TargetResult {
+            target,
+            success: outcome.is_ok(),
+            error: outcome.err(),
+            warning: None,
+        }
+    }
+}
+
+// find the api resource of a kind and whether it is namespaced,
+// using discovery for kinds outside ResourceType
+pub(crate) async fn resolve_api_resource(
+    client: &Client,
+    kind: &str,
+    api_version: Option<&str>,
+) -> Result<(ApiResource, bool), String> {
+    match api_version {
+        None | Some("") => {
+            let resource_type: ResourceType =
+                serde_json::from_value(serde_json::Value::String(kind.to_string()))
+                    .map_err(|_| format!("Unknown resource kind: {}", kind))?;
+            Ok((resource_type.api_resource(), resource_type.is_namespaced()))
+        }
+        Some(api_version) => {
+            let gvk: GroupVersionKind = api_version
+                .parse::<GroupVersion>()
+                .map_err(|e| e.to_string())?
+                .with_kind(kind);
+            let (api_resource, capabilities) = discovery::pinned_kind(client, &gvk)
+                .await
+                .map_err(|e| format!("Failed to discover {}: {}", kind, e))?;
+            Ok((api_resource, capabilities.scope == Scope::Namespaced))
+        }
+    }
+}
+
+// resolve a target to a dynamic api for its kind and namespace
+pub(crate) async fn dynamic_api_for(
+    client: Client,
+    target: &ResourceTarget,
+) -> Result<Api<DynamicObject>, String> {
+    let (api_resource, namespaced) =
+        resolve_api_resource(&client, &target.kind, target.api_version.as_deref()).await?;
+
+    if namespaced && target.namespace.is_empty() {
+        return Err(format!(
+            "{} {} requires a namespace",
+            target.kind, target.name
+        ));
+    }
+    Ok(k8s_client::dynamic_api(
+        client,
+        &api_resource,
+        namespaced,
+        &target.namespace,
+    ))
+}
+
+// merge patch that triggers a rollout by changing the pod template
+pub(crate) fn restart_patch() -> serde_json::Value {
+    serde_json::json!({
+        "spec": {
+            "template": {
+                "metadata": {
+                    "annotations": {
+                        "kube.kubernetes.io/restartedAt": Utc::now().to_rfc3339()
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DeleteOptions {
+    // Foreground, Background or Orphan
+    pub propagation_policy: Option<String>,
+    pub grace_period_seconds: Option<u32>,
+    // delete immediately without waiting for graceful termination, for stuck pods
+    pub force: bool,
+    pub dry_run: bool,
+}
+
+impl DeleteOptions {
+    pub(crate) fn to_delete_params(&self) -> Result<DeleteParams, String> {
+        let propagation_policy = match self.propagation_policy.as_deref() {
+            None | Some("") => None,
+            Some("Foreground") => Some(PropagationPolicy::Foreground),
+            Some("Background") => Some(PropagationPolicy::Background),
+            Some("Orphan") => Some(PropagationPolicy::Orphan),
+            Some(other) => return Err(format!("Invalid propagation policy: {}", other)),
+        };
+        let grace_period_seconds = if self.force {
+            Some(0)
+        } else {
+            self.grace_period_seconds
+        };
+
+        Ok(DeleteParams {
+            dry_run: self.dry_run,
+            grace_period_seconds,
+            propagation_policy,
+            ..Default::default()
+        })
+    }
 }
 
-// delete a resource by name in a namespace
+// delete a resource by name in a namespace, returns warnings, e.g. when it cannot be restored
 #[tauri::command]
 pub async fn delete_resource(
     kubeconfig_path: String,
@@ -116,55 +289,99 @@ pub async fn delete_resource(
     namespace: String,
     resource_type: ResourceType,
     name: String,
-) -> Result<(), String> {
-    let client = k8s_client::create_k8s_client(kubeconfig_path, context).await?;
+    options: Option<DeleteOptions>,
+    confirmation: Option<String>,
+) -> Result<Vec<String>, String> {
+    let audit = AuditEvent::new(
+        &kubeconfig_path,
+        &context,
+        Action::Delete,
+        Some(&namespace),
+        Some(&resource_type.kind()),
+        Some(&name),
+        json!({ "options": options }),
+    );
+    audit
+        .run(confirmation.as_deref(), async move {
+            let dp = options.unwrap_or_default().to_delete_params()?;
+            let client = k8s_client::create_k8s_client(kubeconfig_path, context.clone()).await?;
+            let mut snapshot = None;
+            if !dp.dry_run && !matches!(resource_type, ResourceType::Event) {
+                let api = k8s_client::dynamic_api(
+                    client.clone(),
+                    &resource_type.api_resource(),
+                    resource_type.is_namespaced(),
+                    &namespace,
+                );
+                snapshot = Some(
+                    crate::trash::snapshot(&api, &context, &resource_type.kind(), &name).await?,
+                );
+            }
 
-    match resource_type {
-        ResourceType::Pod => k8s_client::delete_resource::<Pod>(client, &namespace, &name).await,
-        ResourceType::Deployment => {
-            k8s_client::delete_resource::<Deployment>(client, &namespace, &name).await
-        }
-        ResourceType::StatefulSet => {
-            k8s_client::delete_resource::<StatefulSet>(client, &namespace, &name).await
-        }
-        ResourceType::DaemonSet => {
-            k8s_client::delete_resource::<DaemonSet>(client, &namespace, &name).await
-        }
-        ResourceType::Job => k8s_client::delete_resource::<Job>(client, &namespace, &name).await,
-        ResourceType::CronJob => {
-            k8s_client::delete_resource::<CronJob>(client, &namespace, &name).await
-        }
-        ResourceType::Node => k8s_client::delete_cluster_resource::<Node>(client, &name).await,
-        ResourceType::ConfigMap => {
-            k8s_client::delete_resource::<ConfigMap>(client, &namespace, &name).await
-        }
-        ResourceType::Secret => {
-            k8s_client::delete_resource::<Secret>(client, &namespace, &name).await
-        }
-        ResourceType::Service => {
-            k8s_client::delete_resource::<Service>(client, &namespace, &name).await
-        }
-        ResourceType::ServiceAccount => {
-            k8s_client::delete_resource::<ServiceAccount>(client, &namespace, &name).await
-        }
-        ResourceType::Role => k8s_client::delete_resource::<Role>(client, &namespace, &name).await,
-        ResourceType::RoleBinding => {
-            k8s_client::delete_resource::<RoleBinding>(client, &namespace, &name).await
-        }
-        ResourceType::ClusterRole => {
-            k8s_client::delete_cluster_resource::<ClusterRole>(client, &name).await
-        }
-        ResourceType::ClusterRoleBinding => {
-            k8s_client::delete_cluster_resource::<ClusterRoleBinding>(client, &name).await
-        }
-        ResourceType::PersistentVolume => {
-            k8s_client::delete_cluster_resource::<PersistentVolume>(client, &name).await
-        }
-        ResourceType::PersistentVolumeClaim => {
-            k8s_client::delete_resource::<PersistentVolumeClaim>(client, &namespace, &name).await
-        }
-        ResourceType::Event => Err("Event resources cannot be deleted".to_string()),
-    }
+            let deleted = match resource_type {
+                ResourceType::Pod => {
+                    k8s_client::delete_resource::<Pod>(client, &namespace, &name, &dp).await
+                }
+                ResourceType::Deployment => {
+                    k8s_client::delete_resource::<Deployment>(client, &namespace, &name, &dp).await
+                }
+                ResourceType::StatefulSet => {
+                    k8s_client::delete_resource::<StatefulSet>(client, &namespace, &name, &dp).await
+                }
+                ResourceType::DaemonSet => {
+                    k8s_client::delete_resource::<DaemonSet>(client, &namespace, &name, &dp).await
+                }
+                ResourceType::Job => {
+                    k8s_client::delete_resource::<Job>(client, &namespace, &name, &dp).await
+                }
+                ResourceType::CronJob => {
+                    k8s_client::delete_resource::<CronJob>(client, &namespace, &name, &dp).await
+                }
+                ResourceType::Node => {
+                    k8s_client::delete_cluster_resource::<Node>(client, &name, &dp).await
+                }
+                ResourceType::ConfigMap => {
+                    k8s_client::delete_resource::<ConfigMap>(client, &namespace, &name, &dp).await
+                }
+                ResourceType::Secret => {
+                    k8s_client::delete_resource::<Secret>(client, &namespace, &name, &dp).await
+                }
+                ResourceType::Service => {
+                    k8s_client::delete_resource::<Service>(client, &namespace, &name, &dp).await
+                }
+                ResourceType::ServiceAccount => {
+                    k8s_client::delete_resource::<ServiceAccount>(client, &namespace, &name, &dp)
+                        .await
+                }
+                ResourceType::Role => {
+                    k8s_client::delete_resource::<Role>(client, &namespace, &name, &dp).await
+                }
+                ResourceType::RoleBinding => {
+                    k8s_client::delete_resource::<RoleBinding>(client, &namespace, &name, &dp).await
+                }
+                ResourceType::ClusterRole => {
+                    k8s_client::delete_cluster_resource::<ClusterRole>(client, &name, &dp).await
+                }
+                ResourceType::ClusterRoleBinding => {
+                    k8s_client::delete_cluster_resource::<ClusterRoleBinding>(client, &name, &dp)
+                        .await
+                }
+                ResourceType::PersistentVolume => {
+                    k8s_client::delete_cluster_resource::<PersistentVolume>(client, &name, &dp)
+                        .await
+                }
+                ResourceType::PersistentVolumeClaim => {
+                    k8s_client::delete_resource::<PersistentVolumeClaim>(
+                        client, &namespace, &name, &dp,
+                    )
+                    .await
+                }
+                ResourceType::Event => Err("Event resources cannot be deleted".to_string()),
+            };
+            let warnings = snapshot.and_then(|s| s.finish(&deleted));
+            deleted.map(|_| warnings.into_iter().collect())
+        })
+        .await
 }
 
 #[tauri::command]
@@ -182,9 +399,10 @@ pub async fn open_resource_events_in_terminal(
         format!("-n {}", namespace)
     };
 
+    let kubeconfig = crate::k8s_config::kubectl_kubeconfig(&kubeconfig_path, &context)?;
     let cmd_string = format!(
         "--kubeconfig {} --context {} get events {} --field-selector involvedObject.name={},involvedObject.kind={}",
-        kubeconfig_path, context, namespace, name, resource_type.kind()
+        kubeconfig, context, namespace, name, resource_type.kind()
     );
     run_kubectl_command(&cmd_string)?;
     Ok(())
@@ -199,10 +417,11 @@ pub async fn open_resource_logs_in_terminal(
     name: String,
     container_name: Option<String>,
 ) -> Result<(), String> {
+    let kubeconfig = crate::k8s_config::kubectl_kubeconfig(&kubeconfig_path, &context)?;
     // Base command with common parameters
     let mut cmd_string = format!(
         "--kubeconfig {} --context {} logs -n {}",
-        kubeconfig_path, context, namespace,
+        kubeconfig, context, namespace,
     );
 
     // Add resource-specific flags
@@ -241,8 +460,22 @@ pub async fn get_resource(
     namespace: String,
     resource_type: ResourceType,
     name: String,
+    request_id: Option<String>,
 ) -> Result<KubeResource, String> {
-    let client = k8s_client::create_k8s_client(kubeconfig_path, context).await?;
+    // reading a Secret reveals its data, so it goes to the audit log
+    let audit = matches!(resource_type, ResourceType::Secret).then(|| {
+        AuditEvent::new(
+            &kubeconfig_path,
+            &context,
+            Action::RevealSecret,
+            Some(&namespace),
+            Some("Secret"),
+            Some(&name),
+            json!({}),
+        )
+    });
+    let (client, _request) =
+        k8s_client::create_tracked_client(kubeconfig_path, context, request_id).await?;
 
     match resource_type {
         ResourceType::Pod => {
@@ -280,8 +513,11 @@ pub async fn get_resource(
             Ok(KubeResource::ConfigMap(resource))
         }
         ResourceType::Secret => {
-            let resource = k8s_client::get_resource::<Secret>(client, &namespace, &name).await?;
-            Ok(KubeResource::Secret(resource))
+            let resource = k8s_client::get_resource::<Secret>(client, &namespace, &name).await;
+            if let Some(audit) = audit {
+                audit.record(&resource);
+            }
+            Ok(KubeResource::Secret(resource?))
         }
         ResourceType::Service => {
             let resource = k8s_client::get_resource::<Service>(client, &namespace, &name).await?;
@@ -335,12 +571,26 @@ pub async fn list_resource(
     context: String,
     namespace: String,
     resource_type: ResourceType,
+    request_id: Option<String>,
 ) -> Result<Vec<KubeResource>, String> {
-    let client = k8s_client::create_k8s_client(kubeconfig_path, context).await?;
-
     // Check if we need to list resources from all namespaces
     let list_all_namespaces = namespace == "all";
 
+    // listed Secrets carry their data as well, so the listing is audited like a single read
+    let audit = matches!(resource_type, ResourceType::Secret).then(|| {
+        AuditEvent::new(
+            &kubeconfig_path,
+            &context,
+            Action::RevealSecret,
+            (!list_all_namespaces).then_some(namespace.as_str()),
+            Some("Secret"),
+            None,
+            json!({ "list": true }),
+        )
+    });
+    let (client, _request) =
+        k8s_client::create_tracked_client(kubeconfig_path, context, request_id).await?;
+
     match resource_type {
         ResourceType::Pod => {
             let resources =
@@ -395,8 +645,11 @@ pub async fn list_resource(
         }
         ResourceType::Secret => {
             let resources =
-                k8s_client::list_resources::<Secret>(client, &namespace, list_all_namespaces)
-                    .await?;
+                k8s_client::list_resources::<Secret>(client, &namespace, list_all_namespaces).await;
+            if let Some(audit) = audit {
+                audit.record(&resources);
+            }
+            let resources = resources?;
             Ok(resources.into_iter().map(KubeResource::Secret).collect())
         }
         ResourceType::Service => {
@@ -479,6 +732,7 @@ pub async fn list_resource(
 // scale a resource by name in a namespace
 // this will only be allowed for resources that support scaling
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn scale_resource(
     kubeconfig_path: String,
     context: String,
@@ -487,27 +741,42 @@ pub async fn scale_resource(
     name: String,
     current_replicas: i32,
     replicas: i32,
+    confirmation: Option<String>,
 ) -> Result<(), String> {
-    match resource_type {
-        ResourceType::Deployment | ResourceType::StatefulSet => {
-            let cmd_string = format!(
-                "--kubeconfig {} --context {} scale {} {} -n {} --current-replicas={} --replicas={}",
-                kubeconfig_path,
-                context,
-                resource_type.as_str(),
-                name,
-                namespace,
-                current_replicas,
-                replicas,
-            );
-            run_kubectl_command(&cmd_string)?;
-            Ok(())
-        }
-        _ => Err(format!(
-            "Resource type {:?} cannot be scaled",
-            resource_type
-        )),
-    }
+    let audit = AuditEvent::new(
+        &kubeconfig_path,
+        &context,
+        Action::Scale,
+        Some(&namespace),
+        Some(&resource_type.kind()),
+        Some(&name),
+        json!({ "currentReplicas": current_replicas, "replicas": replicas }),
+    );
+    audit
+        .run(confirmation.as_deref(), async move {
+            match resource_type {
+                ResourceType::Deployment | ResourceType::StatefulSet => {
+                    let kubeconfig = crate::k8s_config::kubectl_kubeconfig(&kubeconfig_path, &context)?;
+                    let cmd_string = format!(
+                        "--kubeconfig {} --context {} scale {} {} -n {} --current-replicas={} --replicas={}",
+                        kubeconfig,
+                        context,
+                        resource_type.as_str(),
+                        name,
+                        namespace,
+                        current_replicas,
+                        replicas,
+                    );
+                    run_kubectl_command(&cmd_string)?;
+                    Ok(())
+                }
+                _ => Err(format!(
+                    "Resource type {:?} cannot be scaled",
+                    resource_type
+                )),
+            }
+        })
+        .await
 }
 
 // restart resource by name in a namespace
@@ -518,49 +787,53 @@ pub async fn restart_resource(
     namespace: String,
     resource_type: ResourceType,
     name: String,
+    confirmation: Option<String>,
 ) -> Result<(), String> {
-    let client = k8s_client::create_k8s_client(kubeconfig_path, context).await?;
+    let audit = AuditEvent::new(
+        &kubeconfig_path,
+        &context,
+        Action::Restart,
+        Some(&namespace),
+        Some(&resource_type.kind()),
+        Some(&name),
+        json!({}),
+    );
+    audit
+        .run(confirmation.as_deref(), async move {
+            let client = k8s_client::create_k8s_client(kubeconfig_path, context).await?;
 
-    let patch_payload = serde_json::json!({
-        "spec": {
-            "template": {
-                "metadata": {
-                    "annotations": {
-                        "kube.kubernetes.io/restartedAt": Utc::now().to_rfc3339()
-                    }
+            let patch_payload = restart_patch();
+            let patch_params = PatchParams::default();
+
+            match resource_type {
+                ResourceType::Deployment => {
+                    let api: Api<Deployment> = Api::namespaced(client, &namespace);
+                    api.patch(&name, &patch_params, &Patch::Merge(&patch_payload))
+                        .await
+                        .map_err(|e| format!("Failed to restart Deployment: {}", e))?;
+                    Ok(())
                 }
+                ResourceType::StatefulSet => {
+                    let api: Api<StatefulSet> = Api::namespaced(client, &namespace);
+                    api.patch(&name, &patch_params, &Patch::Merge(&patch_payload))
+                        .await
+                        .map_err(|e| format!("Failed to restart StatefulSet: {}", e))?;
+                    Ok(())
+                }
+                ResourceType::DaemonSet => {
+                    let api: Api<DaemonSet> = Api::namespaced(client, &namespace);
+                    api.patch(&name, &patch_params, &Patch::Merge(&patch_payload))
+                        .await
+                        .map_err(|e| format!("Failed to restart DaemonSet: {}", e))?;
+                    Ok(())
+                }
+                _ => Err(format!(
+                    "Resource type {:?} cannot be restarted",
+                    resource_type
+                )),
             }
-        }
-    });
-    let patch_params = PatchParams::default();
-
-    match resource_type {
-        ResourceType::Deployment => {
-            let api: Api<Deployment> = Api::namespaced(client, &namespace);
-            api.patch(&name, &patch_params, &Patch::Merge(&patch_payload))
-                .await
-                .map_err(|e| format!("Failed to restart Deployment: {}", e))?;
-            Ok(())
-        }
-        ResourceType::StatefulSet => {
-            let api: Api<StatefulSet> = Api::namespaced(client, &namespace);
-            api.patch(&name, &patch_params, &Patch::Merge(&patch_payload))
-                .await
-                .map_err(|e| format!("Failed to restart StatefulSet: {}", e))?;
-            Ok(())
-        }
-        ResourceType::DaemonSet => {
-            let api: Api<DaemonSet> = Api::namespaced(client, &namespace);
-            api.patch(&name, &patch_params, &Patch::Merge(&patch_payload))
-                .await
-                .map_err(|e| format!("Failed to restart DaemonSet: {}", e))?;
-            Ok(())
-        }
-        _ => Err(format!(
-            "Resource type {:?} cannot be restarted",
-            resource_type
-        )),
-    }
+        })
+        .await
 }
 
 // list events of a resource by name
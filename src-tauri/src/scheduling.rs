@@ -0,0 +1,579 @@
+use crate::k8s_client;
+use crate::nodes::toleration_matches;
+use crate::overview::{event_time, node_is_ready};
+use crate::quantity::quantity_value;
+use k8s_openapi::api::core::v1::{
+    Event, Node, NodeSelector, NodeSelectorRequirement, PersistentVolume, PersistentVolumeClaim,
+    Pod, Taint,
+};
+use k8s_openapi::api::storage::v1::StorageClass;
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use kube::api::ListParams;
+use kube::Api;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SchedulingEvent {
+    pub message: String,
+    pub count: Option<i32>,
+    // RFC 3339
+    pub last_seen: Option<String>,
+}
+
+// requested, allocatable and free amount of a resource on a node
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceFit {
+    pub resource: String,
+    pub requested: f64,
+    pub allocatable: f64,
+    // allocatable minus the requests of the pods already running on the node
+    pub free: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeFit {
+    pub node: String,
+    pub fits: bool,
+    pub reasons: Vec<String>,
+    pub resources: Vec<ResourceFit>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingPodExplanation {
+    pub phase: Option<String>,
+    pub node_name: Option<String>,
+    // cpu in cores, memory in bytes, other resources in their own units
+    pub requests: BTreeMap<String, f64>,
+    pub scheduler_events: Vec<SchedulingEvent>,
+    // reasons that apply to every node, e.g. an unbound volume claim
+    pub pod_reasons: Vec<String>,
+    pub nodes: Vec<NodeFit>,
+    // constraints that were not evaluated
+    pub notes: Vec<String>,
+}
+
+fn add_resources(
+    total: &mut BTreeMap<String, f64>,
+    resources: Option<&BTreeMap<String, Quantity>>,
+) {
+    for (name, quantity) in resources.into_iter().flatten() {
+        *total.entry(name.clone()).or_default() += quantity_value(quantity).unwrap_or(0.0);
+    }
+}
+
+// effective requests of a pod like the scheduler computes them: the larger of the summed
+// app containers and any single init container, plus the pod overhead
+pub(crate) fn pod_requests(pod: &Pod) -> BTreeMap<String, f64> {
+    let mut requests = BTreeMap::new();
+    let Some(spec) = pod.spec.as_ref() else {
+        return requests;
+    };
+    for container in &spec.containers {
+        add_resources(
+            &mut requests,
+            container
+                .resources
+                .as_ref()
+                .and_then(|r| r.requests.as_ref()),
+        );
+    }
+    for container in spec.init_containers.iter().flatten() {
+        let mut init = BTreeMap::new();
+        add_resources(
+            &mut init,
+            container
+                .resources
+                .as_ref()
+                .and_then(|r| r.requests.as_ref()),
+        );
+        for (name, value) in init {
+            let total = requests.entry(name).or_default();
+            *total = total.max(value);
+        }
+    }
+    add_resources(&mut requests, spec.overhead.as_ref());
+    requests
+}
+
+fn requirement_matches(requirement: &NodeSelectorRequirement, value: Option<&str>) -> bool {
+    let values = requirement.values.as_deref().unwrap_or_default();
+    let as_number = |v: &str| v.parse::<i64>().ok();
+    match requirement.operator.as_str() {
+        "In" => value.is_some_and(|v| values.iter().any(|x| x == v)),
+        "NotIn" => !value.is_some_and(|v| values.iter().any(|x| x == v)),
+        "Exists" => value.is_some(),
+        "DoesNotExist" => value.is_none(),
+        "Gt" => match (
+            value.and_then(as_number),
+            values.first().and_then(|v| as_number(v)),
+        ) {
+            (Some(v), Some(bound)) => v > bound,
+            _ => false,
+        },
+        "Lt" => match (
+            value.and_then(as_number),
+            values.first().and_then(|v| as_number(v)),
+        ) {
+            (Some(v), Some(bound)) => v < bound,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+// terms are ORed, the expressions and fields within a term are ANDed
+pub(crate) fn node_selector_matches(selector: &NodeSelector, node: &Node) -> bool {
+    let labels = node.metadata.labels.clone().unwrap_or_default();
+    let name = node.metadata.name.as_deref();
+    selector.node_selector_terms.iter().any(|term| {
+        let expressions = term.match_expressions.iter().flatten();
+        let fields = term.match_fields.iter().flatten();
+        let has_requirements =
+            expressions.clone().next().is_some() || fields.clone().next().is_some();
+        has_requirements
+            && expressions
+                .clone()
+                .all(|r| requirement_matches(r, labels.get(&r.key).map(|v| v.as_str())))
+            && fields.clone().all(|r| {
+                let value = if r.key == "metadata.name" { name } else { None };
+                requirement_matches(r, value)
+            })
+    })
+}
+
+fn untolerated_taints<'a>(pod: &Pod, taints: &'a [Taint]) -> Vec<&'a Taint> {
+    let tolerations = pod
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.tolerations.as_deref())
+        .unwrap_or_default();
+    taints
+        .iter()
+        // PreferNoSchedule only lowers the score, it never blocks scheduling
+        .filter(|taint| taint.effect == "NoSchedule" || taint.effect == "NoExecute")
+        .filter(|taint| !tolerations.iter().any(|t| toleration_matches(t, taint)))
+        .collect()
+}
+
+fn format_taint(taint: &Taint) -> String {
+    match taint.value.as_deref() {
+        Some(value) if !value.is_empty() => format!("{}={}:{}", taint.key, value, taint.effect),
+        _ => format!("{}:{}", taint.key, taint.effect),
+    }
+}
+
+fn node_fit(
+    pod: &Pod,
+    node: &Node,
+    requests: &BTreeMap<String, f64>,
+    used: Option<&BTreeMap<String, f64>>,
+    pod_count: usize,
+    volume_selectors: &[(String, NodeSelector)],
+) -> NodeFit {
+    let mut reasons = vec![];
+    let spec = pod.spec.as_ref();
+    let labels = node.metadata.labels.clone().unwrap_or_default();
+
+    if !node_is_ready(node) {
+        reasons.push("Node is not ready".to_string());
+    }
+
+    // an unschedulable node carries the node.kubernetes.io/unschedulable taint, report it once
+    let mut taints = node
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.taints.clone())
+        .unwrap_or_default();
+    let unschedulable = node
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.unschedulable)
+        .unwrap_or(false);
+    if unschedulable
+        && !taints
+            .iter()
+            .any(|t| t.key == "node.kubernetes.io/unschedulable")
+    {
+        taints.push(Taint {
+            key: "node.kubernetes.io/unschedulable".to_string(),
+            effect: "NoSchedule".to_string(),
+            ..Default::default()
+        });
+    }
+    for taint in untolerated_taints(pod, &taints) {
+        if taint.key == "node.kubernetes.io/unschedulable" {
+            reasons.push("Node is cordoned (unschedulable)".to_string());
+        } else {
+            reasons.push(format!("Untolerated taint {}", format_taint(taint)));
+        }
+    }
+
+    for (key, value) in spec
+        .and_then(|spec| spec.node_selector.as_ref())
+        .into_iter()
+        .flatten()
+    {
+        if labels.get(key) != Some(value) {
+            reasons.push(format!("nodeSelector {}={} does not match", key, value));
+        }
+    }
+
+    let required_affinity = spec
+        .and_then(|spec| spec.affinity.as_ref())
+        .and_then(|affinity| affinity.node_affinity.as_ref())
+        .and_then(|affinity| {
+            affinity
+                .required_during_scheduling_ignored_during_execution
+                .as_ref()
+        });
+    if let Some(selector) = required_affinity {
+        if !node_selector_matches(selector, node) {
+            reasons.push("Required node affinity does not match".to_string());
+        }
+    }
+
+    for (volume, selector) in volume_selectors {
+        if !node_selector_matches(selector, node) {
+            reasons.push(format!(
+                "Volume {} is bound to a PersistentVolume that is not reachable from this node",
+                volume
+            ));
+        }
+    }
+
+    let allocatable = node
+        .status
+        .as_ref()
+        .and_then(|status| status.allocatable.as_ref());
+    let mut resources = vec![];
+    for (resource, requested) in requests {
+        let allocatable = allocatable
+            .and_then(|a| a.get(resource))
+            .and_then(quantity_value)
+            .unwrap_or(0.0);
+        let in_use = used.and_then(|u| u.get(resource)).copied().unwrap_or(0.0);
+        let free = allocatable - in_use;
+        if *requested > free {
+            reasons.push(format!("Insufficient {}", resource));
+        }
+        resources.push(ResourceFit {
+            resource: resource.clone(),
+            requested: *requested,
+            allocatable,
+            free,
+        });
+    }
+    let max_pods = allocatable
+        .and_then(|a| a.get("pods"))
+        .and_then(quantity_value)
+        .unwrap_or(f64::MAX);
+    if pod_count as f64 >= max_pods {
+        reasons.push("Too many pods".to_string());
+    }
+
+    NodeFit {
+        node: node.metadata.name.clone().unwrap_or_default(),
+        fits: reasons.is_empty(),
+        reasons,
+        resources,
+    }
+}
+
+// explain why a pod cannot be scheduled: the scheduler's own events and, per node,
+// which resources, selectors, affinity, taints or volume topology rule it out
+#[tauri::command]
+pub async fn explain_pending_pod(
+    kubeconfig_path: String,
+    context: String,
+    namespace: String,
+    pod_name: String,
+    request_id: Option<String>,
+) -> Result<PendingPodExplanation, String> {
+    let (client, _request) =
+        k8s_client::create_tracked_client(kubeconfig_path, context, request_id).await?;
+    let pod = k8s_client::get_resource::<Pod>(client.clone(), &namespace, &pod_name).await?;
+
+    let events: Api<Event> = Api::namespaced(client.clone(), &namespace);
+    let all_pods: Api<Pod> = Api::all(client.clone());
+    let lp = ListParams::default().fields(&format!(
+        "involvedObject.name={},involvedObject.kind=Pod,reason=FailedScheduling",
+        pod_name
+    ));
+    // only running pods hold on to their requests
+    let active = ListParams::default().fields("status.phase!=Succeeded,status.phase!=Failed");
+    let (events, nodes, pods) = futures::join!(
+        events.list(&lp),
+        k8s_client::list_cluster_resources::<Node>(client.clone()),
+        all_pods.list(&active)
+    );
+    let mut events = events.map_err(|e| e.to_string())?.items;
+    let nodes = nodes?;
+    let pods = pods.map_err(|e| e.to_string())?.items;
+
+    events.sort_by_key(|event| std::cmp::Reverse(event_time(event)));
+    let scheduler_events = events
+        .iter()
+        .map(|event| SchedulingEvent {
+            message: event.message.clone().unwrap_or_default(),
+            count: event.count,
+            last_seen: event_time(event).map(|t| t.to_rfc3339()),
+        })
+        .collect();
+
+    let mut used: HashMap<String, BTreeMap<String, f64>> = HashMap::new();
+    let mut pod_counts: HashMap<String, usize> = HashMap::new();
+    // a pod that already has a node counts there once, with the requests evaluated below
+    for other in pods
+        .iter()
+        .filter(|other| other.metadata.uid != pod.metadata.uid)
+    {
+        let Some(node_name) = other.spec.as_ref().and_then(|s| s.node_name.clone()) else {
+            continue;
+        };
+        *pod_counts.entry(node_name.clone()).or_default() += 1;
+        let node_used = used.entry(node_name).or_default();
+        for (resource, value) in pod_requests(other) {
+            *node_used.entry(resource).or_default() += value;
+        }
+    }
+
+    // volume claims either block the pod everywhere or restrict it to the nodes of their volume
+    let mut pod_reasons = vec![];
+    let mut volume_selectors = vec![];
+    let claims = pod
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.volumes.as_ref())
+        .into_iter()
+        .flatten()
+        .filter_map(|volume| volume.persistent_volume_claim.as_ref());
+    for claim in claims {
+        let pvc = match k8s_client::get_resource::<PersistentVolumeClaim>(
+            client.clone(),
+            &namespace,
+            &claim.claim_name,
+        )
+        .await
+        {
+            Ok(pvc) => pvc,
+            Err(e) => {
+                pod_reasons.push(format!(
+                    "PersistentVolumeClaim {} cannot be read: {}",
+                    claim.claim_name, e
+                ));
+                continue;
+            }
+        };
+
+        let volume_name = pvc.spec.as_ref().and_then(|spec| spec.volume_name.clone());
+        match volume_name.filter(|name| !name.is_empty()) {
+            Some(volume_name) => {
+                let pv = match k8s_client::get_cluster_resource::<PersistentVolume>(
+                    client.clone(),
+                    &volume_name,
+                )
+                .await
+                {
+                    Ok(pv) => pv,
+                    Err(e) => {
+                        pod_reasons.push(format!(
+                            "PersistentVolume {} of claim {} cannot be read: {}",
+                            volume_name, claim.claim_name, e
+                        ));
+                        continue;
+                    }
+                };
+                if let Some(selector) = pv
+                    .spec
+                    .and_then(|spec| spec.node_affinity)
+                    .and_then(|affinity| affinity.required)
+                {
+                    volume_selectors.push((claim.claim_name.clone(), selector));
+                }
+            }
+            None => {
+                // claims of a WaitForFirstConsumer class are bound once the pod is scheduled
+                let class_name = pvc
+                    .spec
+                    .as_ref()
+                    .and_then(|spec| spec.storage_class_name.clone());
+                let waits_for_consumer = match class_name {
+                    Some(class_name) => k8s_client::get_cluster_resource::<StorageClass>(
+                        client.clone(),
+                        &class_name,
+                    )
+                    .await
+                    .ok()
+                    .and_then(|class| class.volume_binding_mode)
+                    .is_some_and(|mode| mode == "WaitForFirstConsumer"),
+                    None => false,
+                };
+                if !waits_for_consumer {
+                    pod_reasons.push(format!(
+                        "PersistentVolumeClaim {} is not bound",
+                        claim.claim_name
+                    ));
+                }
+            }
+        }
+    }
+
+    let requests = pod_requests(&pod);
+    let mut node_fits: Vec<NodeFit> = nodes
+        .iter()
+        .map(|node| {
+            let name = node.metadata.name.clone().unwrap_or_default();
+            node_fit(
+                &pod,
+                node,
+                &requests,
+                used.get(&name),
+                pod_counts.get(&name).copied().unwrap_or(0),
+                &volume_selectors,
+            )
+        })
+        .collect();
+    node_fits.sort_by(|a, b| {
+        b.fits
+            .cmp(&a.fits)
+            .then(a.reasons.len().cmp(&b.reasons.len()))
+            .then(a.node.cmp(&b.node))
+    });
+
+    let mut notes = vec![];
+    let affinity = pod.spec.as_ref().and_then(|spec| spec.affinity.as_ref());
+    if affinity.is_some_and(|a| a.pod_affinity.is_some() || a.pod_anti_affinity.is_some()) {
+        notes.push("Inter-pod affinity and anti-affinity rules were not evaluated".to_string());
+    }
+    if pod
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.topology_spread_constraints.as_ref())
+        .is_some_and(|constraints| !constraints.is_empty())
+    {
+        notes.push("Topology spread constraints were not evaluated".to_string());
+    }
+
+    let status = pod.status.as_ref();
+    Ok(PendingPodExplanation {
+        phase: status.and_then(|s| s.phase.clone()),
+        node_name: pod.spec.as_ref().and_then(|s| s.node_name.clone()),
+        requests,
+        scheduler_events,
+        pod_reasons,
+        nodes: node_fits,
+        notes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn requirement(operator: &str, values: &[&str]) -> NodeSelectorRequirement {
+        NodeSelectorRequirement {
+            key: "zone".to_string(),
+            operator: operator.to_string(),
+            values: Some(values.iter().map(|v| v.to_string()).collect()),
+        }
+    }
+
+    #[test]
+    fn requirements_match_like_the_scheduler() {
+        let in_a = requirement("In", &["a", "b"]);
+        assert!(requirement_matches(&in_a, Some("a")));
+        assert!(!requirement_matches(&in_a, Some("c")));
+        assert!(!requirement_matches(&in_a, None));
+
+        let not_in = requirement("NotIn", &["a"]);
+        assert!(!requirement_matches(&not_in, Some("a")));
+        assert!(requirement_matches(&not_in, Some("c")));
+        assert!(requirement_matches(&not_in, None));
+
+        assert!(requirement_matches(&requirement("Exists", &[]), Some("")));
+        assert!(!requirement_matches(&requirement("Exists", &[]), None));
+        assert!(requirement_matches(&requirement("DoesNotExist", &[]), None));
+        assert!(!requirement_matches(
+            &requirement("DoesNotExist", &[]),
+            Some("a")
+        ));
+
+        assert!(requirement_matches(&requirement("Gt", &["4"]), Some("8")));
+        assert!(!requirement_matches(&requirement("Gt", &["4"]), Some("4")));
+        assert!(requirement_matches(&requirement("Lt", &["4"]), Some("2")));
+        assert!(!requirement_matches(&requirement("Lt", &["4"]), Some("8")));
+        // non-numeric values never match a numeric comparison
+        assert!(!requirement_matches(
+            &requirement("Gt", &["4"]),
+            Some("large")
+        ));
+        assert!(!requirement_matches(
+            &requirement("Unknown", &["a"]),
+            Some("a")
+        ));
+    }
+
+    #[test]
+    fn node_selector_terms_are_ored_and_requirements_anded() {
+        let node: Node = serde_json::from_value(json!({
+            "metadata": { "name": "worker-1", "labels": { "zone": "a", "disk": "ssd" } }
+        }))
+        .unwrap();
+        let selector = |terms: serde_json::Value| -> NodeSelector {
+            serde_json::from_value(json!({ "nodeSelectorTerms": terms })).unwrap()
+        };
+
+        assert!(node_selector_matches(
+            &selector(json!([
+                { "matchExpressions": [{ "key": "zone", "operator": "In", "values": ["b"] }] },
+                { "matchExpressions": [
+                    { "key": "zone", "operator": "In", "values": ["a"] },
+                    { "key": "disk", "operator": "Exists" }
+                ] }
+            ])),
+            &node
+        ));
+        assert!(!node_selector_matches(
+            &selector(json!([{ "matchExpressions": [
+                { "key": "zone", "operator": "In", "values": ["a"] },
+                { "key": "gpu", "operator": "Exists" }
+            ] }])),
+            &node
+        ));
+        assert!(node_selector_matches(
+            &selector(json!([{ "matchFields": [
+                { "key": "metadata.name", "operator": "In", "values": ["worker-1"] }
+            ] }])),
+            &node
+        ));
+        // an empty term matches no node
+        assert!(!node_selector_matches(&selector(json!([{}])), &node));
+    }
+
+    #[test]
+    fn requests_take_the_larger_of_app_and_init_containers_plus_overhead() {
+        let pod: Pod = serde_json::from_value(json!({
+            "spec": {
+                "containers": [
+                    { "name": "app", "resources": { "requests": { "cpu": "250m", "memory": "256Mi" } } },
+                    { "name": "proxy", "resources": { "requests": { "cpu": "250m", "memory": "64Mi" } } }
+                ],
+                "initContainers": [
+                    { "name": "migrate", "resources": { "requests": { "cpu": "1", "memory": "128Mi" } } }
+                ],
+                "overhead": { "cpu": "100m" }
+            }
+        }))
+        .unwrap();
+
+        let requests = pod_requests(&pod);
+        assert!((requests["cpu"] - 1.1).abs() < 1e-9);
+        assert_eq!(requests["memory"], (320 * 1024 * 1024) as f64);
+    }
+}
@@ -0,0 +1,225 @@
+use futures::SinkExt;
+use kube::api::{AttachedProcess, TerminalSize};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+const SESSION_OUTPUT_EVENT: &str = "session-output";
+const SESSION_CLOSED_EVENT: &str = "session-closed";
+
+// work to run once a session is over, e.g. deleting a debug pod
+pub type SessionCleanup = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+enum SessionInput {
+    Data(Vec<u8>),
+    Resize(TerminalSize),
+    Close,
+}
+
+struct Session {
+    input: mpsc::UnboundedSender<SessionInput>,
+    cleanup: Option<SessionCleanup>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionOutput {
+    pub session_id: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionClosed {
+    pub session_id: String,
+    pub error: Option<String>,
+}
+
+// interactive exec and attach sessions, keyed by an id chosen by the frontend
+#[derive(Default)]
+pub struct SessionManager {
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl SessionManager {
+    pub fn contains(&self, session_id: &str) -> bool {
+        self.sessions
+            .lock()
+            .is_ok_and(|sessions| sessions.contains_key(session_id))
+    }
+
+    // start pumping a process' streams to the frontend; the cleanup also runs when the
+    // session cannot start
+    pub fn start(
+        &self,
+        app: AppHandle,
+        session_id: String,
+        process: AttachedProcess,
+        cleanup: Option<SessionCleanup>,
+    ) -> Result<(), String> {
+        let (input, input_rx) = mpsc::unbounded_channel();
+        {
+            let mut sessions = self.sessions.lock().map_err(|e| e.to_string())?;
+            if sessions.contains_key(&session_id) {
+                if let Some(cleanup) = cleanup {
+                    tauri::async_runtime::spawn(cleanup);
+                }
+                return Err(format!("Session {} already exists", session_id));
+            }
+            sessions.insert(session_id.clone(), Session { input, cleanup });
+        }
+
+        tauri::async_runtime::spawn(async move {
+            let error = pump(&app, &session_id, process, input_rx).await.err();
+            let _ = app.emit(
+                SESSION_CLOSED_EVENT,
+                SessionClosed {
+                    session_id: session_id.clone(),
+                    error,
+                },
+            );
+            let cleanup = app.state::<SessionManager>().remove(&session_id);
+            if let Some(cleanup) = cleanup {
+                cleanup.await;
+            }
+        });
+        Ok(())
+    }
+
+    fn send(&self, session_id: &str, input: SessionInput) -> Result<(), String> {
+        let sessions = self.sessions.lock().map_err(|e| e.to_string())?;
+        let session = sessions
+            .get(session_id)
+            .ok_or_else(|| format!("Session {} not found", session_id))?;
+        session
+            .input
+            .send(input)
+            .map_err(|_| format!("Session {} is closed", session_id))
+    }
+
+    // forget a session, returning its cleanup so it runs exactly once
+    fn remove(&self, session_id: &str) -> Option<SessionCleanup> {
+        self.sessions
+            .lock()
+            .ok()
+            .and_then(|mut sessions| sessions.remove(session_id))
+            .and_then(|session| session.cleanup)
+    }
+
+    // close every session and wait for their cleanups, used when the app quits
+    pub async fn shutdown(&self) {
+        let sessions: Vec<Session> = match self.sessions.lock() {
+            Ok(mut sessions) => sessions.drain().map(|(_, session)| session).collect(),
+            Err(_) => return,
+        };
+        for session in sessions {
+            let _ = session.input.send(SessionInput::Close);
+            if let Some(cleanup) = session.cleanup {
+                cleanup.await;
+            }
+        }
+    }
+}
+
+// read from an optional stream, never resolving when the stream is absent
+async fn read_chunk<R: AsyncRead + Unpin>(
+    reader: &mut Option<R>,
+    buf: &mut [u8],
+) -> std::io::Result<usize> {
+    match reader.as_mut() {
+        Some(reader) => reader.read(buf).await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn pump(
+    app: &AppHandle,
+    session_id: &str,
+    mut process: AttachedProcess,
+    mut input_rx: mpsc::UnboundedReceiver<SessionInput>,
+) -> Result<(), String> {
+    let mut stdin = process.stdin();
+    let mut stdout = process.stdout();
+    let mut stderr = process.stderr();
+    let mut terminal_size = process.terminal_size();
+    let mut out_buf = vec![0u8; 8192];
+    let mut err_buf = vec![0u8; 8192];
+
+    let emit = |data: &[u8]| {
+        let _ = app.emit(
+            SESSION_OUTPUT_EVENT,
+            SessionOutput {
+                session_id: session_id.to_string(),
+                data: data.to_vec(),
+            },
+        );
+    };
+
+    loop {
+        tokio::select! {
+            input = input_rx.recv() => match input {
+                Some(SessionInput::Data(data)) => {
+                    if let Some(stdin) = stdin.as_mut() {
+                        stdin.write_all(&data).await.map_err(|e| e.to_string())?;
+                    }
+                }
+                Some(SessionInput::Resize(size)) => {
+                    if let Some(terminal_size) = terminal_size.as_mut() {
+                        let _ = terminal_size.send(size).await;
+                    }
+                }
+                Some(SessionInput::Close) | None => break,
+            },
+            read = read_chunk(&mut stdout, &mut out_buf) => match read {
+                Ok(0) => break,
+                Ok(n) => emit(&out_buf[..n]),
+                Err(e) => return Err(e.to_string()),
+            },
+            read = read_chunk(&mut stderr, &mut err_buf) => match read {
+                Ok(0) | Err(_) => stderr = None,
+                Ok(n) => emit(&err_buf[..n]),
+            },
+        }
+    }
+
+    process.abort();
+    Ok(())
+}
+
+// send keystrokes to a session
+#[tauri::command]
+pub async fn write_session_input(
+    sessions: State<'_, SessionManager>,
+    session_id: String,
+    data: String,
+) -> Result<(), String> {
+    sessions.send(&session_id, SessionInput::Data(data.into_bytes()))
+}
+
+// resize the terminal of a session
+#[tauri::command]
+pub async fn resize_session(
+    sessions: State<'_, SessionManager>,
+    session_id: String,
+    width: u16,
+    height: u16,
+) -> Result<(), String> {
+    sessions.send(
+        &session_id,
+        SessionInput::Resize(TerminalSize { width, height }),
+    )
+}
+
+// end a session, the session-closed event is emitted once it is gone
+#[tauri::command]
+pub async fn close_session(
+    sessions: State<'_, SessionManager>,
+    session_id: String,
+) -> Result<(), String> {
+    sessions.send(&session_id, SessionInput::Close)
+}
@@ -0,0 +1,624 @@
+use crate::app_config;
+use crate::audit::AuditEvent;
+use crate::k8s_client;
+use crate::protection::Action;
+use crate::resources::resolve_api_resource;
+use k8s_openapi::chrono::{DateTime, Utc};
+use kube::api::{DynamicObject, PostParams};
+use kube::{Api, Client};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+const TRASH_DIR: &str = "trash";
+// older snapshots are dropped once there are more than this
+const MAX_TRASH_ENTRIES: usize = 200;
+const DEFAULT_LIST_LIMIT: usize = 50;
+
+// metadata the API server sets, a new object gets its own
+const SERVER_METADATA: [&str; 8] = [
+    "uid",
+    "resourceVersion",
+    "managedFields",
+    "creationTimestamp",
+    "generation",
+    "selfLink",
+    "deletionTimestamp",
+    "deletionGracePeriodSeconds",
+];
+
+// a cleaned manifest of a deleted object, saved just before it was deleted
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashEntry {
+    pub id: String,
+    pub deleted_at: DateTime<Utc>,
+    pub context: String,
+    pub namespace: Option<String>,
+    pub kind: String,
+    pub api_version: String,
+    pub name: String,
+    pub restored_at: Option<DateTime<Utc>>,
+    pub manifest: Value,
+}
+
+// a trash entry without the manifest, which may hold Secret data
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletedResource {
+    pub id: String,
+    pub deleted_at: DateTime<Utc>,
+    pub context: String,
+    pub namespace: Option<String>,
+    pub kind: String,
+    pub api_version: String,
+    pub name: String,
+    pub restored_at: Option<DateTime<Utc>>,
+    pub owned: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreResult {
+    pub namespace: Option<String>,
+    pub kind: String,
+    pub name: String,
+    // what differs from the deleted object
+    pub warnings: Vec<String>,
+}
+
+impl From<TrashEntry> for DeletedResource {
+    fn from(entry: TrashEntry) -> Self {
+        DeletedResource {
+            owned: owner_references(&entry.manifest).next().is_some(),
+            id: entry.id,
+            deleted_at: entry.deleted_at,
+            context: entry.context,
+            namespace: entry.namespace,
+            kind: entry.kind,
+            api_version: entry.api_version,
+            name: entry.name,
+            restored_at: entry.restored_at,
+        }
+    }
+}
+
+fn trash_dir() -> Result<PathBuf, String> {
+    Ok(app_config::config_dir()?.join(TRASH_DIR))
+}
+
+fn owner_references(manifest: &Value) -> impl Iterator<Item = &Value> {
+    manifest["metadata"]["ownerReferences"]
+        .as_array()
+        .into_iter()
+        .flatten()
+}
+
+// the object without status and server-set metadata
+fn clean_manifest(object: &DynamicObject) -> Result<Value, String> {
+    let mut manifest = serde_json::to_value(object).map_err(|e| e.to_string())?;
+    if let Some(manifest) = manifest.as_object_mut() {
+        manifest.remove("status");
+    }
+    if let Some(metadata) = manifest["metadata"].as_object_mut() {
+        for field in SERVER_METADATA {
+            metadata.remove(field);
+        }
+        if let Some(annotations) = metadata
+            .get_mut("annotations")
+            .and_then(|a| a.as_object_mut())
+        {
+            annotations.remove("kubectl.kubernetes.io/last-applied-configuration");
+        }
+    }
+    Ok(manifest)
+}
+
+// snapshots of Secrets hold their data, keep them private
+fn write_entry(path: &Path, entry: &TrashEntry) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(entry).map_err(|e| e.to_string())?;
+    app_config::write_private_file(path, content.as_bytes())
+}
+
+// entry files sorted oldest first, their names start with the deletion time
+fn entry_paths() -> Result<Vec<PathBuf>, String> {
+    let dir = trash_dir()?;
+    let mut paths: Vec<PathBuf> = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+        Err(e) => return Err(format!("Failed to read {}: {}", dir.display(), e)),
+    };
+    paths.sort();
+    Ok(paths)
+}
+
+fn read_entry(path: &Path) -> Result<TrashEntry, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+// a trash entry saved ahead of a delete, kept only when the delete goes through
+#[derive(Default)]
+pub(crate) struct Snapshot {
+    path: Option<PathBuf>,
+    warning: Option<String>,
+}
+
+impl Snapshot {
+    // drop the entry of a failed delete, there is nothing to undo; returns the warning otherwise
+    pub(crate) fn finish<T, E>(self, deleted: &Result<T, E>) -> Option<String> {
+        if deleted.is_err() {
+            if let Some(path) = self.path {
+                let _ = std::fs::remove_file(path);
+            }
+            return None;
+        }
+        self.warning
+    }
+}
+
+// save the object before it is deleted, so the deletion can be undone;
+// a missing object has nothing to save and the delete reports it. An identity that may
+// delete but not read the object still deletes it, the snapshot warns it is not undoable
+pub(crate) async fn snapshot(
+    api: &Api<DynamicObject>,
+    context: &str,
+    kind: &str,
+    name: &str,
+) -> Result<Snapshot, String> {
+    let object = match api.get_opt(name).await {
+        Ok(Some(object)) => object,
+        Ok(None) => return Ok(Snapshot::default()),
+        Err(kube::Error::Api(response)) if response.code == 403 => {
+            return Ok(Snapshot {
+                path: None,
+                warning: Some(format!(
+                    "{} {} could not be read to save a snapshot, the deletion cannot be undone",
+                    kind, name
+                )),
+            })
+        }
+        Err(e) => {
+            return Err(format!(
+                "Failed to save {} {} before deleting it: {}",
+                kind, name, e
+            ))
+        }
+    };
+    let manifest = clean_manifest(&object)?;
+    let deleted_at = Utc::now();
+    let file_name: String = format!(
+        "{}-{}-{}",
+        kind,
+        object.metadata.namespace.as_deref().unwrap_or("_"),
+        name
+    )
+    .chars()
+    .map(|c| {
+        if c.is_ascii_alphanumeric() || c == '-' {
+            c
+        } else {
+            '_'
+        }
+    })
+    .collect();
+    let id = format!(
+        "{}-{}",
+        deleted_at.format("%Y%m%d%H%M%S%6f"),
+        file_name.to_lowercase()
+    );
+    let entry = TrashEntry {
+        id: id.clone(),
+        deleted_at,
+        context: context.to_string(),
+        namespace: object.metadata.namespace.clone(),
+        kind: kind.to_string(),
+        api_version: manifest["apiVersion"].as_str().unwrap_or("v1").to_string(),
+        name: name.to_string(),
+        restored_at: None,
+        manifest,
+    };
+
+    let dir = trash_dir()?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let path = dir.join(format!("{}.json", id));
+    write_entry(&path, &entry)?;
+
+    let paths = entry_paths()?;
+    for path in paths
+        .iter()
+        .take(paths.len().saturating_sub(MAX_TRASH_ENTRIES))
+    {
+        let _ = std::fs::remove_file(path);
+    }
+    Ok(Snapshot {
+        path: Some(path),
+        warning: None,
+    })
+}
+
+// drop references to owners that are gone, the garbage collector would delete the
+// restored object right away; the remaining owners may already have recreated it
+async fn check_owners(
+    client: &Client,
+    manifest: &mut Value,
+    namespace: Option<&str>,
+    warnings: &mut Vec<String>,
+) {
+    let mut kept = vec![];
+    for owner in owner_references(manifest) {
+        let kind = owner["kind"].as_str().unwrap_or_default();
+        let name = owner["name"].as_str().unwrap_or_default();
+        let api_version = owner["apiVersion"].as_str().unwrap_or_default();
+        let exists = match resolve_api_resource(client, kind, Some(api_version)).await {
+            Ok((api_resource, namespaced)) => {
+                let api = k8s_client::dynamic_api(
+                    client.clone(),
+                    &api_resource,
+                    namespaced,
+                    namespace.unwrap_or_default(),
+                );
+                match api.get_opt(name).await {
+                    Ok(found) => {
+                        found.and_then(|o| o.metadata.uid)
+                            == owner["uid"].as_str().map(str::to_string)
+                    }
+                    Err(_) => false,
+                }
+            }
+            Err(_) => false,
+        };
+        if exists {
+            warnings.push(format!(
+                "Owner {} {} still exists and may already have replaced the deleted object",
+                kind, name
+            ));
+            kept.push(owner.clone());
+        } else {
+            warnings.push(format!(
+                "Owner {} {} no longer exists, the object is restored without it",
+                kind, name
+            ));
+        }
+    }
+    if let Some(metadata) = manifest["metadata"].as_object_mut() {
+        if kept.is_empty() {
+            metadata.remove("ownerReferences");
+        } else {
+            metadata.insert("ownerReferences".to_string(), Value::Array(kept));
+        }
+    }
+}
+
+// fields the API server allocates or generates, which a create may not reuse
+fn drop_allocated_fields(kind: &str, manifest: &mut Value, warnings: &mut Vec<String>) {
+    match kind {
+        "Service" => {
+            let spec = &mut manifest["spec"];
+            let cluster_ip = spec["clusterIP"].as_str().unwrap_or_default().to_string();
+            if !cluster_ip.is_empty() && cluster_ip != "None" {
+                if let Some(spec) = spec.as_object_mut() {
+                    spec.remove("clusterIP");
+                    spec.remove("clusterIPs");
+                }
+                warnings.push(format!(
+                    "The Service gets a new cluster IP, it had {}",
+                    cluster_ip
+                ));
+            }
+        }
+        "Job" => {
+            // the selector and the labels matching it are generated from the old uid
+            let manual = manifest["spec"]["manualSelector"]
+                .as_bool()
+                .unwrap_or(false);
+            if !manual {
+                if let Some(spec) = manifest["spec"].as_object_mut() {
+                    spec.remove("selector");
+                }
+                if let Some(labels) =
+                    manifest["spec"]["template"]["metadata"]["labels"].as_object_mut()
+                {
+                    for label in [
+                        "controller-uid",
+                        "batch.kubernetes.io/controller-uid",
+                        "job-name",
+                        "batch.kubernetes.io/job-name",
+                    ] {
+                        labels.remove(label);
+                    }
+                }
+                warnings.push("The Job gets a new generated selector and runs again".to_string());
+            }
+        }
+        "PersistentVolumeClaim" => {
+            if let Some(volume) = manifest["spec"]["volumeName"].as_str() {
+                warnings.push(format!(
+                    "The claim asks for volume {}, which stays Released and does not bind until its claimRef is cleared",
+                    volume
+                ));
+            }
+        }
+        "Pod" if manifest["spec"]["nodeName"].is_string() => {
+            warnings.push(format!(
+                "The Pod is pinned to node {} like before",
+                manifest["spec"]["nodeName"].as_str().unwrap_or_default()
+            ));
+        }
+        _ => {}
+    }
+}
+
+// recent deletions, newest first; unreadable entries are skipped
+#[tauri::command]
+pub async fn list_deleted_resources(
+    context: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<DeletedResource>, String> {
+    let mut deleted = vec![];
+    for path in entry_paths()?.iter().rev() {
+        let Ok(entry) = read_entry(path) else {
+            continue;
+        };
+        if context.as_ref().is_some_and(|c| *c != entry.context) {
+            continue;
+        }
+        deleted.push(DeletedResource::from(entry));
+        if deleted.len() >= limit.unwrap_or(DEFAULT_LIST_LIMIT) {
+            break;
+        }
+    }
+    Ok(deleted)
+}
+
+// create a deleted object again from its snapshot
+#[tauri::command]
+pub async fn restore_deleted(
+    kubeconfig_path: String,
+    context: String,
+    id: String,
+    confirmation: Option<String>,
+) -> Result<RestoreResult, String> {
+    let path = trash_dir()?.join(format!("{}.json", id));
+    if id.contains(['/', '\\', '.']) || !path.exists() {
+        return Err(format!("Deleted resource {} not found", id));
+    }
+    let mut entry = read_entry(&path)?;
+
+    let audit = AuditEvent::new(
+        &kubeconfig_path,
+        &context,
+        Action::Restore,
+        entry.namespace.as_deref(),
+        Some(&entry.kind),
+        Some(&entry.name),
+        json!({ "id": id, "deletedFrom": entry.context }),
+    );
+    audit
+        .run(confirmation.as_deref(), async move {
+            let mut warnings = vec![];
+            if entry.context != context {
+                warnings.push(format!(
+                    "The object was deleted from context {}, it is restored into {}",
+                    entry.context, context
+                ));
+            }
+
+            let client = k8s_client::create_k8s_client(kubeconfig_path, context).await?;
+            let (api_resource, namespaced) =
+                resolve_api_resource(&client, &entry.kind, Some(&entry.api_version)).await?;
+            let mut manifest = entry.manifest.clone();
+            check_owners(
+                &client,
+                &mut manifest,
+                entry.namespace.as_deref(),
+                &mut warnings,
+            )
+            .await;
+            drop_allocated_fields(&entry.kind, &mut manifest, &mut warnings);
+
+            let object: DynamicObject =
+                serde_json::from_value(manifest).map_err(|e| e.to_string())?;
+            let api = k8s_client::dynamic_api(
+                client,
+                &api_resource,
+                namespaced,
+                entry.namespace.as_deref().unwrap_or_default(),
+            );
+            api.create(&PostParams::default(), &object)
+                .await
+                .map_err(|e| match e {
+                    kube::Error::Api(response) if response.code == 409 => format!(
+                        "{} {} already exists, delete it first to restore the snapshot",
+                        entry.kind, entry.name
+                    ),
+                    e => format!("Failed to restore {} {}: {}", entry.kind, entry.name, e),
+                })?;
+
+            entry.restored_at = Some(Utc::now());
+            write_entry(&path, &entry)?;
+            Ok(RestoreResult {
+                namespace: entry.namespace,
+                kind: entry.kind,
+                name: entry.name,
+                warnings,
+            })
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(manifest: Value) -> DynamicObject {
+        serde_json::from_value(manifest).unwrap()
+    }
+
+    #[test]
+    fn clean_manifest_drops_server_state() {
+        let manifest = clean_manifest(&object(json!({
+            "apiVersion": "v1",
+            "kind": "ConfigMap",
+            "metadata": {
+                "name": "settings",
+                "namespace": "web",
+                "uid": "0b7f6c1e",
+                "resourceVersion": "4711",
+                "creationTimestamp": "2026-03-01T12:00:00Z",
+                "managedFields": [{ "manager": "kubectl" }],
+                "labels": { "app": "web" },
+                "annotations": {
+                    "kubectl.kubernetes.io/last-applied-configuration": "{}",
+                    "team": "platform"
+                }
+            },
+            "data": { "mode": "production" },
+            "status": { "phase": "Active" }
+        })))
+        .unwrap();
+
+        assert_eq!(
+            manifest,
+            json!({
+                "apiVersion": "v1",
+                "kind": "ConfigMap",
+                "metadata": {
+                    "name": "settings",
+                    "namespace": "web",
+                    "labels": { "app": "web" },
+                    "annotations": { "team": "platform" }
+                },
+                "data": { "mode": "production" }
+            })
+        );
+    }
+
+    #[test]
+    fn services_get_a_new_cluster_ip_unless_headless() {
+        let mut warnings = vec![];
+        let mut service = json!({
+            "spec": { "clusterIP": "10.0.0.12", "clusterIPs": ["10.0.0.12"], "ports": [] }
+        });
+        drop_allocated_fields("Service", &mut service, &mut warnings);
+        assert_eq!(service, json!({ "spec": { "ports": [] } }));
+        assert_eq!(warnings.len(), 1);
+
+        let mut warnings = vec![];
+        let mut headless = json!({ "spec": { "clusterIP": "None" } });
+        drop_allocated_fields("Service", &mut headless, &mut warnings);
+        assert_eq!(headless, json!({ "spec": { "clusterIP": "None" } }));
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn jobs_drop_their_generated_selector() {
+        let job = |manual: bool| {
+            json!({
+                "spec": {
+                    "manualSelector": manual,
+                    "selector": { "matchLabels": { "batch.kubernetes.io/controller-uid": "0b7f" } },
+                    "template": { "metadata": { "labels": {
+                        "app": "migrate",
+                        "batch.kubernetes.io/controller-uid": "0b7f",
+                        "batch.kubernetes.io/job-name": "migrate",
+                        "controller-uid": "0b7f",
+                        "job-name": "migrate"
+                    } } }
+                }
+            })
+        };
+
+        let mut warnings = vec![];
+        let mut generated = job(false);
+        drop_allocated_fields("Job", &mut generated, &mut warnings);
+        assert!(generated["spec"]["selector"].is_null());
+        assert_eq!(
+            generated["spec"]["template"]["metadata"]["labels"],
+            json!({ "app": "migrate" })
+        );
+        assert_eq!(warnings.len(), 1);
+
+        let mut warnings = vec![];
+        let mut manual = job(true);
+        drop_allocated_fields("Job", &mut manual, &mut warnings);
+        assert_eq!(manual, job(true));
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn bound_claims_warn_about_their_volume() {
+        let mut warnings = vec![];
+        let mut claim = json!({ "spec": { "volumeName": "pvc-0b7f" } });
+        drop_allocated_fields("PersistentVolumeClaim", &mut claim, &mut warnings);
+        assert_eq!(claim, json!({ "spec": { "volumeName": "pvc-0b7f" } }));
+        assert!(warnings[0].contains("pvc-0b7f"));
+    }
+
+    #[test]
+    fn snapshots_of_failed_deletes_are_dropped() {
+        let dir = std::env::temp_dir().join(format!("trash-snapshot-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let snapshot = |name: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, "{}").unwrap();
+            Snapshot {
+                path: Some(path),
+                warning: None,
+            }
+        };
+
+        let failed: Result<(), String> = Err("admission webhook denied the request".into());
+        assert_eq!(snapshot("failed.json").finish(&failed), None);
+        assert!(!dir.join("failed.json").exists());
+        assert_eq!(snapshot("deleted.json").finish(&Ok::<_, String>(())), None);
+        assert!(dir.join("deleted.json").exists());
+
+        let unreadable = Snapshot {
+            path: None,
+            warning: Some("not undoable".into()),
+        };
+        assert_eq!(
+            unreadable.finish(&Ok::<_, String>(())).as_deref(),
+            Some("not undoable")
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn listing_skips_unreadable_entries() {
+        app_config::init_for_tests();
+        let trash = trash_dir().unwrap();
+        std::fs::create_dir_all(&trash).unwrap();
+
+        let entry = TrashEntry {
+            id: "20260301120000000000-configmap-web-settings".to_string(),
+            deleted_at: Utc::now(),
+            context: "prod".to_string(),
+            namespace: Some("web".to_string()),
+            kind: "ConfigMap".to_string(),
+            api_version: "v1".to_string(),
+            name: "settings".to_string(),
+            restored_at: None,
+            manifest: json!({}),
+        };
+        let path = trash.join(format!("{}.json", entry.id));
+        write_entry(&path, &entry).unwrap();
+        std::fs::write(trash.join("20260301130000000000-broken.json"), "{").unwrap();
+
+        let deleted = list_deleted_resources(None, None).await.unwrap();
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(deleted[0].name, "settings");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        std::fs::remove_dir_all(&trash).unwrap();
+    }
+}